@@ -1,7 +1,28 @@
 #version 450
-#define SHADOW_MAP_CASCADE_COUNT 4
-#define DEBUG true
-#define USE_PCF true
+// Must match MAX_CASCADE_COUNT in render_target.rs
+#define MAX_CASCADE_COUNT 4
+
+// Must match ShadowFilter in filtering.rs
+#define SHADOW_FILTER_HARD 0u
+#define SHADOW_FILTER_PCF 1u
+#define SHADOW_FILTER_POISSON 2u
+#define SHADOW_FILTER_PCSS 3u
+#define SHADOW_FILTER_EVSM 4u
+
+// Must match evsm_moments.comp
+#define EVSM_POSITIVE_EXPONENT 40.0
+#define EVSM_NEGATIVE_EXPONENT 5.0
+
+// PCSS blocker search and penumbra are clamped to this radius (texels)
+#define PCSS_MAX_RADIUS 32.0
+
+// Must match TILE_SIZE and MAX_LIGHTS_PER_TILE in light_culling.rs
+#define TILE_SIZE 16
+#define MAX_LIGHTS_PER_TILE 255
+
+#define LIGHT_TYPE_POINT 0
+#define LIGHT_TYPE_SPOT 1
+#define LIGHT_TYPE_DIRECTIONAL 2
 
 // The `color_input` parameter of the `draw` method.
 layout(set = 0, binding = 0) uniform sampler2DMS samplerAlbedo;
@@ -13,11 +34,76 @@ layout(set = 0, binding = 3) uniform sampler2DArray shadowMap;
 layout(binding = 4) uniform UniformBufferObject {
     vec4 cascadeSplits;
     mat4 view;
-    mat4 cascadeVP[SHADOW_MAP_CASCADE_COUNT];
+    mat4 cascadeVP[MAX_CASCADE_COUNT];
+    // xyz: direction sun light travels in
+    vec4 sunDirection;
+    // rgb: color * intensity
+    vec4 sunRadiance;
+    uint ssaoDebug;
+    uint lightHeatmap;
+    // tint sun light by shadow cascade
+    uint debugCascades;
+    // fraction of cascade depth range blended with next cascade
+    float cascadeBlend;
+    // depth offset against shadow acne
+    float shadowBias;
+    // PCF and Poisson PCF radius in shadow map texels
+    float shadowFilterRadius;
+    // PCSS: tangent of light angular radius
+    float shadowLightSize;
+    // EVSM: part of Chebyshev bound cut off against light bleeding
+    float evsmBleedReduction;
 } ubo;
 
+// blurred ambient occlusion, 1.0 = not occluded
+layout(set = 0, binding = 5) uniform sampler2D ssaoImage;
+
+// r: roughness, g: metallic, b: baked occlusion
+layout(set = 0, binding = 6) uniform sampler2DMS samplerMaterial;
+layout(set = 0, binding = 7) uniform sampler2DMS samplerEmissive;
+
+struct Light {
+    vec4 positionRange;
+    vec4 colorIntensity;
+    // xyz: direction light travels in
+    vec4 directionType;
+    // x: cos(outer angle), y: cos(inner angle), z: first shadow atlas view (-1: no shadow)
+    vec4 spotCone;
+};
+
+layout(set = 0, binding = 8) readonly buffer LightBuffer {
+    uint lightCount;
+    Light lights[];
+} lightBuffer;
+
+// Output of light_culling.comp: per tile light count followed by light indices
+layout(set = 0, binding = 9) readonly buffer TileLights {
+    uint data[];
+} tileLights;
+
+// Same image as shadowMap with comparison sampler (bilinear filtered depth test)
+layout(set = 0, binding = 10) uniform sampler2DArrayShadow shadowMapCompare;
+// EVSM moments, bound only when SHADOW_FILTER == SHADOW_FILTER_EVSM (shadow map otherwise)
+layout(set = 0, binding = 11) uniform sampler2DArray shadowMoments;
+
+// Shadow maps of spot and point lights (atlas.rs), comparison sampler
+layout(set = 0, binding = 12) uniform sampler2DShadow shadowAtlas;
+
+struct ShadowView {
+    mat4 viewProj;
+    // xy: tile offset, zw: tile size (atlas uv)
+    vec4 atlasRect;
+};
+
+// Point light has 6 consecutive views: +X, -X, +Y, -Y, +Z, -Z
+layout(set = 0, binding = 13) readonly buffer ShadowViews {
+    ShadowView views[];
+} shadowViews;
+
 layout(location = 0) out vec4 outFragcolor;
 layout(constant_id = 0) const int NUM_SAMPLES = 2;
+layout(constant_id = 1) const uint SHADOW_MAP_CASCADE_COUNT = 4u;
+layout(constant_id = 2) const uint SHADOW_FILTER = 1u;
 
 layout (location = 0) in vec2 inUV;
 
@@ -42,63 +128,321 @@ vec4 resolve(sampler2DMS tex, ivec2 uv)
 }
 
 
-float textureProj(vec4 posInLightView, vec2 offset, uint cascadeIndex) {
-    float shadow = 1.0;
-    float bias = 0.005;
-    float ambient = 0.3;
+const float PI = 3.14159265359;
+
+const vec3 AMBIENT_LIGHT = vec3(0.4);
+
+// Trowbridge-Reitz GGX normal distribution
+float distributionGGX(float NdotH, float roughness)
+{
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denom = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+// Smith with Schlick-GGX, k for direct lighting
+float geometrySmith(float NdotV, float NdotL, float roughness)
+{
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    float ggxV = NdotV / (NdotV * (1.0 - k) + k);
+    float ggxL = NdotL / (NdotL * (1.0 - k) + k);
+    return ggxV * ggxL;
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 F0)
+{
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+// Direct light, Cook-Torrance specular + Lambert diffuse. L points towards light.
+vec3 calculateLighting(vec3 N, vec3 V, vec3 L, vec3 radiance, vec3 albedo, float roughness, float metallic)
+{
+    vec3 H = normalize(V + L);
+
+    float NdotL = max(dot(N, L), 0.0);
+    float NdotV = max(dot(N, V), 1e-4);
+    float NdotH = max(dot(N, H), 0.0);
+
+    // too smooth surfaces give a sub-pixel highlight that aliases
+    roughness = max(roughness, 0.04);
+
+    vec3 F0 = mix(vec3(0.04), albedo, metallic);
+    vec3 F = fresnelSchlick(max(dot(H, V), 0.0), F0);
+    float D = distributionGGX(NdotH, roughness);
+    float G = geometrySmith(NdotV, NdotL, roughness);
+
+    vec3 specular = D * G * F / (4.0 * NdotV * NdotL + 1e-4);
+    vec3 kD = (vec3(1.0) - F) * (1.0 - metallic);
+
+    return (kD * albedo / PI + specular) * radiance * NdotL;
+}
+
+// Inverse square falloff, smoothly windowed to zero at light range
+float distanceAttenuation(float dist, float range)
+{
+    float ratio = dist / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / max(dist * dist, 0.01);
+}
 
-    if (posInLightView.z > -1.0 && posInLightView.z < 1.0) {
-        float dist = texture(shadowMap, vec3(posInLightView.st + offset, cascadeIndex)).r;
+// Shadow of spot or point light from shadow atlas, 2x2 bilinear compare taps
+float localLightShadow(Light light, int lightType, vec3 pos, vec3 N)
+{
+    int viewIndex = int(light.spotCone.z);
+    if (viewIndex < 0) {
+        return 1.0;
+    }
 
-        if (posInLightView.w > 0 && dist < posInLightView.z - bias) {
-            shadow = ambient;
+    vec3 fromLight = pos - light.positionRange.xyz;
+    if (lightType == LIGHT_TYPE_POINT) {
+        vec3 absDir = abs(fromLight);
+        if (absDir.x >= absDir.y && absDir.x >= absDir.z) {
+            viewIndex += fromLight.x > 0.0 ? 0 : 1;
+        } else if (absDir.y >= absDir.z) {
+            viewIndex += fromLight.y > 0.0 ? 2 : 3;
+        } else {
+            viewIndex += fromLight.z > 0.0 ? 4 : 5;
         }
     }
-    return shadow;
 
-}
+    ShadowView view = shadowViews.views[viewIndex];
+
+    // normal offset grows with distance, texels of far surfaces are bigger
+    vec3 offsetPos = pos + N * 0.02 * length(fromLight);
+    vec4 clip = view.viewProj * vec4(offsetPos, 1.0);
+    if (clip.w <= 0.0) {
+        return 1.0;
+    }
+
+    vec3 ndc = clip.xyz / clip.w;
+    vec2 tileUV = ndc.xy * 0.5 + 0.5;
 
+    // don't sample neighbour tiles
+    vec2 halfTexel = vec2(0.5 / textureSize(shadowAtlas, 0));
+    vec2 uv = clamp(view.atlasRect.xy + tileUV * view.atlasRect.zw,
+                    view.atlasRect.xy + halfTexel, view.atlasRect.xy + view.atlasRect.zw - halfTexel);
 
-vec3 calculateLighting(vec3 pos, vec3 normal, vec4 albedo)
+    return texture(shadowAtlas, vec3(uv, ndc.z - 0.0005));
+}
+
+// Lights of the screen tile, spot and point lights may be shadowed from atlas
+vec3 calculateTileLights(uint tileOffset, vec3 pos, vec3 N, vec3 V, vec3 albedo, float roughness, float metallic)
 {
-    if (normal == vec3(0.0)) {
-        return albedo.rgb;
+    vec3 result = vec3(0.0);
+
+    uint count = tileLights.data[tileOffset];
+    for (uint i = 0u; i < count; i++) {
+        Light light = lightBuffer.lights[tileLights.data[tileOffset + 1u + i]];
+        int lightType = int(light.directionType.w + 0.5);
+
+        vec3 radiance = light.colorIntensity.rgb * light.colorIntensity.a;
+        vec3 L;
+
+        if (lightType == LIGHT_TYPE_DIRECTIONAL) {
+            L = -normalize(light.directionType.xyz);
+        } else {
+            vec3 toLight = light.positionRange.xyz - pos;
+            float dist = length(toLight);
+            L = toLight / max(dist, 1e-4);
+
+            radiance *= distanceAttenuation(dist, light.positionRange.w);
+
+            if (lightType == LIGHT_TYPE_SPOT) {
+                float cosAngle = dot(-L, normalize(light.directionType.xyz));
+                radiance *= smoothstep(light.spotCone.x, light.spotCone.y, cosAngle);
+            }
+
+            radiance *= localLightShadow(light, lightType, pos, N);
+        }
+
+        result += calculateLighting(N, V, L, radiance, albedo, roughness, metallic);
     }
-    float light_percent = dot(vec3(0.7, 0.25, -0.67), normal);
-    light_percent = max(light_percent, 0.0);
 
-    return albedo.rgb * 1.5 * light_percent;
+    return result;
 }
 
-float filterPCF(vec4 posInLightView, uint cascadeIndex)
+// Blue (no lights) -> green -> red (MAX_LIGHTS_PER_TILE / 8 and more)
+vec3 heatmapColor(uint count)
 {
-    ivec2 texDim = textureSize(shadowMap, 0).xy;
-    float scale = 0.75;
-    float dx = scale * 1.0 / float(texDim.x);
-    float dy = scale * 1.0 / float(texDim.y);
+    float t = clamp(float(count) / float(MAX_LIGHTS_PER_TILE / 8), 0.0, 1.0);
+    return t < 0.5 ? mix(vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 0.0), t * 2.0) : mix(vec3(0.0, 1.0, 0.0), vec3(1.0, 0.0, 0.0), t * 2.0 - 1.0);
+}
 
-    float shadowFactor = 0.0;
-    int count = 0;
-    int range = 2;
+// Light that reaches shadowed surfaces
+const float SHADOW_AMBIENT = 0.3;
+
+const vec2 POISSON_DISK[16] = vec2[](
+    vec2(-0.94201624, -0.39906216), vec2(0.94558609, -0.76890725),
+    vec2(-0.09418410, -0.92938870), vec2(0.34495938, 0.29387760),
+    vec2(-0.91588581, 0.45771432), vec2(-0.81544232, -0.87912464),
+    vec2(-0.38277543, 0.27676845), vec2(0.97484398, 0.75648379),
+    vec2(0.44323325, -0.97511554), vec2(0.53742981, -0.47373420),
+    vec2(-0.26496911, -0.41893023), vec2(0.79197514, 0.19090188),
+    vec2(-0.24188840, 0.99706507), vec2(-0.81409955, 0.91437590),
+    vec2(0.19984126, 0.78641367), vec2(0.14383161, -0.14100790)
+);
+
+// Cascades are square
+float shadowTexelSize()
+{
+    return 1.0 / float(textureSize(shadowMap, 0).x);
+}
+
+// Random rotation of Poisson disk per pixel (interleaved gradient noise), turns banding into noise
+mat2 poissonRotation()
+{
+    float noise = fract(52.9829189 * fract(dot(gl_FragCoord.xy, vec2(0.06711056, 0.00583715))));
+    float angle = noise * 2.0 * PI;
+    float s = sin(angle);
+    float c = cos(angle);
+    return mat2(c, s, -s, c);
+}
 
-    for (int x = -range; x <= range; x++) {
-        for (int y = -range; y <= range; y++) {
-            shadowFactor += textureProj(posInLightView, vec2(dx*x, dy*y), cascadeIndex);
-            count++;
+// All filters return 1.0 for lit and 0.0 for shadowed surface.
+// coord: xy - shadow map uv, z - depth in light clip space
+
+float shadowHard(vec3 coord, uint cascadeIndex)
+{
+    float dist = texture(shadowMap, vec3(coord.xy, cascadeIndex)).r;
+    return dist < coord.z - ubo.shadowBias ? 0.0 : 1.0;
+}
+
+// Hardware depth test of 4 nearest texels with bilinear weights
+float shadowCompare(vec2 uv, float depth, uint cascadeIndex)
+{
+    return texture(shadowMapCompare, vec4(uv, cascadeIndex, depth - ubo.shadowBias));
+}
+
+float filterPCF(vec3 coord, uint cascadeIndex)
+{
+    float step = shadowTexelSize() * ubo.shadowFilterRadius / 2.0;
+
+    float visibility = 0.0;
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            visibility += shadowCompare(coord.xy + vec2(x, y) * step, coord.z, cascadeIndex);
         }
     }
-    return shadowFactor / count;
+    return visibility / 25.0;
+}
+
+// radius in shadow map uv
+float filterPoisson(vec3 coord, uint cascadeIndex, float radius)
+{
+    mat2 rotation = poissonRotation();
+
+    float visibility = 0.0;
+    for (int i = 0; i < 16; i++) {
+        visibility += shadowCompare(coord.xy + rotation * POISSON_DISK[i] * radius, coord.z, cascadeIndex);
+    }
+    return visibility / 16.0;
 }
 
+// Percentage-closer soft shadows: average blocker depth -> penumbra width -> Poisson PCF.
+// Cascade projection is orthographic with depth range equal to its width, so penumbra width in uv
+// is depth difference * light size for every cascade.
+float filterPCSS(vec3 coord, uint cascadeIndex)
+{
+    float texelSize = shadowTexelSize();
+    float maxRadius = PCSS_MAX_RADIUS * texelSize;
+    mat2 rotation = poissonRotation();
+
+    // blocker can't cast wider penumbra than one at light clip plane
+    float searchRadius = clamp(ubo.shadowLightSize * coord.z, texelSize, maxRadius);
+
+    float blockerDepth = 0.0;
+    int blockerCount = 0;
+    for (int i = 0; i < 16; i++) {
+        float depth = texture(shadowMap, vec3(coord.xy + rotation * POISSON_DISK[i] * searchRadius, cascadeIndex)).r;
+        if (depth < coord.z - ubo.shadowBias) {
+            blockerDepth += depth;
+            blockerCount++;
+        }
+    }
+
+    if (blockerCount == 0) {
+        return 1.0;
+    }
+
+    blockerDepth /= float(blockerCount);
+    float penumbra = clamp((coord.z - blockerDepth) * ubo.shadowLightSize, texelSize, maxRadius);
+
+    return filterPoisson(coord, cascadeIndex, penumbra);
+}
+
+// Upper bound of fraction of occluders farther than `mean`
+float chebyshevUpperBound(vec2 moments, float mean, float minVariance)
+{
+    float variance = max(moments.y - moments.x * moments.x, minVariance);
+    float d = mean - moments.x;
+    float pMax = variance / (variance + d * d);
+
+    // cut the tail of distribution against light bleeding
+    pMax = clamp((pMax - ubo.evsmBleedReduction) / (1.0 - ubo.evsmBleedReduction), 0.0, 1.0);
+
+    return mean <= moments.x ? 1.0 : pMax;
+}
+
+float filterEVSM(vec3 coord, uint cascadeIndex)
+{
+    vec4 moments = texture(shadowMoments, vec3(coord.xy, cascadeIndex));
+
+    float depth = coord.z * 2.0 - 1.0;
+    vec2 exponents = vec2(EVSM_POSITIVE_EXPONENT, EVSM_NEGATIVE_EXPONENT);
+    vec2 warpedDepth = vec2(exp(exponents.x * depth), -exp(-exponents.y * depth));
+
+    // derivative of warp scales depth bias to warped space
+    vec2 depthScale = ubo.shadowBias * 0.2 * exponents * warpedDepth;
+    vec2 minVariance = depthScale * depthScale;
+
+    float positive = chebyshevUpperBound(moments.xy, warpedDepth.x, minVariance.x);
+    float negative = chebyshevUpperBound(moments.zw, warpedDepth.y, minVariance.y);
+
+    return min(positive, negative);
+}
+
+float sampleShadow(vec3 pos, uint cascadeIndex)
+{
+    vec4 posInLightView = (biasMat * ubo.cascadeVP[cascadeIndex]) * vec4(pos, 1.0);
+    vec3 coord = posInLightView.xyz / posInLightView.w;
+
+    // out of cascade depth range (Vulkan depth is [0, 1])
+    if (coord.z < 0.0 || coord.z >= 1.0) {
+        return 1.0;
+    }
+
+    float visibility;
+    if (SHADOW_FILTER == SHADOW_FILTER_HARD) {
+        visibility = shadowHard(coord, cascadeIndex);
+    } else if (SHADOW_FILTER == SHADOW_FILTER_PCF) {
+        visibility = filterPCF(coord, cascadeIndex);
+    } else if (SHADOW_FILTER == SHADOW_FILTER_POISSON) {
+        visibility = filterPoisson(coord, cascadeIndex, shadowTexelSize() * ubo.shadowFilterRadius);
+    } else if (SHADOW_FILTER == SHADOW_FILTER_PCSS) {
+        visibility = filterPCSS(coord, cascadeIndex);
+    } else {
+        visibility = filterEVSM(coord, cascadeIndex);
+    }
+
+    return mix(SHADOW_AMBIENT, 1.0, visibility);
+}
 
 void main() {
     ivec2 attDim = textureSize(samplerAlbedo);
     ivec2 UV = ivec2(inUV * attDim);
 
-    // Ambient part
-    vec4 alb = resolve(samplerAlbedo, UV);
+    // camera position from view matrix (rotation is orthonormal)
+    vec3 cameraPos = -transpose(mat3(ubo.view)) * ubo.view[3].xyz;
+
+    float ssao = texture(ssaoImage, inUV).r;
+
+    uint tileCountX = uint(attDim.x + TILE_SIZE - 1) / uint(TILE_SIZE);
+    uvec2 tile = uvec2(gl_FragCoord.xy) / uint(TILE_SIZE);
+    uint tileOffset = (tile.y * tileCountX + tile.x) * uint(MAX_LIGHTS_PER_TILE + 1);
+
     vec3 fragColor = vec3(0.0);
-    float shadow = 0.0;
 
     // Calualte lighting for every MSAA sample
     for (int i = 0; i < NUM_SAMPLES; i++)
@@ -106,9 +450,22 @@ void main() {
         vec3 pos = texelFetch(samplerPosition, UV, i).rgb;
 
         vec3 normal = texelFetch(samplerNormal, UV, i).rgb;
-        vec4 albedo = texelFetch(samplerAlbedo, UV, i);
+        vec3 albedo = texelFetch(samplerAlbedo, UV, i).rgb;
+        vec3 material = texelFetch(samplerMaterial, UV, i).rgb;
+        vec3 emissive = texelFetch(samplerEmissive, UV, i).rgb;
+
+        if (normal == vec3(0.0)) {
+            // sky: unlit
+            fragColor += albedo;
+            continue;
+        }
+
+        vec3 ambient = albedo * AMBIENT_LIGHT * material.b * ssao;
 
-        vec3 outSampleColor = calculateLighting(pos, normal, albedo);
+        vec3 N = normalize(normal);
+        vec3 V = normalize(cameraPos - pos);
+        vec3 outSampleColor = calculateLighting(N, V, -normalize(ubo.sunDirection.xyz), ubo.sunRadiance.rgb, albedo, material.r, material.g);
+        vec3 tileLightsColor = calculateTileLights(tileOffset, pos, N, V, albedo, material.r, material.g);
 
         vec3 view_pos = (ubo.view * vec4(pos, 1.0)).xyz;
 
@@ -120,7 +477,7 @@ void main() {
             }
         }
 
-        if (DEBUG) {
+        if (ubo.debugCascades == 1u) {
             switch (shadowCascadeIndex) {
                 case 0 :
                 outSampleColor.rgb *= vec3(1.0f, 0.25f, 0.25f);
@@ -136,20 +493,33 @@ void main() {
                 break;
             }
         }
-        fragColor += outSampleColor;
+        float shadow = sampleShadow(pos, shadowCascadeIndex);
+
+        // Blend with next cascade near the split to hide the seam
+        if (shadowCascadeIndex < SHADOW_MAP_CASCADE_COUNT - 1) {
+            float splitNear = shadowCascadeIndex == 0 ? 0.0 : ubo.cascadeSplits[shadowCascadeIndex - 1];
+            float splitFar = ubo.cascadeSplits[shadowCascadeIndex];
+            // 0 at far split, 1 at near split
+            float fade = (view_pos.z - splitFar) / (splitNear - splitFar);
+
+            if (fade < ubo.cascadeBlend) {
+                float nextShadow = sampleShadow(pos, shadowCascadeIndex + 1);
+                shadow = mix(nextShadow, shadow, fade / ubo.cascadeBlend);
+            }
+        }
 
-        vec4 posInLightView = (biasMat * ubo.cascadeVP[shadowCascadeIndex]) * vec4(pos, 1.0);
-        posInLightView /= posInLightView.w;
+        fragColor += (ambient + outSampleColor) * shadow + tileLightsColor + emissive;
+    }
 
-        if (USE_PCF) {
-            shadow += filterPCF(posInLightView, shadowCascadeIndex);
-        } else {
-            shadow += textureProj(posInLightView, vec2(0.0), shadowCascadeIndex);
-        }
+    if (ubo.lightHeatmap == 1u) {
+        outFragcolor = vec4(heatmapColor(tileLights.data[tileOffset]), 1.0);
+        return;
     }
 
-    shadow /= NUM_SAMPLES;
-    fragColor = (alb.rgb * vec3(0.4)) + fragColor / float(NUM_SAMPLES);
+    if (ubo.ssaoDebug == 1u) {
+        outFragcolor = vec4(vec3(ssao), 1.0);
+        return;
+    }
 
-    outFragcolor = vec4(fragColor, 1.0) * shadow;
+    outFragcolor = vec4(fragColor / float(NUM_SAMPLES), 1.0);
 }
@@ -0,0 +1,21 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec3 inPosition;
+layout(location = 1) in vec3 inNormal;
+layout(location = 2) in vec2 inTexCoord;
+
+// terrain vertices are in world space, one draw per chunk
+layout(push_constant) uniform Params {
+    mat4 lightViewProj;
+} params;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+layout(location = 0) out vec2 outUV;
+
+void main() {
+    outUV = inTexCoord;
+    gl_Position = params.lightViewProj * vec4(inPosition, 1.0);
+}
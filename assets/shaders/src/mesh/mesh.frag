@@ -4,6 +4,14 @@
 
 layout(binding = 1) uniform sampler2D texSampler;
 
+layout(binding = 2) uniform Material {
+    vec4 baseColor;
+    vec4 emissive;
+    float roughness;
+    float metallic;
+    float occlusion;
+} material;
+
 layout(location = 0) in vec3 fragColor;
 layout(location = 1) in vec2 fragTexCoord;
 layout(location = 2) in vec4 fragPosition;
@@ -12,9 +20,13 @@ layout(location = 3) in vec3 fragNormal;
 layout(location = 0) out vec4 outColor;
 layout(location = 1) out vec4 outPosition;
 layout(location = 2) out vec4 outNormal;
+layout(location = 3) out vec4 outMaterial;
+layout(location = 4) out vec4 outEmissive;
 
 void main() {
-    outColor = texture(texSampler, fragTexCoord); // fragPosition; //vec4(fragNormal, 1.0); //texture(texSampler, fragTexCoord);
+    outColor = texture(texSampler, fragTexCoord) * material.baseColor;
     outPosition = fragPosition;
-    outNormal = vec4(fragNormal, 1.0);
+    outNormal = vec4(normalize(fragNormal), 1.0);
+    outMaterial = vec4(material.roughness, material.metallic, material.occlusion, 1.0);
+    outEmissive = vec4(material.emissive.rgb, 1.0);
 }
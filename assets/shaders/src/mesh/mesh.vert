@@ -27,5 +27,6 @@ void main() {
     fragTexCoord = inTexCoord;
 
     fragWorldPosition = ubo.model * vec4(inPosition, 1.0);
-    fragNormal = inNormal;
+    // model has non-uniform scale in general
+    fragNormal = mat3(transpose(inverse(ubo.model))) * inNormal;
 }
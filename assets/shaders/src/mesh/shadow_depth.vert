@@ -6,11 +6,10 @@ layout (location = 1) in vec2 inColor;
 layout (location = 2) in vec2 inUV;
 layout (location = 3) in vec2 inNormal;
 
-#define SHADOW_MAP_CASCADE_COUNT 4
-
-layout(binding = 0) uniform UniformBufferObject {
-    mat4[SHADOW_MAP_CASCADE_COUNT] cascadeViewProjMat;
-} ubo;
+// light world-view-projection, one draw per cascade or atlas tile
+layout(push_constant) uniform Params {
+    mat4 lightWorldViewProj;
+} params;
 
 out gl_PerVertex {
     vec4 gl_Position;
@@ -19,5 +18,5 @@ layout (location = 0) out vec2 outUV;
 
 void main() {
 	outUV = inUV;
-	gl_Position =  ubo.cascadeViewProjMat[0] * vec4(inPos, 1.0);
+	gl_Position = params.lightWorldViewProj * vec4(inPos, 1.0);
 }
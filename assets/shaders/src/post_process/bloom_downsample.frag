@@ -0,0 +1,89 @@
+#version 450
+
+// Jimenez 2014, "Next Generation Post Processing in Call of Duty: Advanced Warfare"
+
+layout(set = 0, binding = 0) uniform sampler2D srcImage;
+
+layout(push_constant) uniform Params {
+    vec2 srcTexelSize;
+    float threshold;
+    float knee;
+} params;
+
+// 1 for the first pass (HDR image -> mip 0): Karis average + soft threshold
+layout(constant_id = 0) const uint PREFILTER = 0u;
+
+layout(location = 0) in vec2 inUV;
+layout(location = 0) out vec4 outColor;
+
+
+float luminance(vec3 color)
+{
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
+
+// weights groups by inverse luminance: single very bright pixels (fireflies) don't flicker
+float karisWeight(vec3 color)
+{
+    return 1.0 / (1.0 + luminance(color));
+}
+
+vec3 softThreshold(vec3 color)
+{
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 1e-4);
+    float contribution = max(soft, brightness - params.threshold) / max(brightness, 1e-4);
+    return color * contribution;
+}
+
+
+void main() {
+    vec2 t = params.srcTexelSize;
+
+    // 13 bilinear taps:
+    // a . b . c
+    // . j . k .
+    // d . e . f
+    // . l . m .
+    // g . h . i
+    vec3 a = texture(srcImage, inUV + t * vec2(-2.0, -2.0)).rgb;
+    vec3 b = texture(srcImage, inUV + t * vec2( 0.0, -2.0)).rgb;
+    vec3 c = texture(srcImage, inUV + t * vec2( 2.0, -2.0)).rgb;
+    vec3 d = texture(srcImage, inUV + t * vec2(-2.0,  0.0)).rgb;
+    vec3 e = texture(srcImage, inUV).rgb;
+    vec3 f = texture(srcImage, inUV + t * vec2( 2.0,  0.0)).rgb;
+    vec3 g = texture(srcImage, inUV + t * vec2(-2.0,  2.0)).rgb;
+    vec3 h = texture(srcImage, inUV + t * vec2( 0.0,  2.0)).rgb;
+    vec3 i = texture(srcImage, inUV + t * vec2( 2.0,  2.0)).rgb;
+    vec3 j = texture(srcImage, inUV + t * vec2(-1.0, -1.0)).rgb;
+    vec3 k = texture(srcImage, inUV + t * vec2( 1.0, -1.0)).rgb;
+    vec3 l = texture(srcImage, inUV + t * vec2(-1.0,  1.0)).rgb;
+    vec3 m = texture(srcImage, inUV + t * vec2( 1.0,  1.0)).rgb;
+
+    vec3 groups[5] = vec3[](
+        (j + k + l + m) * 0.25,
+        (a + b + d + e) * 0.25,
+        (b + c + e + f) * 0.25,
+        (d + e + g + h) * 0.25,
+        (e + f + h + i) * 0.25
+    );
+    float groupWeights[5] = float[](0.5, 0.125, 0.125, 0.125, 0.125);
+
+    vec3 color = vec3(0.0);
+    if (PREFILTER == 1u) {
+        float weightSum = 0.0;
+        for (int n = 0; n < 5; n++) {
+            float w = groupWeights[n] * karisWeight(groups[n]);
+            color += groups[n] * w;
+            weightSum += w;
+        }
+        color = softThreshold(color / weightSum);
+    } else {
+        for (int n = 0; n < 5; n++) {
+            color += groups[n] * groupWeights[n];
+        }
+    }
+
+    outColor = vec4(max(color, 0.0), 1.0);
+}
@@ -0,0 +1,32 @@
+#version 450
+
+// 3x3 tent filter, result is added to the next larger mip (additive blending)
+
+layout(set = 0, binding = 0) uniform sampler2D srcImage;
+
+layout(push_constant) uniform Params {
+    vec2 srcTexelSize;
+    float radius;
+} params;
+
+layout(location = 0) in vec2 inUV;
+layout(location = 0) out vec4 outColor;
+
+
+void main() {
+    vec2 t = params.srcTexelSize * params.radius;
+
+    vec3 color = texture(srcImage, inUV).rgb * 4.0;
+
+    color += texture(srcImage, inUV + vec2(-t.x, 0.0)).rgb * 2.0;
+    color += texture(srcImage, inUV + vec2( t.x, 0.0)).rgb * 2.0;
+    color += texture(srcImage, inUV + vec2(0.0, -t.y)).rgb * 2.0;
+    color += texture(srcImage, inUV + vec2(0.0,  t.y)).rgb * 2.0;
+
+    color += texture(srcImage, inUV + vec2(-t.x, -t.y)).rgb;
+    color += texture(srcImage, inUV + vec2( t.x, -t.y)).rgb;
+    color += texture(srcImage, inUV + vec2(-t.x,  t.y)).rgb;
+    color += texture(srcImage, inUV + vec2( t.x,  t.y)).rgb;
+
+    outColor = vec4(color / 16.0, 0.0);
+}
@@ -0,0 +1,83 @@
+#version 450
+
+// Hemisphere SSAO (Crytek / LearnOpenGL style) in view space
+
+#define MAX_KERNEL_SIZE 64
+
+layout(set = 0, binding = 0) uniform sampler2DMS samplerPosition;
+layout(set = 0, binding = 1) uniform sampler2DMS samplerNormal;
+
+// 4x4 random rotations around normal, tiled over the screen
+layout(set = 0, binding = 2) uniform sampler2D noiseImage;
+
+layout(set = 0, binding = 3) uniform SsaoParams {
+    mat4 view;
+    mat4 proj;
+    vec4 kernel[MAX_KERNEL_SIZE];
+    float radius;
+    float bias;
+    float power;
+    uint sampleCount;
+} params;
+
+layout(location = 0) in vec2 inUV;
+// r: ambient occlusion, g: linear depth (for bilateral blur)
+layout(location = 0) out vec2 outAo;
+
+const float FAR_DEPTH = 10000.0;
+
+
+void main() {
+    ivec2 dim = textureSize(samplerPosition);
+    ivec2 coord = ivec2(inUV * dim);
+
+    // G-buffer position and normal are in world space (mesh.vert, terrain.vert), occlusion is computed
+    // in view space. Sample 0 is enough for occlusion
+    vec3 normal = texelFetch(samplerNormal, coord, 0).xyz;
+    if (normal == vec3(0.0)) {
+        // sky
+        outAo = vec2(1.0, FAR_DEPTH);
+        return;
+    }
+
+    vec3 fragPos = (params.view * vec4(texelFetch(samplerPosition, coord, 0).xyz, 1.0)).xyz;
+    normal = normalize(mat3(params.view) * normal);
+
+    // back faces (two sided geometry), hemisphere must face the camera
+    if (dot(normal, -fragPos) < 0.0) {
+        normal = -normal;
+    }
+
+    if (params.sampleCount == 0u) {
+        outAo = vec2(1.0, -fragPos.z);
+        return;
+    }
+
+    vec2 noiseScale = vec2(dim) / vec2(textureSize(noiseImage, 0));
+    vec3 randomVec = vec3(texture(noiseImage, inUV * noiseScale).xy * 2.0 - 1.0, 0.0);
+
+    vec3 tangent = normalize(randomVec - normal * dot(randomVec, normal));
+    vec3 bitangent = cross(normal, tangent);
+    mat3 TBN = mat3(tangent, bitangent, normal);
+
+    float occlusion = 0.0;
+    for (uint i = 0u; i < params.sampleCount; i++) {
+        vec3 samplePos = fragPos + TBN * params.kernel[i].xyz * params.radius;
+
+        vec4 offset = params.proj * vec4(samplePos, 1.0);
+        vec2 sampleUV = offset.xy / offset.w * 0.5 + 0.5;
+        ivec2 sampleCoord = clamp(ivec2(sampleUV * dim), ivec2(0), dim - 1);
+
+        if (texelFetch(samplerNormal, sampleCoord, 0).xyz == vec3(0.0)) {
+            continue;
+        }
+
+        float sampleDepth = (params.view * vec4(texelFetch(samplerPosition, sampleCoord, 0).xyz, 1.0)).z;
+
+        float rangeCheck = smoothstep(0.0, 1.0, params.radius / abs(fragPos.z - sampleDepth));
+        occlusion += (sampleDepth >= samplePos.z + params.bias ? 1.0 : 0.0) * rangeCheck;
+    }
+
+    float ao = 1.0 - occlusion / float(params.sampleCount);
+    outAo = vec2(pow(ao, params.power), -fragPos.z);
+}
@@ -0,0 +1,36 @@
+#version 450
+
+// Depth-aware (bilateral) box blur over noise tile size: removes noise pattern without leaking over edges
+
+layout(set = 0, binding = 0) uniform sampler2D ssaoImage;
+
+layout(location = 0) in vec2 inUV;
+layout(location = 0) out float outAo;
+
+const float DEPTH_SHARPNESS = 400.0;
+
+
+void main() {
+    ivec2 dim = textureSize(ssaoImage, 0);
+    ivec2 center = ivec2(inUV * dim);
+    vec2 centerValue = texelFetch(ssaoImage, center, 0).rg;
+
+    float sum = 0.0;
+    float weightSum = 0.0;
+
+    for (int x = -2; x < 2; x++) {
+        for (int y = -2; y < 2; y++) {
+            ivec2 coord = clamp(center + ivec2(x, y), ivec2(0), dim - 1);
+            vec2 value = texelFetch(ssaoImage, coord, 0).rg;
+
+            // relative depth difference, so the same sharpness works near and far
+            float depthDiff = (value.g - centerValue.g) / max(centerValue.g, 1e-3);
+            float weight = exp(-depthDiff * depthDiff * DEPTH_SHARPNESS);
+
+            sum += value.r * weight;
+            weightSum += weight;
+        }
+    }
+
+    outAo = sum / weightSum;
+}
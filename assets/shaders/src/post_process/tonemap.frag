@@ -0,0 +1,161 @@
+#version 450
+
+#define OPERATOR_LINEAR 0u
+#define OPERATOR_REINHARD 1u
+#define OPERATOR_ACES 2u
+#define OPERATOR_UNCHARTED2 3u
+#define OPERATOR_AGX 4u
+
+#define OUTPUT_SRGB 0u
+#define OUTPUT_HDR 1u
+
+layout(set = 0, binding = 0) uniform sampler2D hdrImage;
+
+layout(set = 0, binding = 1) uniform ToneMapSettings {
+    float exposure;
+    float whitePoint;
+    uint toneOperator;
+    uint outputTransform;
+    uint autoExposure;
+    float bloomIntensity;
+} settings;
+
+// written by exposure_adaptation.comp
+layout(set = 0, binding = 2) readonly buffer ExposureState {
+    float exposure;
+} exposureState;
+
+// half resolution, upsampled bilinearly
+layout(set = 0, binding = 3) uniform sampler2D bloomImage;
+
+// 1 if swapchain format is UNORM and sRGB curve must be applied in shader
+layout(constant_id = 0) const uint ENCODE_SRGB = 0u;
+
+layout(location = 0) in vec2 inUV;
+layout(location = 0) out vec4 outColor;
+
+
+vec3 reinhard(vec3 color, float whitePoint)
+{
+    return color * (1.0 + color / (whitePoint * whitePoint)) / (1.0 + color);
+}
+
+// Narkowicz 2015, "ACES Filmic Tone Mapping Curve"
+vec3 aces(vec3 color)
+{
+    color *= 0.6;
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+}
+
+// Hable 2010, "Filmic Tonemapping Operators"
+vec3 uncharted2Curve(vec3 x)
+{
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 uncharted2(vec3 color)
+{
+    const float W = 11.2;
+    const float exposureBias = 2.0;
+    vec3 whiteScale = 1.0 / uncharted2Curve(vec3(W));
+    return uncharted2Curve(color * exposureBias) * whiteScale;
+}
+
+// Minimal AgX (https://iolite-engine.com/blog_posts/minimal_agx_implementation)
+vec3 agxDefaultContrastApprox(vec3 x)
+{
+    vec3 x2 = x * x;
+    vec3 x4 = x2 * x2;
+
+    return + 15.5 * x4 * x2
+           - 40.14 * x4 * x
+           + 31.96 * x4
+           - 6.868 * x2 * x
+           + 0.4298 * x2
+           + 0.1191 * x
+           - 0.00232;
+}
+
+vec3 agx(vec3 color)
+{
+    const mat3 agxMat = mat3(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104);
+
+    const mat3 agxMatInv = mat3(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116);
+
+    const float minEv = -12.47393;
+    const float maxEv = 4.026069;
+
+    color = agxMat * color;
+    color = clamp(log2(max(color, 1e-10)), minEv, maxEv);
+    color = (color - minEv) / (maxEv - minEv);
+    color = agxDefaultContrastApprox(color);
+    color = agxMatInv * color;
+
+    // curve output is display encoded, return to linear
+    return pow(max(color, 0.0), vec3(2.2));
+}
+
+vec3 linearToSrgb(vec3 color)
+{
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, lessThanEqual(color, vec3(0.0031308)));
+}
+
+
+void main() {
+    float exposure = settings.exposure;
+    if (settings.autoExposure == 1u) {
+        exposure *= exposureState.exposure;
+    }
+
+    vec3 color = texture(hdrImage, inUV).rgb;
+    if (settings.bloomIntensity > 0.0) {
+        color += texture(bloomImage, inUV).rgb * settings.bloomIntensity;
+    }
+    color *= exposure;
+
+    if (settings.outputTransform == OUTPUT_HDR) {
+        outColor = vec4(color, 1.0);
+        return;
+    }
+
+    switch (settings.toneOperator) {
+        case OPERATOR_REINHARD:
+        color = reinhard(color, settings.whitePoint);
+        break;
+        case OPERATOR_ACES:
+        color = aces(color);
+        break;
+        case OPERATOR_UNCHARTED2:
+        color = uncharted2(color);
+        break;
+        case OPERATOR_AGX:
+        color = agx(color);
+        break;
+    }
+
+    color = clamp(color, 0.0, 1.0);
+    if (ENCODE_SRGB == 1u) {
+        color = linearToSrgb(color);
+    }
+
+    outColor = vec4(color, 1.0);
+}
@@ -10,10 +10,14 @@ layout(location = 1) in vec3 fragUVW;
 layout(location = 0) out vec4 outColor;
 layout(location = 1) out vec4 outPosition;
 layout(location = 2) out vec4 outNormal;
+layout(location = 3) out vec4 outMaterial;
+layout(location = 4) out vec4 outEmissive;
 
 void main() {
     outColor = texture(texSampler, fragUVW);
 //    outColor = vec4(0.53, 0.81, 0.92, 1.0);
     outPosition = vec4(1.0);
     outNormal = vec4(0.0);
+    outMaterial = vec4(1.0, 0.0, 1.0, 1.0);
+    outEmissive = vec4(0.0, 0.0, 0.0, 1.0);
 }
@@ -1,6 +1,9 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
 
+// Depth buffer is reverse-Z (far plane at depth 0)
+layout(constant_id = 0) const uint REVERSE_Z = 0u;
+
 layout(binding = 0) uniform UniformBufferObject {
     mat4 model;
     mat4 view;
@@ -19,7 +22,8 @@ out gl_PerVertex {
 
 void main() {
     vec4 pos = ubo.proj * ubo.view * ubo.model * vec4(inPosition, 1.0);
-    gl_Position = pos.xyww;
+    // far plane
+    gl_Position = REVERSE_Z == 1u ? vec4(pos.xy, 0.0, pos.w) : pos.xyww;
     fragColor = inColor;
 
     fragUVW = inPosition;
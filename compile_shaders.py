@@ -18,7 +18,7 @@ def main():
     base_src_path = 'assets/shaders/src/'
     base_dst_path = 'assets/shaders/spv/'
     for file in glob.glob('{}/**'.format(base_src_path), recursive=True):
-        if not (file.endswith('.vert') or file.endswith('.frag')):
+        if not (file.endswith('.vert') or file.endswith('.frag') or file.endswith('.comp')):
             continue
 
         dst_file = '{}.spv'.format(os.path.join(base_dst_path, file.replace(base_src_path, '')))
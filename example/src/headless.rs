@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+
+use cgmath::Point3;
+
+use ash_render_env::camera_path::CameraPath;
+use ash_render_env::frame_capture::FrameCapture;
+
+use crate::HelloApplication;
+
+// Headless render of a single frame into PNG, used by golden-image tests:
+// --headless [--size WxH] [--frames N] [--output out.png] [--camera x,y,z,yaw,pitch] [--strict-validation]
+//
+// Offline capture of camera path playback (every path frame at --fps, not limited by real time):
+// --headless --camera-path camera_path.txt [--fps 60] (--capture-dir DIR | --capture-raw FILE | --capture-ffmpeg OUT.mp4)
+pub struct HeadlessArgs {
+    pub size: [u32; 2],
+    // frames rendered before capture (auto exposure and temporal effects settle)
+    pub frames: u32,
+    pub output: PathBuf,
+    // position, yaw and pitch in degrees
+    pub camera: Option<(Point3<f32>, f32, f32)>,
+    // exit with error code if validation layer reported errors
+    pub strict_validation: bool,
+    pub capture: Option<CaptureArgs>,
+}
+
+pub struct CaptureArgs {
+    pub camera_path: PathBuf,
+    pub fps: f32,
+    pub output: CaptureOutput,
+}
+
+pub enum CaptureOutput {
+    // numbered PNG files
+    Dir(PathBuf),
+    // raw RGBA8 frames, file or named pipe
+    Raw(PathBuf),
+    // frames piped to ffmpeg encoding given file
+    Ffmpeg(PathBuf),
+}
+
+impl HeadlessArgs {
+    // None if `--headless` is not given
+    pub fn parse(args: &[String]) -> Result<Option<HeadlessArgs>, String> {
+        if !args.iter().any(|arg| arg == "--headless") {
+            return Ok(None);
+        }
+
+        let mut result = HeadlessArgs {
+            size: [800, 600],
+            frames: 30,
+            output: PathBuf::from("headless.png"),
+            camera: None,
+            strict_validation: false,
+            capture: None,
+        };
+        let mut camera_path = None;
+        let mut fps: f32 = 60.0;
+        let mut capture_output = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+            match arg.as_str() {
+                "--headless" => {}
+                "--strict-validation" => result.strict_validation = true,
+                "--size" => {
+                    let value = value()?;
+                    let size = parse_list(value, 'x')?;
+                    if size.len() != 2 || size.iter().any(|&v| v == 0) {
+                        return Err(format!("Invalid size {}, expected WxH", value));
+                    }
+                    result.size = [size[0], size[1]];
+                }
+                "--frames" => {
+                    let value = value()?;
+                    result.frames = value.parse().map_err(|_| format!("Invalid frame count {}", value))?;
+                    if result.frames == 0 {
+                        return Err("Frame count must be positive".to_string());
+                    }
+                }
+                "--output" => result.output = PathBuf::from(value()?),
+                "--camera" => {
+                    let value = value()?;
+                    let camera: Vec<f32> = parse_list(value, ',')?;
+                    if camera.len() != 5 {
+                        return Err(format!("Invalid camera {}, expected x,y,z,yaw,pitch", value));
+                    }
+                    result.camera = Some((Point3::new(camera[0], camera[1], camera[2]), camera[3], camera[4]));
+                }
+                "--camera-path" => camera_path = Some(PathBuf::from(value()?)),
+                "--fps" => {
+                    let value = value()?;
+                    fps = value.parse().map_err(|_| format!("Invalid fps {}", value))?;
+                    if !fps.is_finite() || fps <= 0.0 {
+                        return Err("Fps must be positive".to_string());
+                    }
+                }
+                "--capture-dir" => capture_output = Some(CaptureOutput::Dir(PathBuf::from(value()?))),
+                "--capture-raw" => capture_output = Some(CaptureOutput::Raw(PathBuf::from(value()?))),
+                "--capture-ffmpeg" => capture_output = Some(CaptureOutput::Ffmpeg(PathBuf::from(value()?))),
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+
+        result.capture = match (camera_path, capture_output) {
+            (Some(camera_path), Some(output)) => Some(CaptureArgs { camera_path, fps, output }),
+            (None, None) => None,
+            (Some(_), None) => return Err("Capture output is not set (--capture-dir, --capture-raw or --capture-ffmpeg)".to_string()),
+            (None, Some(_)) => return Err("Capture requires --camera-path".to_string()),
+        };
+
+        Ok(Some(result))
+    }
+}
+
+fn parse_list<T: std::str::FromStr>(value: &str, separator: char) -> Result<Vec<T>, String> {
+    value
+        .split(separator)
+        .map(|item| item.trim().parse().map_err(|_| format!("Invalid value {}", value)))
+        .collect()
+}
+
+// Returns process exit code
+pub fn run(args: &HeadlessArgs) -> i32 {
+    let mut app = HelloApplication::headless(args.size, args.strict_validation);
+    if let Some((position, yaw, pitch)) = args.camera {
+        app.set_camera(position, yaw, pitch);
+    }
+
+    match &args.capture {
+        Some(capture) => {
+            if let Err(err) = run_capture(&mut app, capture, args.size) {
+                eprintln!("{}", err);
+                return 1;
+            }
+        }
+        None => {
+            let image = app.render_headless(args.frames);
+            image.save(&args.output).expect("Failed to save headless frame!");
+            println!("saved {}", args.output.display());
+        }
+    }
+
+    let errors = app.env.take_validation_errors();
+    if !errors.is_empty() {
+        eprintln!("{} validation error(s):", errors.len());
+        for error in errors.iter() {
+            eprintln!("{}", error);
+        }
+        return 1;
+    }
+
+    0
+}
+
+fn run_capture(app: &mut HelloApplication, args: &CaptureArgs, size: [u32; 2]) -> Result<(), String> {
+    let path = CameraPath::load(&args.camera_path)
+        .map_err(|err| format!("Failed to load camera path {}: {}", args.camera_path.display(), err))?;
+    if path.is_empty() {
+        return Err(format!("Camera path {} is empty", args.camera_path.display()));
+    }
+
+    let mut ffmpeg = None;
+    let capture = match &args.output {
+        CaptureOutput::Dir(dir) => FrameCapture::png_sequence(dir, "frame")
+            .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?,
+        CaptureOutput::Raw(file) => {
+            let file = File::create(file).map_err(|err| format!("Failed to create {}: {}", file.display(), err))?;
+            FrameCapture::raw(Box::new(BufWriter::new(file)))
+        }
+        CaptureOutput::Ffmpeg(video) => {
+            let mut child = spawn_ffmpeg(video, size, args.fps)?;
+            let stdin = child.stdin.take().unwrap();
+            ffmpeg = Some(child);
+            FrameCapture::raw(Box::new(BufWriter::new(stdin)))
+        }
+    };
+
+    app.start_capture(path, 1.0 / args.fps, capture);
+    let result = app.render_capture();
+
+    // capture is finished, ffmpeg stdin is closed
+    if let Some(mut child) = ffmpeg {
+        let status = child.wait().map_err(|err| format!("Failed to wait ffmpeg: {}", err))?;
+        if !status.success() {
+            return Err(format!("ffmpeg failed ({})", status));
+        }
+    }
+
+    result
+        .map(|_| ())
+        .map_err(|err| format!("Failed to write captured frames: {}", err))
+}
+
+fn spawn_ffmpeg(video: &Path, size: [u32; 2], fps: f32) -> Result<Child, String> {
+    Command::new("ffmpeg")
+        .args(&["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba"])
+        .args(&["-s", &format!("{}x{}", size[0], size[1]), "-r", &fps.to_string(), "-i", "-"])
+        .args(&["-pix_fmt", "yuv420p"])
+        .arg(video)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("Failed to run ffmpeg: {}", err))
+}
@@ -0,0 +1,92 @@
+use cgmath::{Point3, Vector3};
+
+use crate::lighting::light_manager::{Light, LightManager, MAX_LIGHTS};
+use crate::utils::heightmap_terrain::terrain::HeightMap;
+
+// Point and spot lights scattered over terrain, animated on small circles
+pub struct DemoLights {
+    lights: Vec<Light>,
+    count: usize,
+    animate: bool,
+    time: f32,
+    show_heatmap: bool,
+}
+
+impl DemoLights {
+    pub fn new(height_map: &HeightMap) -> DemoLights {
+        DemoLights {
+            lights: create_demo_lights(height_map, MAX_LIGHTS),
+            count: 256,
+            animate: true,
+            time: 0.0,
+            show_heatmap: false,
+        }
+    }
+
+    // Compose pass shows number of lights per tile instead of lit scene
+    pub fn show_heatmap(&self) -> bool {
+        self.show_heatmap
+    }
+
+    // Replaces lights of `light_manager` with enabled demo lights
+    pub fn update(&mut self, light_manager: &mut LightManager, delta_time: f32) {
+        if self.animate {
+            self.time += delta_time;
+        }
+
+        light_manager.clear();
+        for (idx, light) in self.lights.iter().take(self.count).enumerate() {
+            // small circle around initial position, every light has own phase
+            let phase = self.time + idx as f32 * 0.37;
+            let mut light = *light;
+            light.position += Vector3::new(phase.cos(), 0.0, phase.sin()) * 0.5;
+
+            light_manager.add(light);
+        }
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.count, 0..=MAX_LIGHTS).text("Point/spot lights"));
+        ui.checkbox(&mut self.animate, "Animate lights");
+        ui.checkbox(&mut self.show_heatmap, "Show lights per tile");
+    }
+}
+
+// Lights over terrain around origin (Vogel spiral, first lights are closer to center).
+// Every 8th light is a spot light looking down.
+fn create_demo_lights(height_map: &HeightMap, count: usize) -> Vec<Light> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+
+    (0..count).map(|idx| {
+        let radius = 20.0 * ((idx as f32 + 0.5) / count as f32).sqrt();
+        let angle = idx as f32 * golden_angle;
+        let x = radius * angle.cos();
+        let z = radius * angle.sin();
+
+        // height grows to -Y
+        let height = height_map.world_height(x, z);
+
+        let color = hue_to_rgb((idx as f32 * 0.618034).fract());
+        let light = if idx % 8 == 0 {
+            Light::spot(Point3::new(x, height - 1.0, z), Vector3::new(0.0, 1.0, 0.0), color, 4.0, 3.0, 0.3, 0.6)
+        } else {
+            Light::point(Point3::new(x, height - 0.3, z), color, 1.0, 2.0)
+        };
+
+        // lights near the center cast shadows (8 spot, 4 point lights)
+        if (idx % 8 == 0 && idx < 64) || (idx % 32 == 4 && idx < 128) {
+            light.with_shadows()
+        } else {
+            light
+        }
+    }).collect()
+}
+
+fn hue_to_rgb(hue: f32) -> [f32; 3] {
+    let h = hue * 6.0;
+    [
+        ((h - 3.0).abs() - 1.0).max(0.0).min(1.0),
+        (2.0 - (h - 2.0).abs()).max(0.0).min(1.0),
+        (2.0 - (h - 4.0).abs()).max(0.0).min(1.0),
+    ]
+}
@@ -0,0 +1,146 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use cgmath::{InnerSpace, Vector3};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeOfDay {
+    // 0..24, sunrise at 6:00, sunset at 18:00
+    pub hours: f32,
+    // hours per second
+    pub speed: f32,
+    // sun elevation at noon (radians)
+    pub max_elevation: f32,
+    // horizontal direction of sunrise (radians)
+    pub azimuth: f32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        TimeOfDay {
+            hours: 10.0,
+            speed: 0.5,
+            max_elevation: PI / 3.0,
+            azimuth: 2.4,
+        }
+    }
+}
+
+impl TimeOfDay {
+    fn sun_direction(&self) -> Vector3<f32> {
+        let angle = (self.hours - 6.0) / 12.0 * PI;
+
+        let sunrise = Vector3::new(self.azimuth.cos(), 0.0, self.azimuth.sin());
+        let noon = to_light_direction(self.azimuth + PI / 2.0, self.max_elevation);
+        let to_light = sunrise * angle.cos() + noon * angle.sin();
+
+        -to_light.normalize()
+    }
+}
+
+// Sun: lights whole scene and casts cascaded shadows
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DirectionalLight {
+    // direction light travels in, shadow cascades look along it
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // `update` moves the sun over the sky (overwrites `direction`)
+    pub time_of_day: Option<TimeOfDay>,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        DirectionalLight {
+            direction: Vector3::new(0.70, 0.25, -0.67).normalize(),
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.5 * PI,
+            time_of_day: None,
+        }
+    }
+}
+
+impl DirectionalLight {
+    // Returns true if direction is changed (shadow cascades must be updated)
+    pub fn update(&mut self, delta_time: f32) -> bool {
+        let time_of_day = match self.time_of_day.as_mut() {
+            Some(time_of_day) => time_of_day,
+            None => return false,
+        };
+
+        time_of_day.hours = (time_of_day.hours + time_of_day.speed * delta_time).rem_euclid(24.0);
+
+        let direction = time_of_day.sun_direction();
+        let changed = direction != self.direction;
+        self.direction = direction;
+
+        changed
+    }
+
+    // Azimuth and elevation (radians) of direction towards the light
+    pub fn angles(&self) -> (f32, f32) {
+        let to_light = -self.direction.normalize();
+        (to_light.z.atan2(to_light.x), (-to_light.y).asin())
+    }
+
+    pub fn set_angles(&mut self, azimuth: f32, elevation: f32) {
+        self.direction = -to_light_direction(azimuth, elevation);
+    }
+
+    // Color * intensity, fades out when the sun goes below horizon
+    pub fn radiance(&self) -> [f32; 3] {
+        let (_, elevation) = self.angles();
+        let visibility = (elevation.sin() * 10.0).max(0.0).min(1.0);
+        let scale = self.intensity * visibility;
+
+        [self.color[0] * scale, self.color[1] * scale, self.color[2] * scale]
+    }
+}
+
+// World Y axis points down on the screen, so elevation goes to -Y
+fn to_light_direction(azimuth: f32, elevation: f32) -> Vector3<f32> {
+    Vector3::new(
+        elevation.cos() * azimuth.cos(),
+        -elevation.sin(),
+        elevation.cos() * azimuth.sin(),
+    )
+}
+
+// Returns true if sun direction was changed
+pub fn sun_editor(ui: &mut egui::Ui, sun: &mut DirectionalLight) -> bool {
+    let mut direction_changed = false;
+
+    ui.collapsing("Sun", |ui| {
+        ui.horizontal(|ui| {
+            ui.color_edit_button_rgb(&mut sun.color);
+            ui.label("Color");
+        });
+        ui.add(egui::Slider::new(&mut sun.intensity, 0.0..=20.0).text("Intensity"));
+
+        let mut time_of_day_enabled = sun.time_of_day.is_some();
+        if ui.checkbox(&mut time_of_day_enabled, "Time of day").changed() {
+            sun.time_of_day = if time_of_day_enabled { Some(TimeOfDay::default()) } else { None };
+        }
+
+        match sun.time_of_day.as_mut() {
+            // direction is updated every frame by `DirectionalLight::update`
+            Some(time_of_day) => {
+                ui.add(egui::Slider::new(&mut time_of_day.hours, 0.0..=24.0).text("Hours"));
+                ui.add(egui::Slider::new(&mut time_of_day.speed, 0.0..=4.0).text("Speed (hours/s)"));
+                ui.add(egui::Slider::new(&mut time_of_day.max_elevation, 0.0..=FRAC_PI_2).text("Noon elevation (rad)"));
+                ui.add(egui::Slider::new(&mut time_of_day.azimuth, -PI..=PI).text("Sunrise azimuth (rad)"));
+            }
+            None => {
+                let (mut azimuth, mut elevation) = sun.angles();
+                let mut changed = ui.add(egui::Slider::new(&mut azimuth, -PI..=PI).text("Azimuth (rad)")).changed();
+                changed |= ui.add(egui::Slider::new(&mut elevation, -FRAC_PI_2..=FRAC_PI_2).text("Elevation (rad)")).changed();
+
+                if changed {
+                    sun.set_angles(azimuth, elevation);
+                    direction_changed = true;
+                }
+            }
+        }
+    });
+
+    direction_changed
+}
@@ -0,0 +1,179 @@
+use std::ptr;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use cgmath::{Matrix4, SquareMatrix};
+
+use ash_render_env::compute_pipeline::ComputePipeline;
+use ash_render_env::descriptor_set::{DescriptorSet, DescriptorSetBuilder};
+use ash_render_env::env::RenderEnv;
+use ash_render_env::frame_buffer::Framebuffer;
+use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+use ash_render_env::shader;
+use ash_render_env::utils::buffer_utils::create_buffer_;
+
+use crate::post_process::create_linear_sampler;
+
+// Must match defines in light_culling.comp and compose.frag
+pub const TILE_SIZE: u32 = 16;
+pub const MAX_LIGHTS_PER_TILE: u32 = 255;
+
+// Tiled light culling: for every 16x16 screen tile finds depth bounds of G-buffer geometry
+// and builds list of lights affecting it. Compose pass shades only lights from the pixel tile.
+pub struct LightCulling {
+    pipeline: ComputePipeline,
+    descriptor_set: DescriptorSet,
+
+    // per tile: light count + MAX_LIGHTS_PER_TILE indices (u32)
+    tile_buffer: vk::Buffer,
+    tile_buffer_memory: vk::DeviceMemory,
+    light_buffer: vk::Buffer,
+
+    commands: PrimaryCommandBuffer,
+    sampler: vk::Sampler,
+    tile_count: [u32; 2],
+    env: Arc<RenderEnv>,
+}
+
+impl LightCulling {
+    pub fn new(env: Arc<RenderEnv>, gbuffer: &Framebuffer, light_buffer: vk::Buffer, input_samples: vk::SampleCountFlags,
+               dimensions: [u32; 2], max_frames_in_flight: usize) -> LightCulling {
+        let pipeline = ComputePipeline::new(
+            env.device().clone(),
+            shader::Shader::load(env.device(), "assets/shaders/spv/lighting/light_culling.comp.spv")
+                .specialize(shader::ConstantsBuilder::new().add_u32(input_samples.as_raw())),
+        ).debug_name(&env, "light_culling");
+
+        let sampler = create_linear_sampler(env.device());
+        env.set_name(sampler, "light_culling.sampler");
+        let tile_count = Self::tile_count(dimensions);
+        let (tile_buffer, tile_buffer_memory) = Self::create_tile_buffer(&env, tile_count);
+        let descriptor_set = Self::create_descriptor_set(&env, &pipeline, gbuffer, sampler, light_buffer, tile_buffer);
+
+        LightCulling {
+            pipeline,
+            descriptor_set,
+            tile_buffer,
+            tile_buffer_memory,
+            light_buffer,
+            commands: PrimaryCommandBuffer::new(env.clone(), max_frames_in_flight).debug_name("light_culling"),
+            sampler,
+            tile_count,
+            env,
+        }
+    }
+
+    pub fn tile_buffer(&self) -> vk::Buffer {
+        self.tile_buffer
+    }
+
+    // Tile buffer is recreated, compose pass descriptors must be updated too
+    pub fn update_framebuffer(&mut self, gbuffer: &Framebuffer, dimensions: [u32; 2]) {
+        unsafe {
+            self.env.device().destroy_buffer(self.tile_buffer, None);
+            self.env.device().free_memory(self.tile_buffer_memory, None);
+        }
+
+        self.tile_count = Self::tile_count(dimensions);
+        let (tile_buffer, tile_buffer_memory) = Self::create_tile_buffer(&self.env, self.tile_count);
+        self.tile_buffer = tile_buffer;
+        self.tile_buffer_memory = tile_buffer_memory;
+
+        self.descriptor_set = Self::create_descriptor_set(
+            &self.env, &self.pipeline, gbuffer, self.sampler, self.light_buffer, self.tile_buffer);
+    }
+
+    // Must be submitted after geometry pass and before compose pass
+    pub fn execute(&mut self, view: Matrix4<f32>, proj: Matrix4<f32>) -> vk::CommandBuffer {
+        let view: [[f32; 4]; 4] = view.into();
+        let inv_proj: [[f32; 4]; 4] = proj.invert().unwrap().into();
+
+        let mut params = Vec::new();
+        for column in view.iter().chain(inv_proj.iter()) {
+            for value in column.iter() {
+                params.extend(value.to_le_bytes());
+            }
+        }
+
+        let pipeline = &self.pipeline;
+        let descriptor_set = self.descriptor_set.set;
+        let tile_buffer = self.tile_buffer;
+        let tile_count = self.tile_count;
+
+        self.commands.execute(|device, cmd_buf| unsafe {
+            device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::COMPUTE, pipeline.pipeline);
+            device.cmd_bind_descriptor_sets(cmd_buf, vk::PipelineBindPoint::COMPUTE, pipeline.pipeline_layout,
+                                            0, &[descriptor_set], &[]);
+            device.cmd_push_constants(cmd_buf, pipeline.pipeline_layout, vk::ShaderStageFlags::COMPUTE, 0, &params);
+            device.cmd_dispatch(cmd_buf, tile_count[0], tile_count[1], 1);
+
+            let buffer_barriers = [
+                vk::BufferMemoryBarrier {
+                    s_type: vk::StructureType::BUFFER_MEMORY_BARRIER,
+                    p_next: ptr::null(),
+                    src_access_mask: vk::AccessFlags::SHADER_WRITE,
+                    dst_access_mask: vk::AccessFlags::SHADER_READ,
+                    src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+                    buffer: tile_buffer,
+                    offset: 0,
+                    size: vk::WHOLE_SIZE,
+                }
+            ];
+
+            // tile lists are read by compose fragment shader
+            device.cmd_pipeline_barrier(
+                cmd_buf,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &buffer_barriers,
+                &[],
+            );
+        })
+    }
+
+    fn tile_count(dimensions: [u32; 2]) -> [u32; 2] {
+        [
+            (dimensions[0] + TILE_SIZE - 1) / TILE_SIZE,
+            (dimensions[1] + TILE_SIZE - 1) / TILE_SIZE,
+        ]
+    }
+
+    fn create_tile_buffer(env: &RenderEnv, tile_count: [u32; 2]) -> (vk::Buffer, vk::DeviceMemory) {
+        let tile_stride = (MAX_LIGHTS_PER_TILE + 1) as u64 * std::mem::size_of::<u32>() as u64;
+
+        let (buffer, memory, _) = create_buffer_(
+            env.device(),
+            tile_count[0] as u64 * tile_count[1] as u64 * tile_stride,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            &env.mem_properties,
+            Some((env.debug_names(), "light_culling.tiles")),
+        );
+
+        (buffer, memory)
+    }
+
+    fn create_descriptor_set(env: &RenderEnv, pipeline: &ComputePipeline, gbuffer: &Framebuffer, sampler: vk::Sampler,
+                             light_buffer: vk::Buffer, tile_buffer: vk::Buffer) -> DescriptorSet {
+        DescriptorSetBuilder::new(env.device(), pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(gbuffer.attachments.get(1).unwrap().view, sampler)
+            .add_image(gbuffer.attachments.get(2).unwrap().view, sampler)
+            .add_buffer(light_buffer)
+            .add_buffer(tile_buffer)
+            .build()
+    }
+}
+
+impl Drop for LightCulling {
+    fn drop(&mut self) {
+        unsafe {
+            self.env.device().destroy_sampler(self.sampler, None);
+            self.env.device().destroy_buffer(self.tile_buffer, None);
+            self.env.device().free_memory(self.tile_buffer_memory, None);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use ash::vk;
+use cgmath::{InnerSpace, Point3, Vector3};
+
+use ash_render_env::env::RenderEnv;
+
+use crate::utils::storage_buffer::StorageBuffer;
+
+// Capacity of light storage buffer
+pub const MAX_LIGHTS: usize = 1024;
+
+// Values of `directionType.w` in shaders
+const LIGHT_TYPE_POINT: f32 = 0.0;
+const LIGHT_TYPE_SPOT: f32 = 1.0;
+const LIGHT_TYPE_DIRECTIONAL: f32 = 2.0;
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LightKind {
+    Point,
+    // cone half angles in radians, full intensity inside `inner_angle`
+    Spot { inner_angle: f32, outer_angle: f32 },
+    Directional,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: Point3<f32>,
+    // direction light travels in (spot and directional lights)
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // distance where light influence ends (point and spot lights)
+    pub range: f32,
+    // point and spot lights: rendered to shadow atlas
+    pub cast_shadows: bool,
+}
+
+#[allow(dead_code)]
+impl Light {
+    pub fn point(position: Point3<f32>, color: [f32; 3], intensity: f32, range: f32) -> Light {
+        Light {
+            kind: LightKind::Point,
+            position,
+            direction: Vector3::new(0.0, 1.0, 0.0),
+            color,
+            intensity,
+            range,
+            cast_shadows: false,
+        }
+    }
+
+    pub fn spot(position: Point3<f32>, direction: Vector3<f32>, color: [f32; 3], intensity: f32, range: f32,
+                inner_angle: f32, outer_angle: f32) -> Light {
+        Light {
+            kind: LightKind::Spot { inner_angle, outer_angle },
+            position,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            range,
+            cast_shadows: false,
+        }
+    }
+
+    pub fn directional(direction: Vector3<f32>, color: [f32; 3], intensity: f32) -> Light {
+        Light {
+            kind: LightKind::Directional,
+            position: Point3::new(0.0, 0.0, 0.0),
+            direction: direction.normalize(),
+            color,
+            intensity,
+            range: 0.0,
+            cast_shadows: false,
+        }
+    }
+
+    pub fn with_shadows(mut self) -> Light {
+        self.cast_shadows = true;
+        self
+    }
+
+    fn gpu_data(&self, shadow_view: Option<u32>) -> GpuLight {
+        let (light_type, spot_cone) = match self.kind {
+            LightKind::Point => (LIGHT_TYPE_POINT, [0.0, 0.0]),
+            LightKind::Spot { inner_angle, outer_angle } => {
+                let outer = outer_angle.max(0.001);
+                (LIGHT_TYPE_SPOT, [outer.cos(), inner_angle.min(outer).cos()])
+            }
+            LightKind::Directional => (LIGHT_TYPE_DIRECTIONAL, [0.0, 0.0]),
+        };
+
+        GpuLight {
+            position_range: [self.position.x, self.position.y, self.position.z, self.range],
+            color_intensity: [self.color[0], self.color[1], self.color[2], self.intensity],
+            direction_type: [self.direction.x, self.direction.y, self.direction.z, light_type],
+            spot_cone: [spot_cone[0], spot_cone[1], shadow_view.map_or(-1.0, |view| view as f32), 0.0],
+        }
+    }
+}
+
+// Layout of `Light` struct in shaders (std430)
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuLight {
+    position_range: [f32; 4],
+    color_intensity: [f32; 4],
+    direction_type: [f32; 4],
+    // x: cos(outer angle), y: cos(inner angle), z: first shadow atlas view (-1: no shadow)
+    spot_cone: [f32; 4],
+}
+
+const EMPTY_LIGHT: GpuLight = GpuLight {
+    position_range: [0.0; 4],
+    color_intensity: [0.0; 4],
+    direction_type: [0.0; 4],
+    spot_cone: [0.0; 4],
+};
+
+// Layout of `LightBuffer` in shaders
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LightBufferData {
+    light_count: u32,
+    _padding: [u32; 3],
+    lights: [GpuLight; MAX_LIGHTS],
+}
+
+// Scene lights (world space). Copied to GPU storage buffer by `upload`, which is read
+// by light culling and compose passes.
+pub struct LightManager {
+    lights: Vec<Light>,
+    buffer: StorageBuffer<LightBufferData>,
+}
+
+#[allow(dead_code)]
+impl LightManager {
+    pub fn new(env: Arc<RenderEnv>) -> LightManager {
+        LightManager {
+            lights: Vec::new(),
+            buffer: StorageBuffer::new(env.clone(), LightBufferData {
+                light_count: 0,
+                _padding: [0; 3],
+                lights: [EMPTY_LIGHT; MAX_LIGHTS],
+            }).debug_name(&env, "lights"),
+        }
+    }
+
+    pub fn buffer(&self) -> vk::Buffer {
+        self.buffer.buffer
+    }
+
+    // Returns light index, indices of next lights are shifted by `remove`
+    pub fn add(&mut self, light: Light) -> usize {
+        assert!(self.lights.len() < MAX_LIGHTS, "Too many lights (max {})", MAX_LIGHTS);
+
+        self.lights.push(light);
+        self.lights.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Light {
+        self.lights.remove(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Light> {
+        self.lights.get_mut(index)
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.lights.clear();
+    }
+
+    // Must be called before frame submit, buffer is not double buffered.
+    // `shadow_views`: first shadow atlas view of every light (see `ShadowAtlas::update`)
+    pub fn upload(&self, shadow_views: &[Option<u32>]) {
+        let mut data = LightBufferData {
+            light_count: self.lights.len() as u32,
+            _padding: [0; 3],
+            lights: [EMPTY_LIGHT; MAX_LIGHTS],
+        };
+
+        for (idx, light) in self.lights.iter().enumerate() {
+            data.lights[idx] = light.gpu_data(shadow_views.get(idx).copied().flatten());
+        }
+
+        self.buffer.write_data(data);
+    }
+}
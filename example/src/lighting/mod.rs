@@ -0,0 +1,4 @@
+pub mod demo_lights;
+pub mod directional_light;
+pub mod light_culling;
+pub mod light_manager;
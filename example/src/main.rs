@@ -1,49 +1,96 @@
-use std::ops::RangeInclusive;
+use std::io;
 use std::path::Path;
 use std::ptr;
+use std::rc::Rc;
 use std::sync::Arc;
 
 use ash::version::DeviceV1_0;
 use ash::vk;
-use cgmath::{Matrix4, SquareMatrix};
+use cgmath::{Matrix4, MetricSpace, Point3, SquareMatrix};
 use winit::event::{Event, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::platform::run_return::EventLoopExtRunReturn;
 
 use ash_render_env::{env, frame_buffer};
 use ash_render_env::camera::Camera;
-use ash_render_env::egui::Egui;
+use ash_render_env::camera_path::CameraPath;
+use ash_render_env::camera_controller::{CameraController, CameraMode};
+use ash_render_env::input::{InputSettings, InputState};
+use ash_render_env::egui::{camera_mode, Egui, frame_pacer_panel, gpu_profiler_panel, input_settings, projection_editor};
+use ash_render_env::debug_messages::ValidationSettings;
 use ash_render_env::env::RenderEnv;
-use ash_render_env::fps_limiter::FPSLimiter;
+use ash_render_env::frame_capture::FrameCapture;
+use ash_render_env::frame_pacer::FramePacer;
+use ash_render_env::gpu_profiler::GpuProfiler;
 use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+use ash_render_env::projection::Projection;
+use ash_render_env::utils::texture_utils;
 use utils::{render_pass, sync};
 
-use crate::shadow_map::{CASCADE_COUNT, CascadeInfo, ShadowMapFramebuffer};
+use crate::lighting::demo_lights::DemoLights;
+use crate::lighting::directional_light::{DirectionalLight, sun_editor};
+use crate::lighting::light_culling::LightCulling;
+use crate::lighting::light_manager::LightManager;
+use crate::post_process::auto_exposure;
+use crate::post_process::auto_exposure::{AutoExposure, AutoExposureSettings};
+use crate::post_process::bloom;
+use crate::post_process::bloom::{Bloom, BloomSettings};
+use crate::post_process::ssao;
+use crate::post_process::ssao::{Ssao, SsaoSettings};
+use crate::post_process::tonemap;
+use crate::post_process::tonemap::{OutputTransform, ToneMapRenderer, ToneMapSettings};
+use crate::shadow_map::atlas::ShadowAtlas;
+use crate::shadow_map::cascaded::CascadedShadows;
+use crate::shadow_map::caster::ShadowCaster;
+use crate::utils::camera_path_control::{CameraPathControl, CameraPathEvent};
 use crate::utils::heightmap_terrain::terrain::{HeightMap, TerrainData};
 use crate::utils::heightmap_terrain::terrain_renderer::TerrainRenderer;
+use crate::utils::material::material_editor;
 use crate::utils::mesh::Mesh;
-use crate::utils::mesh_render::MeshRenderer;
-use crate::utils::mesh_shadowmap_render::MeshShadowMapRenderer;
+use crate::utils::mesh_render::{mesh_world_matrix, MeshRenderer};
+use crate::utils::screenshot::{ScreenshotImage, ScreenshotSource, Screenshots};
 use crate::utils::quad_render::QuadRenderer;
 use crate::utils::skybox_render::SkyboxRenderer;
 use crate::utils::sync::MAX_FRAMES_IN_FLIGHT;
 
 mod utils;
 mod shadow_map;
+mod post_process;
+mod lighting;
+mod headless;
+
+// camera path time per rendered frame during playback
+const PLAYBACK_TIMESTEP: f32 = 1.0 / 60.0;
+const GPU_TRACE_FILE: &str = "gpu_trace.json";
+// frames written to GPU trace
+const GPU_TRACE_FRAMES: u32 = 120;
+// offscreen swapchain format in headless mode, read back as RGBA8
+const HEADLESS_FORMAT: vk::Format = vk::Format::B8G8R8A8_SRGB;
 
 struct HelloApplication {
     egui: Egui,
 
     final_pass_draw_command: PrimaryCommandBuffer,
+    compose_pass_draw_command: PrimaryCommandBuffer,
     geometry_pass_draw_command: PrimaryCommandBuffer,
-    shadowmap_pass_draw_commands: Vec<PrimaryCommandBuffer>,
 
     quad_renderer: QuadRenderer,
+    sun: DirectionalLight,
+    light_manager: LightManager,
+    light_culling: LightCulling,
+    demo_lights: DemoLights,
+    ssao: Ssao,
+    ssao_settings: SsaoSettings,
+    bloom: Bloom,
+    bloom_settings: BloomSettings,
+    auto_exposure: AutoExposure,
+    auto_exposure_settings: AutoExposureSettings,
+    tone_map_renderer: ToneMapRenderer,
+    tone_map_settings: ToneMapSettings,
     swapchain_stuff: ash_render_env::swapchain::SwapChain,
 
     mesh: Arc<Mesh>,
     mesh_renderer: MeshRenderer,
-    mesh_shadow_map_renderers: Vec<MeshShadowMapRenderer>,
 
     skybox_renderer: SkyboxRenderer,
 
@@ -53,36 +100,82 @@ struct HelloApplication {
     is_window_resized: bool,
 
     camera: Camera,
+    // edited in GUI, applied at the start of next frame
+    projection: Projection,
+    camera_mode: CameraMode,
+    camera_controller: Box<dyn CameraController>,
+    input: InputState,
+    height_map: Rc<HeightMap>,
+    camera_path: CameraPathControl,
+    // world position and object under mouse cursor
+    cursor_hit: Option<(Point3<f32>, &'static str)>,
 
     offscreen_buffer: frame_buffer::Framebuffer,
+    hdr_buffer: frame_buffer::Framebuffer,
 
     terrain_renderer: TerrainRenderer,
 
     final_render_pass: vk::RenderPass,
 
     clear_color: [f32; 3],
-    tick_counter: FPSLimiter,
+    show_gui: bool,
+    frame_pacer: FramePacer,
+    // replaces measured frame time for deterministic animation (headless rendering)
+    fixed_timestep: Option<f32>,
+    gpu_profiler: GpuProfiler,
 
-    shadow_map_fb: ShadowMapFramebuffer,
+    shadows: CascadedShadows,
+    shadow_atlas: ShadowAtlas,
+    msaa_samples: vk::SampleCountFlags,
 
     env: Arc<env::RenderEnv>,
-    cascades: Vec<CascadeInfo>,
-    cascade_split_lambda: f32,
-    egui_current_shadowmap_cascade_image: u32,
+
+    screenshots: Screenshots,
 }
 
 impl HelloApplication {
-    pub fn new(wnd: &winit::window::Window) -> HelloApplication {
+    // `hdr`: scRGB swapchain if display supports it (enables HDR output transform)
+    pub fn new(wnd: &winit::window::Window, hdr: bool) -> HelloApplication {
         let env = Arc::new(env::RenderEnv::new(wnd));
+        let swapchain_stuff = ash_render_env::swapchain::SwapChain::new(&env, wnd.inner_size(), hdr);
+        if hdr && !swapchain_stuff.is_hdr() {
+            log::warn!("HDR surface format is not available, using {:?}", swapchain_stuff.format);
+        }
+
+        let mut app = HelloApplication::create(env, swapchain_stuff, wnd.scale_factor());
+        if app.swapchain_stuff.is_hdr() {
+            app.tone_map_settings.output = OutputTransform::Hdr;
+        }
+        app
+    }
+
+    // Renders into offscreen image without window, GUI is hidden
+    pub fn headless(size: [u32; 2], strict_validation: bool) -> HelloApplication {
+        let env = Arc::new(env::RenderEnv::headless(ValidationSettings {
+            strict: strict_validation,
+            callback: None,
+        }));
+        let extent = vk::Extent2D { width: size[0], height: size[1] };
+        let swapchain_stuff = ash_render_env::swapchain::SwapChain::offscreen(&env, extent, HEADLESS_FORMAT);
+
+        let mut app = HelloApplication::create(env, swapchain_stuff, 1.0);
+        app.show_gui = false;
+        app.fixed_timestep = Some(PLAYBACK_TIMESTEP);
+        app
+    }
 
+    fn create(env: Arc<RenderEnv>, mut swapchain_stuff: ash_render_env::swapchain::SwapChain, scale_factor: f64) -> HelloApplication {
         let max_msaa_samples = ash_render_env::utils::get_max_usable_sample_count(&env);
         let msaa_samples = vk::SampleCountFlags::TYPE_2; //ash_render_env::utils::get_max_usable_sample_count(&env);
 
         println!("MSAA: {:?} (max={:?})", msaa_samples, max_msaa_samples);
 
-        let mut swapchain_stuff = ash_render_env::swapchain::SwapChain::new(&env, wnd.inner_size());
-
-        let quad_render_pass = render_pass::create_quad_render_pass(env.device(), swapchain_stuff.format);
+        let final_layout = if swapchain_stuff.is_offscreen() {
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+        } else {
+            vk::ImageLayout::PRESENT_SRC_KHR
+        };
+        let quad_render_pass = render_pass::create_quad_render_pass(env.device(), swapchain_stuff.format, final_layout);
         swapchain_stuff.create_framebuffers(env.device(), quad_render_pass);
 
         let mut camera = Camera::new();
@@ -94,45 +187,80 @@ impl HelloApplication {
         let dimensions = [swapchain_stuff.size.width, swapchain_stuff.size.height];
         let mut offscreen_framebuffer = frame_buffer::Framebuffer::new(env.clone(), vec!(
             frame_buffer::AttachmentDesciption {  // color
+                name: "color",
                 samples_count: msaa_samples,
                 format: vk::Format::R8G8B8A8_SRGB,
+                mip_levels: 1,
             },
             frame_buffer::AttachmentDesciption {  // pos
+                name: "position",
                 samples_count: msaa_samples,
                 format: vk::Format::R16G16B16A16_SFLOAT,
+                mip_levels: 1,
             },
             frame_buffer::AttachmentDesciption {  // normal
+                name: "normal",
                 samples_count: msaa_samples,
                 format: vk::Format::R16G16B16A16_SFLOAT,
+                mip_levels: 1,
+            },
+            frame_buffer::AttachmentDesciption {  // roughness, metallic, occlusion
+                name: "material",
+                samples_count: msaa_samples,
+                format: vk::Format::R8G8B8A8_UNORM,
+                mip_levels: 1,
+            },
+            frame_buffer::AttachmentDesciption {  // emissive
+                name: "emissive",
+                samples_count: msaa_samples,
+                format: vk::Format::R16G16B16A16_SFLOAT,
+                mip_levels: 1,
             },
             frame_buffer::AttachmentDesciption {  // depth
+                name: "depth",
                 samples_count: msaa_samples,
                 format: vk::Format::D32_SFLOAT,
+                mip_levels: 1,
             },
-        ));
+        )).debug_name("gbuffer");
         offscreen_framebuffer.resize_swapchain(dimensions);
 
+        // lighting result before tone mapping
+        let mut hdr_framebuffer = frame_buffer::Framebuffer::new(env.clone(), vec!(
+            frame_buffer::AttachmentDesciption {
+                name: "color",
+                samples_count: vk::SampleCountFlags::TYPE_1,
+                format: vk::Format::R16G16B16A16_SFLOAT,
+                mip_levels: 1,
+            },
+        )).debug_name("hdr");
+        hdr_framebuffer.resize_swapchain(dimensions);
+
         let sync = sync::create_sync_objects(env.device());
 
-        let mut egui = Egui::new(env.clone(), swapchain_stuff.format, wnd.scale_factor(), dimensions, MAX_FRAMES_IN_FLIGHT, msaa_samples);
+        let mut egui = Egui::new(env.clone(), swapchain_stuff.format, scale_factor, dimensions, MAX_FRAMES_IN_FLIGHT, msaa_samples);
         egui.register_texture(0, offscreen_framebuffer.attachments[2].view, true);
 
-        let mut draw_mesh_render_system = PrimaryCommandBuffer::new(env.clone(), MAX_FRAMES_IN_FLIGHT);
+        let mut draw_mesh_render_system = PrimaryCommandBuffer::new(env.clone(), MAX_FRAMES_IN_FLIGHT).debug_name("geometry_pass");
         draw_mesh_render_system.set_dimensions(dimensions);
 
-        let mut quad_render_system = PrimaryCommandBuffer::new(env.clone(), MAX_FRAMES_IN_FLIGHT);
+        let mut quad_render_system = PrimaryCommandBuffer::new(env.clone(), MAX_FRAMES_IN_FLIGHT).debug_name("final_pass");
         quad_render_system.set_dimensions(dimensions);
 
+        let mut compose_render_system = PrimaryCommandBuffer::new(env.clone(), MAX_FRAMES_IN_FLIGHT).debug_name("compose_pass");
+        compose_render_system.set_dimensions(dimensions);
+
         let mesh = Arc::new(
             Mesh::load_from_file(env.clone(), Path::new("assets/chalet2.obj"))
         );
 
-        let mesh_renderer = MeshRenderer::new(
+        let mut mesh_renderer = MeshRenderer::new(
             env.clone(),
             offscreen_framebuffer.render_pass(),
             mesh.clone(),
             offscreen_framebuffer.attachments.len() - 1, // color attachments only
             msaa_samples,
+            camera.projection().reverse_z,
             MAX_FRAMES_IN_FLIGHT,
             dimensions,
         );
@@ -142,96 +270,140 @@ impl HelloApplication {
             offscreen_framebuffer.render_pass(),
             offscreen_framebuffer.attachments.len() - 1, // color attachments only
             msaa_samples,
+            camera.projection().reverse_z,
             MAX_FRAMES_IN_FLIGHT,
             dimensions,
         );
 
-        let height_map = HeightMap::from_png(Path::new("./assets/terrain/heightmap2.png"));
-        let terrain_data = TerrainData::new(env.clone(), height_map);
-        let terrain_renderer = TerrainRenderer::new(
+        let height_map = Rc::new(HeightMap::from_png(Path::new("./assets/terrain/heightmap2.png")));
+        let demo_lights = DemoLights::new(&height_map);
+        let terrain_data = TerrainData::new(env.clone(), &height_map);
+        let camera_mode = CameraMode::Fly;
+        let camera_controller = create_camera_controller(camera_mode, &camera, &height_map);
+        let mut terrain_renderer = TerrainRenderer::new(
             env.clone(),
             offscreen_framebuffer.render_pass(),
             offscreen_framebuffer.attachments.len() - 1,
             terrain_data, msaa_samples,
+            camera.projection().reverse_z,
             MAX_FRAMES_IN_FLIGHT,
             dimensions);
 
 
-        let mut shadow_map_fb = ShadowMapFramebuffer::new(env.clone());
-        egui.register_texture_layout(1, shadow_map_fb.get_cascade_view(0), vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
-        egui.register_texture_layout(2, shadow_map_fb.get_cascade_view(1), vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
-        egui.register_texture_layout(3, shadow_map_fb.get_cascade_view(2), vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
-        egui.register_texture_layout(4, shadow_map_fb.get_cascade_view(3), vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
+        let sun = DirectionalLight::default();
+        let shadows = CascadedShadows::new(env.clone(), &mut egui, &camera, &sun, MAX_FRAMES_IN_FLIGHT);
+        mesh_renderer.create_shadow_pipeline(shadows.framebuffer().render_pass());
+        terrain_renderer.create_shadow_pipeline(shadows.framebuffer().render_pass());
 
-        let mut shadowmap_pass_draw_commands = Vec::new();
+        let ssao = Ssao::new(env.clone(), &offscreen_framebuffer, dimensions, MAX_FRAMES_IN_FLIGHT);
 
-        for cascade_idx in 0..CASCADE_COUNT {
-            let mut shadowmap_pass_draw_command = PrimaryCommandBuffer::new(env.clone(), MAX_FRAMES_IN_FLIGHT);
-            shadowmap_pass_draw_command.set_dimensions([4096 as u32, 4096 as u32]);
+        let light_manager = LightManager::new(env.clone());
+        let shadow_atlas = ShadowAtlas::new(env.clone(), shadows.settings(), MAX_FRAMES_IN_FLIGHT);
+        let light_culling = LightCulling::new(
+            env.clone(),
+            &offscreen_framebuffer,
+            light_manager.buffer(),
+            msaa_samples,
+            dimensions,
+            MAX_FRAMES_IN_FLIGHT);
 
-            shadowmap_pass_draw_commands.push(shadowmap_pass_draw_command);
-        }
+        let quad_renderer = QuadRenderer::new(
+            env.clone(),
+            &offscreen_framebuffer,
+            shadows.framebuffer().view,
+            shadows.moments_view(),
+            ssao.output_view(),
+            light_manager.buffer(),
+            light_culling.tile_buffer(),
+            &shadow_atlas,
+            hdr_framebuffer.render_pass(),
+            msaa_samples,
+            shadows.settings(),
+            dimensions);
 
-        let mut mesh_shadow_map_renderers = Vec::new();
-        for _ in 0..CASCADE_COUNT {
-            mesh_shadow_map_renderers.push(
-                MeshShadowMapRenderer::new(
-                    env.clone(),
-                    shadow_map_fb.render_pass(),
-                    mesh.clone(),
-                    MAX_FRAMES_IN_FLIGHT,
-                    [4096, 4096],
-                )
-            );
-        }
+        let bloom_settings = BloomSettings::default();
+        let bloom = Bloom::new(
+            env.clone(),
+            hdr_framebuffer.attachments[0].view,
+            dimensions,
+            bloom_settings.mip_count,
+            MAX_FRAMES_IN_FLIGHT);
 
-        let cascade_split_lambda = 0.1;
-        let cascades = shadow_map_fb.update_cascades(&camera, cascade_split_lambda);
+        let auto_exposure = AutoExposure::new(
+            env.clone(),
+            hdr_framebuffer.attachments[0].view,
+            dimensions,
+            MAX_FRAMES_IN_FLIGHT);
 
-        let quad_renderer = QuadRenderer::new(
+        let tone_map_renderer = ToneMapRenderer::new(
             env.clone(),
-            &offscreen_framebuffer,
-            shadow_map_fb.view,
+            hdr_framebuffer.attachments[0].view,
+            bloom.output_view(),
+            auto_exposure.exposure_buffer(),
             quad_render_pass,
-            msaa_samples,
+            swapchain_stuff.format,
             dimensions);
 
         println!("created");
 
-        let tick_counter = FPSLimiter::new();
+        let frame_pacer = FramePacer::new();
+        let gpu_profiler = GpuProfiler::new(env.clone(), MAX_FRAMES_IN_FLIGHT);
         HelloApplication {
             env,
-            shadow_map_fb,
+            shadows,
+            shadow_atlas,
+            msaa_samples,
             final_pass_draw_command: quad_render_system,
+            compose_pass_draw_command: compose_render_system,
             geometry_pass_draw_command: draw_mesh_render_system,
-            shadowmap_pass_draw_commands,
 
             quad_renderer,
+            sun,
+            light_manager,
+            light_culling,
+            demo_lights,
+            ssao,
+            ssao_settings: SsaoSettings::default(),
+            bloom,
+            bloom_settings,
+            auto_exposure,
+            auto_exposure_settings: AutoExposureSettings::default(),
+            tone_map_renderer,
+            tone_map_settings: ToneMapSettings::default(),
             swapchain_stuff,
 
             sync,
             current_frame: 0,
             is_window_resized: false,
+            projection: *camera.projection(),
             camera,
+            camera_mode,
+            camera_controller,
+            input: InputState::new(InputSettings::default()),
+            camera_path: CameraPathControl::new(PLAYBACK_TIMESTEP),
+            cursor_hit: None,
+            height_map,
 
             offscreen_buffer: offscreen_framebuffer,
+            hdr_buffer: hdr_framebuffer,
 
             egui,
 
             clear_color: [0.0, 0.0, 0.0],
+            show_gui: true,
             final_render_pass: quad_render_pass,
 
             mesh,
             mesh_renderer,
-            mesh_shadow_map_renderers,
 
             skybox_renderer,
             terrain_renderer,
 
-            tick_counter,
-            cascades,
-            cascade_split_lambda,
-            egui_current_shadowmap_cascade_image: 1,
+            frame_pacer,
+            fixed_timestep: None,
+            gpu_profiler,
+
+            screenshots: Screenshots::new(),
         }
     }
 
@@ -255,29 +427,30 @@ impl HelloApplication {
                             *control_flow = ControlFlow::Exit;
                             return;
                         }
+
+                        self.screenshots.handle_key(&input);
                     }
 
                     if let WindowEvent::Resized(_) = event {
                         self.is_window_resized = true;
                     }
 
-                    if !self.egui.context().is_pointer_over_area() {
-                        let changed = self.camera.handle_event(&event);
-                        if changed {
-                            self.cascades = self.shadow_map_fb.update_cascades(&self.camera, self.cascade_split_lambda);
-                        }
-                    }
+                    self.input.handle_window_event(&event, !self.egui.context().is_pointer_over_area());
 
-                    if !self.camera.mouse_acquired() {
+                    if !self.input.looking() {
                         self.egui.handle_event(&event);
                     }
                 }
+                Event::DeviceEvent { event, .. } => {
+                    self.input.handle_device_event(&event);
+                }
                 Event::MainEventsCleared => {
                     wnd.request_redraw()
                 }
                 Event::RedrawRequested(_) => {
-                    self.draw_frame(&wnd);
-                    self.tick_counter.tick_frame();
+                    self.input.update_cursor(&wnd);
+                    self.draw_frame(Some(&wnd));
+                    self.frame_pacer.tick_frame();
                 }
                 // Important!
                 Event::LoopDestroyed => {
@@ -288,36 +461,92 @@ impl HelloApplication {
         })
     }
 
-    fn draw_frame(&mut self, wnd: &winit::window::Window) {
+    // Renders `frames` frames offscreen and reads back the last one
+    pub fn render_headless(&mut self, frames: u32) -> image::RgbaImage {
+        for _ in 0..frames {
+            self.draw_frame(None);
+        }
+
+        unsafe {
+            self.env.device()
+                .device_wait_idle()
+                .expect("Failed to wait device idle!")
+        };
+
+        let size = [self.swapchain_stuff.size.width, self.swapchain_stuff.size.height];
+        texture_utils::read_image(
+            &self.env,
+            self.swapchain_stuff.images[0],
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            self.swapchain_stuff.format,
+            size,
+            vk::SampleCountFlags::TYPE_1,
+            0,
+        ).to_rgba8()
+    }
+
+    pub fn set_camera(&mut self, position: Point3<f32>, yaw: f32, pitch: f32) {
+        self.camera.set_position(position);
+        self.camera.set_rotation(yaw, pitch);
+        self.camera_controller = create_camera_controller(self.camera_mode, &self.camera, &self.height_map);
+        self.shadows.update_cascades(&self.camera, &self.sun);
+    }
+
+    fn delta_time(&self) -> f32 {
+        self.fixed_timestep.unwrap_or_else(|| self.frame_pacer.delta_time())
+    }
+
+    // `wnd` is None for offscreen swapchain: no acquire / present
+    fn draw_frame(&mut self, wnd: Option<&winit::window::Window>) {
         let wait_fences = [self.sync.inflight_fences[self.current_frame]];
 
-        let (image_index, _is_sub_optimal) = unsafe {
+        unsafe {
             self.env.device()
                 .wait_for_fences(&wait_fences, true, u64::MAX)
                 .expect("Failed to wait for Fence!");
+        }
 
-            let result = self.swapchain_stuff.swapchain_api
-                .acquire_next_image(
-                    self.swapchain_stuff.swapchain,
-                    u64::MAX,
-                    self.sync.image_available_semaphores[self.current_frame],
-                    vk::Fence::null(),
-                );
+        let offscreen = self.swapchain_stuff.is_offscreen();
+        let image_index = if offscreen {
+            0
+        } else {
+            let result = unsafe {
+                self.swapchain_stuff.swapchain_api
+                    .acquire_next_image(
+                        self.swapchain_stuff.swapchain,
+                        u64::MAX,
+                        self.sync.image_available_semaphores[self.current_frame],
+                        vk::Fence::null(),
+                    )
+            };
             match result {
-                Ok(image_index) => image_index,
+                Ok((image_index, _is_sub_optimal)) => image_index,
                 Err(vk_result) => match vk_result {
                     vk::Result::ERROR_OUT_OF_DATE_KHR => {
-                        self.recreate_swapchain(&wnd);
+                        self.recreate_swapchain(wnd.expect("Window is required for swapchain!"));
                         return;
                     }
                     _ => panic!("Failed to acquire Swap Chain Image!"),
                 },
             }
         };
-        let wait_semaphores = [self.sync.image_available_semaphores[self.current_frame]];
+        self.gpu_profiler.begin_frame();
+        let delta_time = self.delta_time();
+
+        // offscreen image is not acquired and not presented
+        let (wait_semaphores, present_semaphores) = if offscreen {
+            (vec![], vec![])
+        } else {
+            (vec![self.sync.image_available_semaphores[self.current_frame]], vec![self.sync.render_quad_semaphore])
+        };
         let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        // light culling compute shader reads G-buffer
+        let composite_wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::COMPUTE_SHADER];
         let first_pass_finished = [self.sync.render_finished_semaphores[self.current_frame]];
-        let second_pass_finished = [self.sync.render_quad_semaphore];
+
+        if self.projection != *self.camera.projection() {
+            self.apply_projection();
+        }
 
         let clear_values = vec![
             vk::ClearValue {
@@ -335,50 +564,106 @@ impl HelloApplication {
                     float32: [0.0, 0.0, 0.0, 1.0],
                 },
             },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [1.0, 0.0, 1.0, 1.0],
+                },
+            },
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 1.0],
+                },
+            },
             vk::ClearValue {
                 depth_stencil: vk::ClearDepthStencilValue {
-                    depth: 1.0,
+                    depth: self.camera.projection().depth_clear_value(),
                     stencil: 0,
                 }
             },
         ];
 
-        let shadow_map_clear = vec![vk::ClearValue {
-            depth_stencil: vk::ClearDepthStencilValue {
-                depth: 1.0,
-                stencil: 0,
+        if self.shadows.needs_rebuild() {
+            self.rebuild_shadows();
+        }
+
+        // frame of camera path is rendered (captured if capture is running)
+        let mut path_frame = false;
+        let camera_moved = match self.camera_path.update(&mut self.camera, &mut self.input) {
+            Some(playing) => {
+                if !playing {
+                    self.camera_controller = create_camera_controller(self.camera_mode, &self.camera, &self.height_map);
+                }
+                path_frame = playing;
+                playing
             }
-        }];
-
-        let mut cascade_draws = Vec::new();
-        for (cascade_idx, cascade) in self.cascades.iter().enumerate() {
-            let mesh_shadowmap_draw = self.mesh_shadow_map_renderers[cascade_idx].draw(&self.camera, cascade.view_proj_mat);
-
-            cascade_draws.push(
-                self.shadowmap_pass_draw_commands[cascade_idx].execute_secondary(
-                    shadow_map_clear.clone(),
-                    self.shadow_map_fb.framebuffer(cascade_idx),
-                    self.shadow_map_fb.render_pass(),
-                    &[mesh_shadowmap_draw],
-                )
-            );
+            None => self.camera_controller.update(&mut self.camera, &mut self.input, delta_time),
+        };
+        self.camera_path.record(&self.camera, delta_time);
+        let sun_moved = self.sun.update(delta_time);
+        if camera_moved || sun_moved {
+            self.shadows.update_cascades(&self.camera, &self.sun);
         }
+        self.cursor_hit = self.pick_cursor();
+
+        self.update_lights(delta_time);
 
-        self.quad_renderer.write_shadowmap_ubo(self.camera.view_matrix(), &self.cascades);
+        let casters: [&dyn ShadowCaster; 2] = [&self.terrain_renderer, &self.mesh_renderer];
+        let (cascades_cmd, evsm_cmd) = self.shadows.execute(&casters);
+        let shadow_atlas_cmd = self.shadow_atlas.execute(&casters);
 
-        let mesh_draw = self.mesh_renderer.draw(self.camera.view_matrix(), self.camera.proj_matrix());
-        let terrain_draw = self.terrain_renderer.draw(self.camera.view_matrix(), self.camera.proj_matrix());
+        self.quad_renderer.set_ssao_debug(self.ssao_settings.enabled && self.ssao_settings.debug_view);
+        self.quad_renderer.set_light_heatmap(self.demo_lights.show_heatmap());
+        self.quad_renderer.set_cascade_blend(self.shadows.cascade_blend());
+        self.quad_renderer.set_debug_cascades(self.shadows.show_cascades());
+        self.quad_renderer.set_shadow_filter_params(self.shadows.filter_params());
+        self.quad_renderer.write_uniforms(self.camera.view_matrix(), self.shadows.cascades(), &self.sun);
+        self.ssao.write_settings(&self.ssao_settings, self.camera.view_matrix(), self.camera.proj_matrix());
+
+        let frustum = self.camera.frustum();
+        let mesh_draw = self.mesh_renderer.draw(self.camera.view_matrix(), self.camera.proj_matrix(), &frustum);
+        let terrain_draw = self.terrain_renderer.draw(self.camera.view_matrix(), self.camera.proj_matrix(), &frustum);
         let skybox_draw = self.skybox_renderer.draw(self.camera.skybox_view_matrix(), self.camera.proj_matrix());
 
+        let mut geometry_draws = vec![terrain_draw];
+        geometry_draws.extend(mesh_draw);
+        geometry_draws.push(skybox_draw);
+
         let geometry_pass_cmd = self.geometry_pass_draw_command.execute_secondary(
             clear_values,
             self.offscreen_buffer.framebuffer.unwrap(),
             self.offscreen_buffer.render_pass,
-            &[terrain_draw, mesh_draw, skybox_draw]);
+            &geometry_draws);
 
-        self.egui.begin_frame();
-        self.render_gui();
-        let gui_render_op = self.egui.end_frame(wnd);
+        let hdr_clear_values = vec![
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 1.0],
+                },
+            },
+        ];
+
+        let compose_cmd_buf = self.compose_pass_draw_command.execute_secondary(
+            hdr_clear_values,
+            self.hdr_buffer.framebuffer.unwrap(),
+            self.hdr_buffer.render_pass,
+            &[self.quad_renderer.second_buffer],
+        );
+
+        let gui_render_op = match wnd {
+            Some(wnd) if self.show_gui => {
+                self.egui.begin_frame();
+                self.render_gui();
+                Some(self.egui.end_frame(wnd))
+            }
+            _ => None,
+        };
+
+        if self.bloom_settings.mip_count != self.bloom.mip_count() {
+            self.rebuild_bloom();
+        }
+
+        let bloom_intensity = if self.bloom_settings.enabled { self.bloom_settings.intensity } else { 0.0 };
+        self.tone_map_renderer.write_settings(&self.tone_map_settings, self.auto_exposure_settings.enabled, bloom_intensity);
 
         let clear_values = vec![
             vk::ClearValue {
@@ -388,15 +673,71 @@ impl HelloApplication {
             },
         ];
 
+        let profiler = &mut self.gpu_profiler;
+        let mut mrt_pass = Vec::new();
+        profiler.begin_scope(&mut mrt_pass, "Frame");
+        profiler.begin_scope(&mut mrt_pass, "Shadows");
+        profiler.begin_scope(&mut mrt_pass, "Cascades");
+        mrt_pass.push(cascades_cmd);
+        profiler.end_scope(&mut mrt_pass);
+        profiler.begin_scope(&mut mrt_pass, "Shadow atlas");
+        mrt_pass.push(shadow_atlas_cmd);
+        profiler.end_scope(&mut mrt_pass);
+        profiler.end_scope(&mut mrt_pass);
+        profiler.begin_scope(&mut mrt_pass, "Geometry");
+        mrt_pass.push(geometry_pass_cmd);
+        profiler.end_scope(&mut mrt_pass);
+
+        let mut composite_pass = Vec::new();
+        profiler.begin_scope(&mut composite_pass, "Composite");
+        if let Some(evsm_cmd) = evsm_cmd {
+            profiler.begin_scope(&mut composite_pass, "EVSM");
+            composite_pass.push(evsm_cmd);
+            profiler.end_scope(&mut composite_pass);
+        }
+        profiler.begin_scope(&mut composite_pass, "SSAO");
+        composite_pass.extend(self.ssao.execute(self.ssao_settings.enabled));
+        profiler.end_scope(&mut composite_pass);
+        profiler.begin_scope(&mut composite_pass, "Light culling");
+        composite_pass.push(self.light_culling.execute(self.camera.view_matrix(), self.camera.proj_matrix()));
+        profiler.end_scope(&mut composite_pass);
+        profiler.begin_scope(&mut composite_pass, "Lighting");
+        composite_pass.push(compose_cmd_buf);
+        profiler.end_scope(&mut composite_pass);
+        if self.bloom_settings.enabled {
+            profiler.begin_scope(&mut composite_pass, "Bloom");
+            composite_pass.push(self.bloom.execute(&self.bloom_settings));
+            profiler.end_scope(&mut composite_pass);
+        }
+        if self.auto_exposure_settings.enabled {
+            profiler.begin_scope(&mut composite_pass, "Auto exposure");
+            composite_pass.push(self.auto_exposure.execute(&self.auto_exposure_settings, delta_time));
+            profiler.end_scope(&mut composite_pass);
+        }
+        profiler.end_scope(&mut composite_pass);
+
+        // tone map and GUI share the final render pass, timestamps go between secondary buffers
+        let mut final_pass_draws = Vec::new();
+        profiler.begin_scope_in_render_pass(&mut final_pass_draws, self.final_render_pass, "Tone map");
+        final_pass_draws.push(self.tone_map_renderer.second_buffer);
+        profiler.end_scope_in_render_pass(&mut final_pass_draws, self.final_render_pass);
+        if let Some(gui_render_op) = gui_render_op {
+            profiler.begin_scope_in_render_pass(&mut final_pass_draws, self.final_render_pass, "GUI");
+            final_pass_draws.push(gui_render_op);
+            profiler.end_scope_in_render_pass(&mut final_pass_draws, self.final_render_pass);
+        }
+
         let quad_cmd_buf = self.final_pass_draw_command.execute_secondary(
             clear_values,
             self.swapchain_stuff.framebuffers[image_index as usize],
-            self.quad_renderer.render_pass,
-            &[self.quad_renderer.second_buffer, gui_render_op],
+            self.final_render_pass,
+            &final_pass_draws,
         );
 
-        let mrt_pass: Vec<vk::CommandBuffer> = cascade_draws.iter().chain(vec!(geometry_pass_cmd).iter()).copied().collect();
-        let composite_pass = [quad_cmd_buf];
+        profiler.begin_scope(&mut composite_pass, "Final");
+        composite_pass.push(quad_cmd_buf);
+        profiler.end_scope(&mut composite_pass);
+        profiler.end_scope(&mut composite_pass);
 
         let submit_infos = [
             vk::SubmitInfo {
@@ -415,11 +756,11 @@ impl HelloApplication {
                 p_next: ptr::null(),
                 wait_semaphore_count: first_pass_finished.len() as u32,
                 p_wait_semaphores: first_pass_finished.as_ptr(),
-                p_wait_dst_stage_mask: wait_stages.as_ptr(),
-                command_buffer_count: 1,
+                p_wait_dst_stage_mask: composite_wait_stages.as_ptr(),
+                command_buffer_count: composite_pass.len() as u32,
                 p_command_buffers: composite_pass.as_ptr(),
-                signal_semaphore_count: second_pass_finished.len() as u32,
-                p_signal_semaphores: second_pass_finished.as_ptr(),
+                signal_semaphore_count: present_semaphores.len() as u32,
+                p_signal_semaphores: present_semaphores.as_ptr(),
             },
         ];
 
@@ -436,13 +777,32 @@ impl HelloApplication {
                 )
                 .expect("Failed to execute queue submit.");
         }
+
+        // swapchain image is still acquired here, it is read before present
+        if self.screenshots.take_request() {
+            self.take_screenshot(image_index as usize);
+        }
+        if self.camera_path.is_capturing() {
+            if path_frame {
+                self.capture_frame(image_index as usize);
+            }
+            if !self.camera_path.is_playing() {
+                self.finish_capture();
+            }
+        }
+
+        let wnd = match wnd {
+            Some(wnd) if !offscreen => wnd,
+            _ => return,
+        };
+
         let swapchains = [self.swapchain_stuff.swapchain];
 
         let present_info = vk::PresentInfoKHR {
             s_type: vk::StructureType::PRESENT_INFO_KHR,
             p_next: ptr::null(),
             wait_semaphore_count: 1,
-            p_wait_semaphores: second_pass_finished.as_ptr(),
+            p_wait_semaphores: present_semaphores.as_ptr(),
             swapchain_count: 1,
             p_swapchains: swapchains.as_ptr(),
             p_image_indices: &image_index,
@@ -486,22 +846,81 @@ impl HelloApplication {
             ui.label(format!("X: {:.2}, Y: {:.2}, Z: {:.2}", camera_pos.x, camera_pos.y, camera_pos.z));
 
             ui.label(format!("X: {:.2}, Y: {:.2}, Z: {:.2}", view_dir.x, view_dir.y, view_dir.z));
-            ui.label(format!("FPS: {:.2}", self.tick_counter.fps()));
-
-            egui::ComboBox::from_label("Shadow map data")
-                .selected_text(format!("{}", self.egui_current_shadowmap_cascade_image))
-                .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut self.egui_current_shadowmap_cascade_image, 1, "1");
-                    ui.selectable_value(&mut self.egui_current_shadowmap_cascade_image, 2, "2");
-                    ui.selectable_value(&mut self.egui_current_shadowmap_cascade_image, 3, "3");
-                    ui.selectable_value(&mut self.egui_current_shadowmap_cascade_image, 4, "4");
-                });
-            ui.image(egui::TextureId::User(self.egui_current_shadowmap_cascade_image as u64), [200.0, 200.0]);
-
-            let resp = ui.add(egui::DragValue::new(&mut self.cascade_split_lambda).speed(0.01).clamp_range(RangeInclusive::new(0.1, 1.0)));
-            if resp.changed() {
-                self.cascades = self.shadow_map_fb.update_cascades(&self.camera, self.cascade_split_lambda);
+            ui.label(format!("FPS: {:.2}", self.frame_pacer.fps()));
+            ui.collapsing("Frame time", |ui| frame_pacer_panel(ui, &mut self.frame_pacer));
+            ui.collapsing("GPU timings", |ui| {
+                gpu_profiler_panel(ui, &mut self.gpu_profiler, Path::new(GPU_TRACE_FILE), GPU_TRACE_FRAMES)
+            });
+            let screenshot_sources: Vec<(ScreenshotSource, String)> = self.screenshot_sources().into_iter()
+                .map(|source| (source, self.screenshot_source_name(source)))
+                .collect();
+            ui.collapsing("Screenshot", |ui| self.screenshots.gui(ui, &screenshot_sources));
+            match self.cursor_hit {
+                Some((pos, object)) => ui.label(format!("Cursor: X: {:.2}, Y: {:.2}, Z: {:.2} ({})", pos.x, pos.y, pos.z, object)),
+                None => ui.label("Cursor: -"),
+            };
+            ui.label(format!("Terrain chunks drawn: {} / {}",
+                             self.terrain_renderer.visible_chunk_count(), self.terrain_renderer.chunk_count()));
+
+            if camera_mode(ui, &mut self.camera_mode) {
+                self.camera_controller = create_camera_controller(self.camera_mode, &self.camera, &self.height_map);
+            }
+
+            let can_capture = self.swapchain_stuff.supports_readback;
+            let event = ui.collapsing("Camera path", |ui| self.camera_path.gui(ui, &self.camera, can_capture))
+                .body_returned
+                .flatten();
+            match event {
+                Some(CameraPathEvent::Stopped) => {
+                    self.camera_controller = create_camera_controller(self.camera_mode, &self.camera, &self.height_map);
+                }
+                Some(CameraPathEvent::CaptureStarted) => self.fixed_timestep = Some(PLAYBACK_TIMESTEP),
+                None => {}
+            }
+
+            // applied at the start of next frame, see `apply_projection`
+            ui.collapsing("Projection", |ui| projection_editor(ui, &mut self.projection));
+
+            ui.collapsing("Input", |ui| input_settings(ui, &mut self.input.settings));
+
+            self.shadows.gui(ui, &self.camera, &self.sun);
+
+            ui.separator();
+
+            if sun_editor(ui, &mut self.sun) {
+                self.shadows.update_cascades(&self.camera, &self.sun);
             }
+
+            let mut mesh_material = self.mesh_renderer.material();
+            if material_editor(ui, "Mesh material", &mut mesh_material) {
+                self.mesh_renderer.set_material(mesh_material);
+            }
+
+            let mut terrain_material = self.terrain_renderer.material();
+            if material_editor(ui, "Terrain material", &mut terrain_material) {
+                self.terrain_renderer.set_material(terrain_material);
+            }
+
+            ui.separator();
+
+            self.demo_lights.gui(ui);
+            ui.label(format!("Shadow atlas views: {}", self.shadow_atlas.view_count()));
+
+            ui.separator();
+
+            tonemap::settings_editor(ui, &mut self.tone_map_settings, self.swapchain_stuff.is_hdr());
+
+            ui.separator();
+
+            ssao::settings_editor(ui, &mut self.ssao_settings);
+
+            ui.separator();
+
+            bloom::settings_editor(ui, &mut self.bloom_settings);
+
+            ui.separator();
+
+            auto_exposure::settings_editor(ui, &mut self.auto_exposure_settings, &self.auto_exposure.state());
         });
     }
 
@@ -511,9 +930,10 @@ impl HelloApplication {
                 .device_wait_idle()
                 .expect("Failed to wait device idle!")
         };
+        let hdr = self.swapchain_stuff.is_hdr();
         self.cleanup_swapchain();
 
-        self.swapchain_stuff = ash_render_env::swapchain::SwapChain::new(&self.env, wnd.inner_size());
+        self.swapchain_stuff = ash_render_env::swapchain::SwapChain::new(&self.env, wnd.inner_size(), hdr);
         self.swapchain_stuff.create_framebuffers(self.env.device(), self.final_render_pass);
 
         let dimensions = [self.swapchain_stuff.size.width, self.swapchain_stuff.size.height];
@@ -521,10 +941,19 @@ impl HelloApplication {
         self.final_pass_draw_command.set_dimensions(dimensions);
 
         self.offscreen_buffer.resize_swapchain(dimensions);
+        self.hdr_buffer.resize_swapchain(dimensions);
+        self.compose_pass_draw_command.set_dimensions(dimensions);
         self.egui.set_dimensions(dimensions);
         self.egui.register_texture(0, self.offscreen_buffer.attachments[2].view, true);
 
-        self.quad_renderer.update_framebuffer(&self.offscreen_buffer, self.shadow_map_fb.view, dimensions);
+        self.ssao.update_framebuffer(&self.offscreen_buffer, dimensions);
+        self.light_culling.update_framebuffer(&self.offscreen_buffer, dimensions);
+        self.quad_renderer.update_framebuffer(&self.offscreen_buffer, self.shadows.framebuffer().view,
+                                              self.shadows.moments_view(),
+                                              self.ssao.output_view(), self.light_culling.tile_buffer(), dimensions);
+        self.bloom.resize(self.hdr_buffer.attachments[0].view, dimensions, self.bloom_settings.mip_count);
+        self.auto_exposure.update_framebuffer(self.hdr_buffer.attachments[0].view, dimensions);
+        self.tone_map_renderer.update_framebuffer(self.hdr_buffer.attachments[0].view, self.bloom.output_view(), dimensions);
         self.mesh_renderer.resize_framebuffer(dimensions);
         self.skybox_renderer.resize_framebuffer(dimensions);
         self.terrain_renderer.resize_framebuffer(dimensions);
@@ -532,6 +961,265 @@ impl HelloApplication {
         self.camera.set_viewport(dimensions[0], dimensions[1]);
     }
 
+    // Sources that can be read back, only these are offered
+    fn screenshot_sources(&self) -> Vec<ScreenshotSource> {
+        let mut sources = vec![ScreenshotSource::Swapchain];
+        sources.extend((0..self.offscreen_buffer.attachments.len()).map(ScreenshotSource::GBuffer));
+        sources.push(ScreenshotSource::Hdr);
+        sources.extend((0..self.shadows.framebuffer().cascade_count()).map(ScreenshotSource::ShadowCascade));
+        sources.retain(|source| self.screenshot_image(*source, 0).is_some());
+        sources
+    }
+
+    fn screenshot_source_name(&self, source: ScreenshotSource) -> String {
+        match source {
+            ScreenshotSource::Swapchain => "swapchain".to_string(),
+            ScreenshotSource::GBuffer(idx) => format!("gbuffer.{}", self.offscreen_buffer.attachment_desc()[idx].name),
+            ScreenshotSource::Hdr => "hdr".to_string(),
+            ScreenshotSource::ShadowCascade(idx) => format!("cascade{}", idx),
+        }
+    }
+
+    // None if source can't be read back
+    fn screenshot_image(&self, source: ScreenshotSource, image_index: usize) -> Option<ScreenshotImage> {
+        let framebuffer_image = |framebuffer: &frame_buffer::Framebuffer, idx: usize| ScreenshotImage {
+            image: framebuffer.attachments[idx].image,
+            layout: framebuffer.attachment_layout(idx),
+            format: framebuffer.attachments[idx].format,
+            size: framebuffer.dimensions(),
+            samples: framebuffer.attachment_desc()[idx].samples_count,
+            layer: 0,
+        };
+
+        let image = match source {
+            ScreenshotSource::Swapchain if self.swapchain_stuff.supports_readback => ScreenshotImage {
+                image: self.swapchain_stuff.images[image_index],
+                layout: if self.swapchain_stuff.is_offscreen() {
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+                } else {
+                    vk::ImageLayout::PRESENT_SRC_KHR
+                },
+                format: self.swapchain_stuff.format,
+                size: [self.swapchain_stuff.size.width, self.swapchain_stuff.size.height],
+                samples: vk::SampleCountFlags::TYPE_1,
+                layer: 0,
+            },
+            ScreenshotSource::Swapchain => return None,
+            ScreenshotSource::GBuffer(idx) => framebuffer_image(&self.offscreen_buffer, idx),
+            ScreenshotSource::Hdr => framebuffer_image(&self.hdr_buffer, 0),
+            ScreenshotSource::ShadowCascade(idx) if idx < self.shadows.framebuffer().cascade_count() => {
+                let settings = self.shadows.settings();
+                ScreenshotImage {
+                    image: self.shadows.framebuffer().image(),
+                    layout: vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+                    format: settings.depth_format,
+                    size: [settings.resolution, settings.resolution],
+                    samples: vk::SampleCountFlags::TYPE_1,
+                    layer: idx as u32,
+                }
+            }
+            ScreenshotSource::ShadowCascade(_) => return None,
+        };
+
+        Some(image).filter(|image| image.is_readable())
+    }
+
+    // Plays `path` with fixed `timestep` (independent of real frame time) and writes every frame
+    // of swapchain image to `capture`
+    pub fn start_capture(&mut self, path: CameraPath, timestep: f32, capture: FrameCapture) {
+        assert!(self.swapchain_stuff.supports_readback, "Swapchain images can't be read back!");
+
+        self.camera_path.start_capture(path, timestep, capture);
+        self.fixed_timestep = Some(timestep);
+    }
+
+    // Renders offscreen until capture started with `start_capture` is finished. Returns number of
+    // written frames, capture stops at the first write error.
+    pub fn render_capture(&mut self) -> io::Result<u32> {
+        while self.camera_path.is_capturing() {
+            self.draw_frame(None);
+        }
+
+        self.camera_path.take_capture_result().expect("Capture is not started!")
+    }
+
+    fn capture_frame(&mut self, image_index: usize) {
+        let image = self.screenshot_image(ScreenshotSource::Swapchain, image_index)
+            .expect("Swapchain images can't be read back!");
+
+        unsafe {
+            self.env.device()
+                .wait_for_fences(&[self.sync.inflight_fences[self.current_frame]], true, u64::MAX)
+                .expect("Failed to wait for Fence!");
+        }
+
+        let frame = texture_utils::read_image(&self.env, image.image, image.layout, image.format, image.size, image.samples, image.layer)
+            .to_rgba8();
+        self.camera_path.capture_frame(&frame);
+        if !self.camera_path.is_playing() {
+            self.camera_controller = create_camera_controller(self.camera_mode, &self.camera, &self.height_map);
+        }
+    }
+
+    fn finish_capture(&mut self) {
+        self.camera_path.finish_capture();
+
+        // headless rendering keeps fixed timestep
+        if !self.swapchain_stuff.is_offscreen() {
+            self.fixed_timestep = None;
+        }
+    }
+
+    // Waits for the frame and saves selected source
+    fn take_screenshot(&mut self, image_index: usize) {
+        let source = self.screenshots.select_source(&self.screenshot_sources());
+        let name = self.screenshot_source_name(source);
+        let image = self.screenshot_image(source, image_index).expect("Screenshot source can't be read back!");
+
+        unsafe {
+            self.env.device()
+                .wait_for_fences(&[self.sync.inflight_fences[self.current_frame]], true, u64::MAX)
+                .expect("Failed to wait for Fence!");
+        }
+
+        self.screenshots.save(&self.env, &image, &name);
+    }
+
+    // Closest of terrain and mesh hits under cursor, nothing while cursor is over UI
+    fn pick_cursor(&self) -> Option<(Point3<f32>, &'static str)> {
+        let cursor = self.input.cursor_position()?;
+        if self.egui.context().is_pointer_over_area() {
+            return None;
+        }
+
+        let ray = self.camera.screen_ray(cursor[0], cursor[1]);
+        let max_distance = self.camera.projection().far_clip;
+
+        let terrain_hit = self.height_map.intersect_ray(&ray, max_distance)
+            .map(|point| (point.distance(ray.origin), "terrain"));
+        let mesh_hit = self.mesh.intersect_ray(&ray, &mesh_world_matrix())
+            .filter(|distance| *distance <= max_distance)
+            .map(|distance| (distance, "mesh"));
+
+        let (distance, object) = match (terrain_hit, mesh_hit) {
+            (Some(terrain), Some(mesh)) => if mesh.0 < terrain.0 { mesh } else { terrain },
+            (hit, None) | (None, hit) => hit?,
+        };
+
+        Some((ray.at(distance), object))
+    }
+
+    fn update_lights(&mut self, delta_time: f32) {
+        self.demo_lights.update(&mut self.light_manager, delta_time);
+        let shadow_views = self.shadow_atlas.update(
+            self.light_manager.lights(), &self.camera, self.swapchain_stuff.size.height);
+        self.light_manager.upload(&shadow_views);
+    }
+
+    fn rebuild_bloom(&mut self) {
+        unsafe {
+            self.env.device()
+                .device_wait_idle()
+                .expect("Failed to wait device idle!")
+        };
+
+        let dimensions = [self.swapchain_stuff.size.width, self.swapchain_stuff.size.height];
+        self.bloom.resize(self.hdr_buffer.attachments[0].view, dimensions, self.bloom_settings.mip_count);
+        self.tone_map_renderer.update_framebuffer(self.hdr_buffer.attachments[0].view, self.bloom.output_view(), dimensions);
+    }
+
+    fn apply_projection(&mut self) {
+        let rebuild_pipelines = self.projection.reverse_z != self.camera.projection().reverse_z;
+        self.camera.set_projection(self.projection);
+        if rebuild_pipelines {
+            self.rebuild_geometry_pipelines();
+        }
+        self.shadows.update_cascades(&self.camera, &self.sun);
+    }
+
+    // Depth compare op of geometry pipelines (and skybox depth) follows `Projection::reverse_z`, so
+    // the renderers are recreated when it is switched. Materials are kept.
+    fn rebuild_geometry_pipelines(&mut self) {
+        unsafe {
+            self.env.device()
+                .device_wait_idle()
+                .expect("Failed to wait device idle!")
+        };
+
+        let reverse_z = self.camera.projection().reverse_z;
+        let dimensions = [self.swapchain_stuff.size.width, self.swapchain_stuff.size.height];
+        let render_pass = self.offscreen_buffer.render_pass();
+        let color_attachment_count = self.offscreen_buffer.attachments.len() - 1;
+
+        let mesh_material = self.mesh_renderer.material();
+        self.mesh_renderer = MeshRenderer::new(
+            self.env.clone(),
+            render_pass,
+            self.mesh.clone(),
+            color_attachment_count,
+            self.msaa_samples,
+            reverse_z,
+            MAX_FRAMES_IN_FLIGHT,
+            dimensions,
+        );
+        self.mesh_renderer.set_material(mesh_material);
+        self.mesh_renderer.create_shadow_pipeline(self.shadows.framebuffer().render_pass());
+
+        self.skybox_renderer = SkyboxRenderer::new(
+            self.env.clone(),
+            render_pass,
+            color_attachment_count,
+            self.msaa_samples,
+            reverse_z,
+            MAX_FRAMES_IN_FLIGHT,
+            dimensions,
+        );
+
+        let terrain_material = self.terrain_renderer.material();
+        self.terrain_renderer = TerrainRenderer::new(
+            self.env.clone(),
+            render_pass,
+            color_attachment_count,
+            TerrainData::new(self.env.clone(), &self.height_map),
+            self.msaa_samples,
+            reverse_z,
+            MAX_FRAMES_IN_FLIGHT,
+            dimensions);
+        self.terrain_renderer.set_material(terrain_material);
+        self.terrain_renderer.create_shadow_pipeline(self.shadows.framebuffer().render_pass());
+    }
+
+    // Shadow map, shadow atlas, caster pipelines, EVSM moments and compose pipeline (cascade count and
+    // filter are specialization constants) depend on shadow settings, all of them are recreated
+    fn rebuild_shadows(&mut self) {
+        unsafe {
+            self.env.device()
+                .device_wait_idle()
+                .expect("Failed to wait device idle!")
+        };
+
+        self.shadows.rebuild(&mut self.egui, &self.camera, &self.sun);
+
+        self.mesh_renderer.create_shadow_pipeline(self.shadows.framebuffer().render_pass());
+        self.terrain_renderer.create_shadow_pipeline(self.shadows.framebuffer().render_pass());
+        self.shadow_atlas = ShadowAtlas::new(self.env.clone(), self.shadows.settings(), MAX_FRAMES_IN_FLIGHT);
+
+        let dimensions = [self.swapchain_stuff.size.width, self.swapchain_stuff.size.height];
+        self.quad_renderer = QuadRenderer::new(
+            self.env.clone(),
+            &self.offscreen_buffer,
+            self.shadows.framebuffer().view,
+            self.shadows.moments_view(),
+            self.ssao.output_view(),
+            self.light_manager.buffer(),
+            self.light_culling.tile_buffer(),
+            &self.shadow_atlas,
+            self.hdr_buffer.render_pass(),
+            self.msaa_samples,
+            self.shadows.settings(),
+            dimensions);
+    }
+
     fn cleanup_swapchain(&mut self) {
         self.swapchain_stuff.destroy();
     }
@@ -544,12 +1232,31 @@ impl Drop for HelloApplication {
             self.cleanup_swapchain();
 
             self.offscreen_buffer.destroy();
+            self.hdr_buffer.destroy();
             self.env.device().destroy_render_pass(self.final_render_pass, None);
         }
     }
 }
 
+// Walk mode follows terrain height
+fn create_camera_controller(mode: CameraMode, camera: &Camera, height_map: &Rc<HeightMap>) -> Box<dyn CameraController> {
+    let height_map = height_map.clone();
+    mode.create_controller(camera, Box::new(move |x, z| height_map.world_height(x, z)))
+}
+
 fn main() {
+    utils::logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match headless::HeadlessArgs::parse(&args) {
+        Ok(Some(headless_args)) => std::process::exit(headless::run(&headless_args)),
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    }
+
     let event_loop = EventLoop::new();
     let wnd = winit::window::WindowBuilder::new()
         .with_title("test")
@@ -557,6 +1264,6 @@ fn main() {
         .build(&event_loop)
         .expect("Failed to create window");
 
-    let mut app = HelloApplication::new(&wnd);
+    let mut app = HelloApplication::new(&wnd, args.iter().any(|arg| arg == "--hdr"));
     app.run(event_loop, wnd);
 }
@@ -0,0 +1,293 @@
+use std::ptr;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use ash_render_env::compute_pipeline::ComputePipeline;
+use ash_render_env::descriptor_set::{DescriptorSet, DescriptorSetBuilder};
+use ash_render_env::env::RenderEnv;
+use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+use ash_render_env::shader;
+
+use crate::post_process::create_linear_sampler;
+use crate::utils::storage_buffer::StorageBuffer;
+
+pub const HISTOGRAM_BINS: usize = 256;
+
+// Luminance range covered by histogram (log2 scale)
+const MIN_LOG_LUMINANCE: f32 = -10.0;
+const MAX_LOG_LUMINANCE: f32 = 6.0;
+
+const HISTOGRAM_GROUP_SIZE: u32 = 16;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AutoExposureSettings {
+    pub enabled: bool,
+    // darkest and brightest pixels excluded from average (fraction of non-black pixels)
+    pub low_percentile: f32,
+    pub high_percentile: f32,
+    pub min_ev: f32,
+    pub max_ev: f32,
+    // exponential adaptation speed (1/s) when scene becomes brighter / darker
+    pub speed_to_light: f32,
+    pub speed_to_dark: f32,
+    pub show_histogram: bool,
+}
+
+impl Default for AutoExposureSettings {
+    fn default() -> Self {
+        AutoExposureSettings {
+            enabled: true,
+            low_percentile: 0.5,
+            high_percentile: 0.95,
+            min_ev: -4.0,
+            max_ev: 12.0,
+            speed_to_light: 3.0,
+            speed_to_dark: 1.0,
+            show_histogram: false,
+        }
+    }
+}
+
+// GUI for eye adaptation settings with current exposure state and luminance histogram
+pub fn settings_editor(ui: &mut egui::Ui, settings: &mut AutoExposureSettings, state: &ExposureState) {
+    ui.checkbox(&mut settings.enabled, "Auto exposure");
+    if settings.enabled {
+        ui.add(egui::Slider::new(&mut settings.low_percentile, 0.0..=0.99).text("Low percentile"));
+        ui.add(egui::Slider::new(&mut settings.high_percentile, 0.01..=1.0).text("High percentile"));
+        if settings.high_percentile <= settings.low_percentile {
+            settings.high_percentile = settings.low_percentile + 0.01;
+        }
+
+        ui.add(egui::Slider::new(&mut settings.min_ev, -8.0..=20.0).text("Min EV100"));
+        ui.add(egui::Slider::new(&mut settings.max_ev, -8.0..=20.0).text("Max EV100"));
+        if settings.max_ev < settings.min_ev {
+            settings.max_ev = settings.min_ev;
+        }
+
+        ui.add(egui::Slider::new(&mut settings.speed_to_light, 0.1..=10.0).text("Adaptation speed (to light)"));
+        ui.add(egui::Slider::new(&mut settings.speed_to_dark, 0.1..=10.0).text("Adaptation speed (to dark)"));
+
+        ui.label(format!("Avg luminance: {:.4}", state.average_luminance));
+        ui.label(format!("EV100: {:.2} (target {:.2})", state.ev100, state.target_ev100));
+
+        ui.checkbox(&mut settings.show_histogram, "Show histogram");
+        if settings.show_histogram {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(256.0, 80.0), egui::Sense::hover());
+            let painter = ui.painter();
+            painter.rect_filled(rect, 0.0, egui::Color32::from_gray(20));
+
+            // bin 0 holds black pixels, skip it to keep scale readable
+            let max_count = state.histogram[1..].iter().copied().max().unwrap_or(0).max(1) as f32;
+            let bar_width = rect.width() / HISTOGRAM_BINS as f32;
+            for (i, count) in state.histogram.iter().enumerate().skip(1) {
+                let height = (*count as f32 / max_count).min(1.0) * rect.height();
+                let x = rect.left() + i as f32 * bar_width;
+                painter.rect_filled(
+                    egui::Rect::from_min_max(egui::pos2(x, rect.bottom() - height), egui::pos2(x + bar_width, rect.bottom())),
+                    0.0,
+                    egui::Color32::from_gray(200),
+                );
+            }
+        }
+    }
+}
+
+// Layout of `ExposureState` buffer in exposure_adaptation.comp
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ExposureState {
+    pub exposure: f32,
+    pub average_luminance: f32,
+    pub ev100: f32,
+    pub target_ev100: f32,
+    pub histogram: [u32; HISTOGRAM_BINS],
+}
+
+// Eye adaptation: luminance histogram of HDR image -> average luminance -> exposure.
+// Result stays on GPU (`exposure_buffer`) and is consumed by tone mapping pass.
+pub struct AutoExposure {
+    histogram_pipeline: ComputePipeline,
+    adaptation_pipeline: ComputePipeline,
+    histogram_descriptor_set: DescriptorSet,
+    adaptation_descriptor_set: DescriptorSet,
+
+    histogram_buffer: StorageBuffer<[u32; HISTOGRAM_BINS]>,
+    exposure_buffer: StorageBuffer<ExposureState>,
+
+    commands: PrimaryCommandBuffer,
+    sampler: vk::Sampler,
+    dimensions: [u32; 2],
+    env: Arc<RenderEnv>,
+}
+
+impl AutoExposure {
+    pub fn new(env: Arc<RenderEnv>, hdr_view: vk::ImageView, dimensions: [u32; 2], max_frames_in_flight: usize) -> AutoExposure {
+        let histogram_pipeline = ComputePipeline::new(
+            env.device().clone(),
+            shader::Shader::load(env.device(), "assets/shaders/spv/post_process/luminance_histogram.comp.spv"),
+        ).debug_name(&env, "luminance_histogram");
+
+        let adaptation_pipeline = ComputePipeline::new(
+            env.device().clone(),
+            shader::Shader::load(env.device(), "assets/shaders/spv/post_process/exposure_adaptation.comp.spv"),
+        ).debug_name(&env, "exposure_adaptation");
+
+        let sampler = create_linear_sampler(env.device());
+        env.set_name(sampler, "auto_exposure.sampler");
+        let histogram_buffer = StorageBuffer::new(env.clone(), [0; HISTOGRAM_BINS]).debug_name(&env, "auto_exposure.histogram");
+        let exposure_buffer = StorageBuffer::new(env.clone(), ExposureState {
+            exposure: 1.0,
+            average_luminance: 0.0,
+            ev100: 0.0,
+            target_ev100: 0.0,
+            histogram: [0; HISTOGRAM_BINS],
+        }).debug_name(&env, "auto_exposure.state");
+
+        let histogram_descriptor_set = DescriptorSetBuilder::new(
+            env.device(), histogram_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(hdr_view, sampler)
+            .add_buffer(histogram_buffer.buffer)
+            .build();
+
+        let adaptation_descriptor_set = DescriptorSetBuilder::new(
+            env.device(), adaptation_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_buffer(histogram_buffer.buffer)
+            .add_buffer(exposure_buffer.buffer)
+            .build();
+
+        AutoExposure {
+            histogram_pipeline,
+            adaptation_pipeline,
+            histogram_descriptor_set,
+            adaptation_descriptor_set,
+            histogram_buffer,
+            exposure_buffer,
+            commands: PrimaryCommandBuffer::new(env.clone(), max_frames_in_flight).debug_name("auto_exposure"),
+            sampler,
+            dimensions,
+            env,
+        }
+    }
+
+    pub fn exposure_buffer(&self) -> vk::Buffer {
+        self.exposure_buffer.buffer
+    }
+
+    // Last computed values, valid after frame fence is signaled
+    pub fn state(&self) -> ExposureState {
+        self.exposure_buffer.read_data()
+    }
+
+    pub fn update_framebuffer(&mut self, hdr_view: vk::ImageView, dimensions: [u32; 2]) {
+        self.histogram_descriptor_set = DescriptorSetBuilder::new(
+            self.env.device(), self.histogram_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(hdr_view, self.sampler)
+            .add_buffer(self.histogram_buffer.buffer)
+            .build();
+
+        self.dimensions = dimensions;
+    }
+
+    // Must be submitted after HDR image is rendered and before tone mapping
+    pub fn execute(&mut self, settings: &AutoExposureSettings, delta_time: f32) -> vk::CommandBuffer {
+        let log_luminance_range = MAX_LOG_LUMINANCE - MIN_LOG_LUMINANCE;
+
+        let mut histogram_params = Vec::new();
+        histogram_params.extend(MIN_LOG_LUMINANCE.to_le_bytes());
+        histogram_params.extend((1.0 / log_luminance_range).to_le_bytes());
+
+        let mut adaptation_params = Vec::new();
+        adaptation_params.extend(MIN_LOG_LUMINANCE.to_le_bytes());
+        adaptation_params.extend(log_luminance_range.to_le_bytes());
+        adaptation_params.extend(settings.low_percentile.to_le_bytes());
+        adaptation_params.extend(settings.high_percentile.to_le_bytes());
+        adaptation_params.extend(settings.min_ev.to_le_bytes());
+        adaptation_params.extend(settings.max_ev.to_le_bytes());
+        adaptation_params.extend(settings.speed_to_light.to_le_bytes());
+        adaptation_params.extend(settings.speed_to_dark.to_le_bytes());
+        adaptation_params.extend(delta_time.to_le_bytes());
+
+        let histogram_pipeline = &self.histogram_pipeline;
+        let adaptation_pipeline = &self.adaptation_pipeline;
+        let histogram_set = self.histogram_descriptor_set.set;
+        let adaptation_set = self.adaptation_descriptor_set.set;
+        let histogram_buffer = self.histogram_buffer.buffer;
+        let exposure_buffer = self.exposure_buffer.buffer;
+        let dimensions = self.dimensions;
+
+        self.commands.execute(|device, cmd_buf| unsafe {
+            // previous frame adaptation pass cleared histogram
+            compute_buffer_barrier(device, cmd_buf, histogram_buffer,
+                                   vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::COMPUTE_SHADER);
+
+            device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::COMPUTE, histogram_pipeline.pipeline);
+            device.cmd_bind_descriptor_sets(cmd_buf, vk::PipelineBindPoint::COMPUTE, histogram_pipeline.pipeline_layout,
+                                            0, &[histogram_set], &[]);
+            device.cmd_push_constants(cmd_buf, histogram_pipeline.pipeline_layout, vk::ShaderStageFlags::COMPUTE, 0,
+                                      &histogram_params);
+            device.cmd_dispatch(
+                cmd_buf,
+                (dimensions[0] + HISTOGRAM_GROUP_SIZE - 1) / HISTOGRAM_GROUP_SIZE,
+                (dimensions[1] + HISTOGRAM_GROUP_SIZE - 1) / HISTOGRAM_GROUP_SIZE,
+                1,
+            );
+
+            compute_buffer_barrier(device, cmd_buf, histogram_buffer,
+                                   vk::PipelineStageFlags::COMPUTE_SHADER, vk::PipelineStageFlags::COMPUTE_SHADER);
+
+            device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::COMPUTE, adaptation_pipeline.pipeline);
+            device.cmd_bind_descriptor_sets(cmd_buf, vk::PipelineBindPoint::COMPUTE, adaptation_pipeline.pipeline_layout,
+                                            0, &[adaptation_set], &[]);
+            device.cmd_push_constants(cmd_buf, adaptation_pipeline.pipeline_layout, vk::ShaderStageFlags::COMPUTE, 0,
+                                      &adaptation_params);
+            device.cmd_dispatch(cmd_buf, 1, 1, 1);
+
+            // exposure is read by tone mapping fragment shader and by host (debug view)
+            compute_buffer_barrier(device, cmd_buf, exposure_buffer,
+                                   vk::PipelineStageFlags::COMPUTE_SHADER,
+                                   vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::HOST);
+        })
+    }
+}
+
+impl Drop for AutoExposure {
+    fn drop(&mut self) {
+        unsafe {
+            self.env.device().destroy_sampler(self.sampler, None);
+        }
+    }
+}
+
+unsafe fn compute_buffer_barrier(device: &ash::Device, cmd_buf: vk::CommandBuffer, buffer: vk::Buffer,
+                                 src_stage: vk::PipelineStageFlags, dst_stage: vk::PipelineStageFlags) {
+    let mut dst_access_mask = vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE;
+    if dst_stage.contains(vk::PipelineStageFlags::HOST) {
+        dst_access_mask |= vk::AccessFlags::HOST_READ;
+    }
+
+    let buffer_barriers = [
+        vk::BufferMemoryBarrier {
+            s_type: vk::StructureType::BUFFER_MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: vk::AccessFlags::SHADER_WRITE,
+            dst_access_mask,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            buffer,
+            offset: 0,
+            size: vk::WHOLE_SIZE,
+        }
+    ];
+
+    device.cmd_pipeline_barrier(
+        cmd_buf,
+        src_stage,
+        dst_stage,
+        vk::DependencyFlags::empty(),
+        &[],
+        &buffer_barriers,
+        &[],
+    );
+}
@@ -0,0 +1,499 @@
+use std::ptr;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use ash_render_env::attachment_texture::AttachmentImage;
+use ash_render_env::descriptor_set::{DescriptorSet, DescriptorSetBuilder};
+use ash_render_env::env::RenderEnv;
+use ash_render_env::pipeline_builder::{Pipeline, PipelineBuilder};
+use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+use ash_render_env::shader;
+use ash_render_env::utils::buffer_utils::{begin_single_time_command, end_single_time_command};
+
+use crate::post_process::create_linear_sampler;
+
+const BLOOM_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+
+pub const MAX_BLOOM_MIPS: u32 = 8;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BloomSettings {
+    pub enabled: bool,
+    // weight of bloom added to HDR image
+    pub intensity: f32,
+    // brightness where bloom starts, knee makes transition smooth
+    pub threshold: f32,
+    pub knee: f32,
+    // levels of downsample chain, each one doubles bloom radius
+    pub mip_count: u32,
+    // upsample tent filter radius in source texels
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        BloomSettings {
+            enabled: true,
+            intensity: 0.05,
+            threshold: 1.0,
+            knee: 0.5,
+            mip_count: 6,
+            radius: 1.0,
+        }
+    }
+}
+
+pub fn settings_editor(ui: &mut egui::Ui, settings: &mut BloomSettings) {
+    ui.checkbox(&mut settings.enabled, "Bloom");
+    if settings.enabled {
+        ui.add(egui::Slider::new(&mut settings.intensity, 0.0..=1.0).text("Intensity"));
+        ui.add(egui::Slider::new(&mut settings.threshold, 0.0..=10.0).text("Threshold"));
+        ui.add(egui::Slider::new(&mut settings.knee, 0.0..=2.0).text("Knee"));
+        ui.add(egui::Slider::new(&mut settings.radius, 0.5..=3.0).text("Radius"));
+        ui.add(egui::Slider::new(&mut settings.mip_count, 1..=MAX_BLOOM_MIPS).text("Mip count"));
+    }
+}
+
+struct BloomMip {
+    framebuffer: vk::Framebuffer,
+    dimensions: [u32; 2],
+    // samples previous level (or input image for level 0)
+    downsample_set: DescriptorSet,
+    // samples next level, None for the smallest one
+    upsample_set: Option<DescriptorSet>,
+}
+
+// Post-process node: input HDR image -> threshold -> downsample chain -> upsample chain.
+// Result (mip 0 of `output_view`, half resolution) is added to HDR image by tone mapping pass.
+pub struct Bloom {
+    downsample_render_pass: vk::RenderPass,
+    upsample_render_pass: vk::RenderPass,
+
+    prefilter_pipeline: Pipeline,
+    downsample_pipeline: Pipeline,
+    upsample_pipeline: Pipeline,
+
+    image: AttachmentImage,
+    mips: Vec<BloomMip>,
+    mip_count: u32,
+
+    commands: PrimaryCommandBuffer,
+    sampler: vk::Sampler,
+    input_dimensions: [u32; 2],
+    env: Arc<RenderEnv>,
+}
+
+impl Bloom {
+    pub fn new(env: Arc<RenderEnv>, input_view: vk::ImageView, dimensions: [u32; 2], mip_count: u32,
+               max_frames_in_flight: usize) -> Bloom {
+        // mip N is cleared (DONT_CARE) by downsample and accumulated (LOAD) by upsample, passes are compatible
+        let downsample_render_pass = create_render_pass(
+            env.device(), vk::AttachmentLoadOp::DONT_CARE, vk::ImageLayout::UNDEFINED);
+        let upsample_render_pass = create_render_pass(
+            env.device(), vk::AttachmentLoadOp::LOAD, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        let downsample_shader = |prefilter: bool| {
+            shader::Shader::load(env.device(), "assets/shaders/spv/post_process/bloom_downsample.frag.spv")
+                .specialize(shader::ConstantsBuilder::new().add_u32(prefilter as u32))
+        };
+
+        let prefilter_pipeline = PipelineBuilder::new(env.device().clone(), downsample_render_pass, 0)
+            .vertex_shader(shader::Shader::load(env.device(), "assets/shaders/spv/compose.vert.spv"))
+            .fragment_shader(downsample_shader(true))
+            .debug_name(&env, "bloom_prefilter")
+            .build();
+
+        let downsample_pipeline = PipelineBuilder::new(env.device().clone(), downsample_render_pass, 0)
+            .vertex_shader(shader::Shader::load(env.device(), "assets/shaders/spv/compose.vert.spv"))
+            .fragment_shader(downsample_shader(false))
+            .debug_name(&env, "bloom_downsample")
+            .build();
+
+        let upsample_pipeline = PipelineBuilder::new(env.device().clone(), upsample_render_pass, 0)
+            .vertex_shader(shader::Shader::load(env.device(), "assets/shaders/spv/compose.vert.spv"))
+            .fragment_shader(shader::Shader::load(env.device(), "assets/shaders/spv/post_process/bloom_upsample.frag.spv"))
+            .additive_blend()
+            .debug_name(&env, "bloom_upsample")
+            .build();
+
+        let sampler = create_linear_sampler(env.device());
+        env.set_name(sampler, "bloom.sampler");
+        let image = create_bloom_image(&env, dimensions, mip_count);
+
+        let mut bloom = Bloom {
+            downsample_render_pass,
+            upsample_render_pass,
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            image,
+            mips: vec![],
+            mip_count,
+            commands: PrimaryCommandBuffer::new(env.clone(), max_frames_in_flight).debug_name("bloom"),
+            sampler,
+            input_dimensions: dimensions,
+            env,
+        };
+        bloom.create_mips(input_view);
+
+        bloom
+    }
+
+    // Bloom texture for tone mapping pass
+    pub fn output_view(&self) -> vk::ImageView {
+        self.image.view
+    }
+
+    pub fn mip_count(&self) -> u32 {
+        self.mip_count
+    }
+
+    // Device must be idle: all images and framebuffers are recreated
+    pub fn resize(&mut self, input_view: vk::ImageView, dimensions: [u32; 2], mip_count: u32) {
+        self.destroy_mips();
+
+        self.image = create_bloom_image(&self.env, dimensions, mip_count);
+        self.input_dimensions = dimensions;
+        self.mip_count = mip_count;
+
+        self.create_mips(input_view);
+    }
+
+    // Must be submitted after HDR image is rendered and before tone mapping
+    pub fn execute(&mut self, settings: &BloomSettings) -> vk::CommandBuffer {
+        let input_dimensions = self.input_dimensions;
+        let mips = &self.mips;
+        let downsample_render_pass = self.downsample_render_pass;
+        let upsample_render_pass = self.upsample_render_pass;
+        let prefilter_pipeline = &self.prefilter_pipeline;
+        let downsample_pipeline = &self.downsample_pipeline;
+        let upsample_pipeline = &self.upsample_pipeline;
+
+        self.commands.execute(|device, cmd_buf| {
+            for (level, mip) in mips.iter().enumerate() {
+                let (pipeline, src_dimensions) = if level == 0 {
+                    (prefilter_pipeline, input_dimensions)
+                } else {
+                    (downsample_pipeline, mips[level - 1].dimensions)
+                };
+
+                let mut params = Vec::new();
+                params.extend((1.0 / src_dimensions[0] as f32).to_le_bytes());
+                params.extend((1.0 / src_dimensions[1] as f32).to_le_bytes());
+                params.extend(settings.threshold.to_le_bytes());
+                params.extend(settings.knee.to_le_bytes());
+
+                draw_pass(device, cmd_buf, downsample_render_pass, mip.framebuffer, mip.dimensions,
+                          pipeline, &mip.downsample_set, &params);
+            }
+
+            for level in (0..mips.len().saturating_sub(1)).rev() {
+                let mip = &mips[level];
+                let src_dimensions = mips[level + 1].dimensions;
+
+                let mut params = Vec::new();
+                params.extend((1.0 / src_dimensions[0] as f32).to_le_bytes());
+                params.extend((1.0 / src_dimensions[1] as f32).to_le_bytes());
+                params.extend(settings.radius.to_le_bytes());
+
+                draw_pass(device, cmd_buf, upsample_render_pass, mip.framebuffer, mip.dimensions,
+                          upsample_pipeline, mip.upsample_set.as_ref().unwrap(), &params);
+            }
+        })
+    }
+
+    fn create_mips(&mut self, input_view: vk::ImageView) {
+        let mut dimensions = [
+            (self.input_dimensions[0] / 2).max(1),
+            (self.input_dimensions[1] / 2).max(1),
+        ];
+
+        for level in 0..self.image.mip_levels {
+            let view = self.image.mip_view(level);
+
+            let framebuffer_info = vk::FramebufferCreateInfo {
+                s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: Default::default(),
+                render_pass: self.downsample_render_pass,
+                attachment_count: 1,
+                p_attachments: &view,
+                width: dimensions[0],
+                height: dimensions[1],
+                layers: 1,
+            };
+
+            let framebuffer = unsafe {
+                self.env.device()
+                    .create_framebuffer(&framebuffer_info, None)
+                    .expect("Failed to create Framebuffer!")
+            };
+
+            let (src_view, src_pipeline) = if level == 0 {
+                (input_view, &self.prefilter_pipeline)
+            } else {
+                (self.image.mip_view(level - 1), &self.downsample_pipeline)
+            };
+
+            let downsample_set = DescriptorSetBuilder::new(
+                self.env.device(), src_pipeline.descriptor_set_layouts.get(0).unwrap())
+                .add_image(src_view, self.sampler)
+                .build();
+
+            let upsample_set = if level + 1 < self.image.mip_levels {
+                Some(DescriptorSetBuilder::new(
+                    self.env.device(), self.upsample_pipeline.descriptor_set_layouts.get(0).unwrap())
+                    .add_image(self.image.mip_view(level + 1), self.sampler)
+                    .build())
+            } else {
+                None
+            };
+
+            self.mips.push(BloomMip {
+                framebuffer,
+                dimensions,
+                downsample_set,
+                upsample_set,
+            });
+
+            dimensions = [(dimensions[0] / 2).max(1), (dimensions[1] / 2).max(1)];
+        }
+    }
+
+    fn destroy_mips(&mut self) {
+        for mip in self.mips.drain(..) {
+            unsafe {
+                self.env.device().destroy_framebuffer(mip.framebuffer, None);
+            }
+        }
+    }
+}
+
+impl Drop for Bloom {
+    fn drop(&mut self) {
+        self.destroy_mips();
+
+        unsafe {
+            self.env.device().destroy_sampler(self.sampler, None);
+            self.env.device().destroy_render_pass(self.downsample_render_pass, None);
+            self.env.device().destroy_render_pass(self.upsample_render_pass, None);
+        }
+    }
+}
+
+// Mip chain starts at half resolution and stops at 1 pixel
+fn create_bloom_image(env: &RenderEnv, input_dimensions: [u32; 2], mip_count: u32) -> AttachmentImage {
+    let dimensions = [(input_dimensions[0] / 2).max(1), (input_dimensions[1] / 2).max(1)];
+    let max_levels = 32 - dimensions[0].min(dimensions[1]).leading_zeros();
+    let mip_levels = mip_count.min(max_levels).max(1);
+
+    let image = AttachmentImage::new(
+        env,
+        dimensions,
+        BLOOM_FORMAT,
+        mip_levels,
+        vk::SampleCountFlags::TYPE_1,
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+    );
+
+    // tone mapping samples bloom even if the pass is disabled: start from black
+    let command_buffer = begin_single_time_command(env.device(), env.command_pool());
+
+    let subresource_range = vk::ImageSubresourceRange {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        base_mip_level: 0,
+        level_count: mip_levels,
+        base_array_layer: 0,
+        layer_count: 1,
+    };
+
+    unsafe {
+        let to_transfer = [vk::ImageMemoryBarrier {
+            s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: vk::AccessFlags::empty(),
+            dst_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            old_layout: vk::ImageLayout::UNDEFINED,
+            new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: image.image,
+            subresource_range,
+        }];
+
+        env.device().cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &to_transfer,
+        );
+
+        env.device().cmd_clear_color_image(
+            command_buffer,
+            image.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &vk::ClearColorValue { float32: [0.0, 0.0, 0.0, 0.0] },
+            &[subresource_range],
+        );
+
+        let to_shader_read = [vk::ImageMemoryBarrier {
+            s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+            p_next: ptr::null(),
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+            image: image.image,
+            subresource_range,
+        }];
+
+        env.device().cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::FRAGMENT_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &to_shader_read,
+        );
+    }
+
+    end_single_time_command(env.device(), env.command_pool(), env.queue(), command_buffer);
+
+    image
+}
+
+fn create_render_pass(device: &ash::Device, load_op: vk::AttachmentLoadOp, initial_layout: vk::ImageLayout) -> vk::RenderPass {
+    let color_attachment = vk::AttachmentDescription {
+        format: BLOOM_FORMAT,
+        flags: vk::AttachmentDescriptionFlags::empty(),
+        samples: vk::SampleCountFlags::TYPE_1,
+        load_op,
+        store_op: vk::AttachmentStoreOp::STORE,
+        stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
+        stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+        initial_layout,
+        final_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    };
+
+    let color_attachment_ref = vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    };
+
+    let subpasses = [
+        vk::SubpassDescription {
+            color_attachment_count: 1,
+            p_color_attachments: &color_attachment_ref,
+            p_depth_stencil_attachment: ptr::null(),
+            flags: vk::SubpassDescriptionFlags::empty(),
+            pipeline_bind_point: vk::PipelineBindPoint::GRAPHICS,
+            input_attachment_count: 0,
+            p_input_attachments: ptr::null(),
+            p_resolve_attachments: ptr::null(),
+            preserve_attachment_count: 0,
+            p_preserve_attachments: ptr::null(),
+        }
+    ];
+
+    let render_pass_attachments = [color_attachment];
+
+    let subpass_dependencies = [
+        // previous pass could sample this level or write it (upsample accumulates into downsample result)
+        vk::SubpassDependency {
+            src_subpass: vk::SUBPASS_EXTERNAL,
+            dst_subpass: 0,
+            src_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dependency_flags: vk::DependencyFlags::empty(),
+        },
+        // result is sampled by the next level and by tone mapping
+        vk::SubpassDependency {
+            src_subpass: 0,
+            dst_subpass: vk::SUBPASS_EXTERNAL,
+            src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER,
+            src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            dst_access_mask: vk::AccessFlags::SHADER_READ,
+            dependency_flags: vk::DependencyFlags::empty(),
+        }
+    ];
+
+    let renderpass_create_info = vk::RenderPassCreateInfo {
+        s_type: vk::StructureType::RENDER_PASS_CREATE_INFO,
+        flags: vk::RenderPassCreateFlags::empty(),
+        p_next: ptr::null(),
+        attachment_count: render_pass_attachments.len() as u32,
+        p_attachments: render_pass_attachments.as_ptr(),
+        subpass_count: subpasses.len() as u32,
+        p_subpasses: subpasses.as_ptr(),
+        dependency_count: subpass_dependencies.len() as u32,
+        p_dependencies: subpass_dependencies.as_ptr(),
+    };
+
+    unsafe {
+        device
+            .create_render_pass(&renderpass_create_info, None)
+            .expect("Failed to create render pass!")
+    }
+}
+
+fn draw_pass(device: &ash::Device, cmd_buf: vk::CommandBuffer, render_pass: vk::RenderPass, framebuffer: vk::Framebuffer,
+             dimensions: [u32; 2], pipeline: &Pipeline, descriptor_set: &DescriptorSet, params: &[u8]) {
+    let extent = vk::Extent2D {
+        width: dimensions[0],
+        height: dimensions[1],
+    };
+
+    let render_pass_begin_info = vk::RenderPassBeginInfo {
+        s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+        p_next: ptr::null(),
+        render_pass,
+        framebuffer,
+        render_area: vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent,
+        },
+        clear_value_count: 0,
+        p_clear_values: ptr::null(),
+    };
+
+    let viewports = [vk::Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: dimensions[0] as f32,
+        height: dimensions[1] as f32,
+        min_depth: 0.0,
+        max_depth: 1.0,
+    }];
+
+    let scissors = [vk::Rect2D {
+        offset: vk::Offset2D { x: 0, y: 0 },
+        extent,
+    }];
+
+    unsafe {
+        device.cmd_begin_render_pass(cmd_buf, &render_pass_begin_info, vk::SubpassContents::INLINE);
+
+        device.cmd_set_viewport(cmd_buf, 0, &viewports);
+        device.cmd_set_scissor(cmd_buf, 0, &scissors);
+
+        device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::GRAPHICS, pipeline.graphics_pipeline);
+        device.cmd_bind_descriptor_sets(cmd_buf, vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline_layout,
+                                        0, &[descriptor_set.set], &[]);
+        device.cmd_push_constants(cmd_buf, pipeline.pipeline_layout, vk::ShaderStageFlags::FRAGMENT, 0, params);
+
+        device.cmd_draw(cmd_buf, 3, 1, 0, 0);
+
+        device.cmd_end_render_pass(cmd_buf);
+    }
+}
@@ -0,0 +1,94 @@
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use ash_render_env::descriptor_set::DescriptorSet;
+use ash_render_env::env::RenderEnv;
+use ash_render_env::pipeline_builder::Pipeline;
+
+// Secondary command buffer that draws one full-screen triangle (compose.vert) with given pipeline
+pub fn record_fullscreen_pass(env: &RenderEnv, dimensions: [u32; 2], pipeline: &Pipeline, descriptor_set: &DescriptorSet, render_pass: vk::RenderPass) -> vk::CommandBuffer {
+    let device = env.device();
+    let cmd_buf = env.create_secondary_command_buffer();
+
+    let viewports = [vk::Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: dimensions[0] as f32,
+        height: dimensions[1] as f32,
+        min_depth: 0.0,
+        max_depth: 1.0,
+    }];
+
+    let scissors = [vk::Rect2D {
+        offset: vk::Offset2D { x: 0, y: 0 },
+        extent: vk::Extent2D {
+            width: dimensions[0],
+            height: dimensions[1],
+        },
+    }];
+
+    unsafe {
+        let inheritance_info = vk::CommandBufferInheritanceInfo {
+            s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
+            p_next: ptr::null(),
+            render_pass,
+            subpass: 0,
+            framebuffer: vk::Framebuffer::null(),
+            occlusion_query_enable: 0,
+            query_flags: Default::default(),
+            pipeline_statistics: Default::default(),
+        };
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+            s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+            p_next: ptr::null(),
+            p_inheritance_info: &inheritance_info,
+            flags: vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE | vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
+        };
+
+        device
+            .begin_command_buffer(cmd_buf, &command_buffer_begin_info)
+            .expect("Failed to begin recording Command Buffer at beginning!");
+
+        device.cmd_set_viewport(cmd_buf, 0, viewports.as_ref());
+        device.cmd_set_scissor(cmd_buf, 0, scissors.as_ref());
+
+        device.cmd_bind_pipeline(
+            cmd_buf,
+            vk::PipelineBindPoint::GRAPHICS,
+            pipeline.graphics_pipeline,
+        );
+
+        let descriptor_sets_to_bind = [descriptor_set.set];
+        device.cmd_bind_descriptor_sets(
+            cmd_buf,
+            vk::PipelineBindPoint::GRAPHICS,
+            pipeline.pipeline_layout,
+            0,
+            &descriptor_sets_to_bind,
+            &[],
+        );
+
+        device.cmd_draw(cmd_buf, 3, 1, 0, 0);
+
+        device.end_command_buffer(cmd_buf).unwrap();
+    }
+
+    cmd_buf
+}
+
+pub fn create_linear_sampler(device: &ash::Device) -> vk::Sampler {
+    let sampler_create_info = vk::SamplerCreateInfo::builder()
+        .min_filter(vk::Filter::LINEAR)
+        .mag_filter(vk::Filter::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .anisotropy_enable(false);
+
+    unsafe {
+        device.create_sampler(&sampler_create_info, None).unwrap()
+    }
+}
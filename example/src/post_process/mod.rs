@@ -0,0 +1,7 @@
+mod fullscreen_pass;
+pub mod auto_exposure;
+pub mod bloom;
+pub mod ssao;
+pub mod tonemap;
+
+pub use fullscreen_pass::*;
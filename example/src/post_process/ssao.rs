@@ -0,0 +1,359 @@
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use cgmath::{InnerSpace, Matrix4, vec3};
+
+use ash_render_env::descriptor_set::{DescriptorSet, DescriptorSetBuilder};
+use ash_render_env::env::RenderEnv;
+use ash_render_env::frame_buffer::{AttachmentDesciption, Framebuffer};
+use ash_render_env::pipeline_builder::{Pipeline, PipelineBuilder};
+use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+use ash_render_env::shader;
+use ash_render_env::utils::texture_utils::{create_image_view, create_texture_image};
+
+use crate::post_process::{create_linear_sampler, record_fullscreen_pass};
+use crate::shadow_map::uniform_buffer::UniformBuffer;
+
+pub const MAX_KERNEL_SIZE: usize = 64;
+
+const NOISE_SIZE: u32 = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SsaoSettings {
+    pub enabled: bool,
+    // hemisphere radius in world units
+    pub radius: f32,
+    // depth offset against self-occlusion (acne) on flat surfaces
+    pub bias: f32,
+    pub sample_count: u32,
+    // contrast of the result: ao^power
+    pub power: f32,
+    // show occlusion only instead of lit scene
+    pub debug_view: bool,
+}
+
+impl Default for SsaoSettings {
+    fn default() -> Self {
+        SsaoSettings {
+            enabled: true,
+            radius: 0.5,
+            bias: 0.025,
+            sample_count: 16,
+            power: 1.5,
+            debug_view: false,
+        }
+    }
+}
+
+pub fn settings_editor(ui: &mut egui::Ui, settings: &mut SsaoSettings) {
+    ui.checkbox(&mut settings.enabled, "SSAO");
+    if settings.enabled {
+        ui.add(egui::Slider::new(&mut settings.radius, 0.05..=2.0).text("Radius"));
+        ui.add(egui::Slider::new(&mut settings.bias, 0.0..=0.1).text("Bias"));
+        ui.add(egui::Slider::new(&mut settings.sample_count, 4..=MAX_KERNEL_SIZE as u32).text("Samples"));
+        ui.add(egui::Slider::new(&mut settings.power, 0.5..=4.0).text("Power"));
+        ui.checkbox(&mut settings.debug_view, "Show occlusion only");
+    }
+}
+
+#[repr(C)]
+struct Uniforms {
+    view: Matrix4<f32>,
+    proj: Matrix4<f32>,
+    kernel: [[f32; 4]; MAX_KERNEL_SIZE],
+    radius: f32,
+    bias: f32,
+    power: f32,
+    sample_count: u32,
+}
+
+// Ambient occlusion from G-buffer: SSAO pass (occlusion + linear depth) -> bilateral blur.
+// Result (`output_view`, R8_UNORM) is consumed by compose pass.
+pub struct Ssao {
+    ssao_buffer: Framebuffer,
+    blur_buffer: Framebuffer,
+
+    ssao_pipeline: Pipeline,
+    blur_pipeline: Pipeline,
+    ssao_descriptor_set: DescriptorSet,
+    blur_descriptor_set: DescriptorSet,
+    ssao_second_buffer: vk::CommandBuffer,
+    blur_second_buffer: vk::CommandBuffer,
+
+    ssao_pass_draw_command: PrimaryCommandBuffer,
+    blur_pass_draw_command: PrimaryCommandBuffer,
+
+    uniform_buffer: UniformBuffer<Uniforms>,
+
+    noise_image: vk::Image,
+    noise_image_memory: vk::DeviceMemory,
+    noise_view: vk::ImageView,
+    noise_sampler: vk::Sampler,
+    sampler: vk::Sampler,
+
+    env: Arc<RenderEnv>,
+}
+
+impl Ssao {
+    pub fn new(env: Arc<RenderEnv>, gbuffer: &Framebuffer, dimensions: [u32; 2], max_frames_in_flight: usize) -> Ssao {
+        let mut ssao_buffer = Framebuffer::new(env.clone(), vec!(
+            AttachmentDesciption {
+                name: "occlusion",
+                samples_count: vk::SampleCountFlags::TYPE_1,
+                format: vk::Format::R16G16_SFLOAT,
+                mip_levels: 1,
+            },
+        )).debug_name("ssao");
+        ssao_buffer.resize_swapchain(dimensions);
+
+        let mut blur_buffer = Framebuffer::new(env.clone(), vec!(
+            AttachmentDesciption {
+                name: "occlusion",
+                samples_count: vk::SampleCountFlags::TYPE_1,
+                format: vk::Format::R8_UNORM,
+                mip_levels: 1,
+            },
+        )).debug_name("ssao_blur");
+        blur_buffer.resize_swapchain(dimensions);
+
+        let ssao_pipeline = PipelineBuilder::new(env.device().clone(), ssao_buffer.render_pass(), 0)
+            .vertex_shader(shader::Shader::load(env.device(), "assets/shaders/spv/compose.vert.spv"))
+            .fragment_shader(shader::Shader::load(env.device(), "assets/shaders/spv/post_process/ssao.frag.spv"))
+            .debug_name(&env, "ssao")
+            .build();
+
+        let blur_pipeline = PipelineBuilder::new(env.device().clone(), blur_buffer.render_pass(), 0)
+            .vertex_shader(shader::Shader::load(env.device(), "assets/shaders/spv/compose.vert.spv"))
+            .fragment_shader(shader::Shader::load(env.device(), "assets/shaders/spv/post_process/ssao_blur.frag.spv"))
+            .debug_name(&env, "ssao_blur")
+            .build();
+
+        let (noise_image, noise_image_memory, _) = create_texture_image(
+            env.device(), env.command_pool(), env.queue(), &env.mem_properties, vk::Format::R8G8B8A8_UNORM,
+            &generate_noise(), NOISE_SIZE, NOISE_SIZE, 1, false, Some((env.debug_names(), "ssao.noise")));
+        let noise_view = create_image_view(
+            env.device(), noise_image, vk::Format::R8G8B8A8_UNORM, vk::ImageAspectFlags::COLOR, 1, 1,
+            Some((env.debug_names(), "ssao.noise.view")));
+        let noise_sampler = create_noise_sampler(env.device());
+        env.set_name(noise_sampler, "ssao.noise.sampler");
+        let sampler = create_linear_sampler(env.device());
+        env.set_name(sampler, "ssao.sampler");
+
+        let uniform_buffer = UniformBuffer::new(env.clone()).debug_name(&env, "ssao.uniforms");
+
+        let ssao_descriptor_set = DescriptorSetBuilder::new(
+            env.device(), ssao_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(gbuffer.attachments[1].view, sampler)
+            .add_image(gbuffer.attachments[2].view, sampler)
+            .add_image(noise_view, noise_sampler)
+            .add_buffer(uniform_buffer.buffer)
+            .build();
+
+        let blur_descriptor_set = DescriptorSetBuilder::new(
+            env.device(), blur_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(ssao_buffer.attachments[0].view, sampler)
+            .build();
+
+        let ssao_second_buffer = record_fullscreen_pass(&env, dimensions, &ssao_pipeline, &ssao_descriptor_set, ssao_buffer.render_pass());
+        let blur_second_buffer = record_fullscreen_pass(&env, dimensions, &blur_pipeline, &blur_descriptor_set, blur_buffer.render_pass());
+
+        let mut ssao_pass_draw_command = PrimaryCommandBuffer::new(env.clone(), max_frames_in_flight).debug_name("ssao");
+        ssao_pass_draw_command.set_dimensions(dimensions);
+        let mut blur_pass_draw_command = PrimaryCommandBuffer::new(env.clone(), max_frames_in_flight).debug_name("ssao_blur");
+        blur_pass_draw_command.set_dimensions(dimensions);
+
+        Ssao {
+            ssao_buffer,
+            blur_buffer,
+            ssao_pipeline,
+            blur_pipeline,
+            ssao_descriptor_set,
+            blur_descriptor_set,
+            ssao_second_buffer,
+            blur_second_buffer,
+            ssao_pass_draw_command,
+            blur_pass_draw_command,
+            uniform_buffer,
+            noise_image,
+            noise_image_memory,
+            noise_view,
+            noise_sampler,
+            sampler,
+            env,
+        }
+    }
+
+    pub fn output_view(&self) -> vk::ImageView {
+        self.blur_buffer.attachments[0].view
+    }
+
+    pub fn write_settings(&mut self, settings: &SsaoSettings, view: Matrix4<f32>, proj: Matrix4<f32>) {
+        // not used when disabled, see `execute`
+        let sample_count = if settings.enabled {
+            settings.sample_count.min(MAX_KERNEL_SIZE as u32)
+        } else {
+            0
+        };
+
+        self.uniform_buffer.write_data(Uniforms {
+            view,
+            proj,
+            kernel: generate_kernel(sample_count as usize),
+            radius: settings.radius,
+            bias: settings.bias,
+            power: settings.power,
+            sample_count,
+        });
+    }
+
+    pub fn update_framebuffer(&mut self, gbuffer: &Framebuffer, dimensions: [u32; 2]) {
+        unsafe {
+            self.env.device().free_command_buffers(
+                self.env.command_pool(), &[self.ssao_second_buffer, self.blur_second_buffer]);
+        }
+
+        self.ssao_buffer.resize_swapchain(dimensions);
+        self.blur_buffer.resize_swapchain(dimensions);
+
+        self.ssao_descriptor_set = DescriptorSetBuilder::new(
+            self.env.device(), self.ssao_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(gbuffer.attachments[1].view, self.sampler)
+            .add_image(gbuffer.attachments[2].view, self.sampler)
+            .add_image(self.noise_view, self.noise_sampler)
+            .add_buffer(self.uniform_buffer.buffer)
+            .build();
+
+        self.blur_descriptor_set = DescriptorSetBuilder::new(
+            self.env.device(), self.blur_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(self.ssao_buffer.attachments[0].view, self.sampler)
+            .build();
+
+        self.ssao_second_buffer = record_fullscreen_pass(
+            &self.env, dimensions, &self.ssao_pipeline, &self.ssao_descriptor_set, self.ssao_buffer.render_pass());
+        self.blur_second_buffer = record_fullscreen_pass(
+            &self.env, dimensions, &self.blur_pipeline, &self.blur_descriptor_set, self.blur_buffer.render_pass());
+
+        self.ssao_pass_draw_command.set_dimensions(dimensions);
+        self.blur_pass_draw_command.set_dimensions(dimensions);
+    }
+
+    // Must be submitted after geometry pass and before compose
+    // Disabled: SSAO and blur shaders are skipped, blur target is only cleared to 1 (no occlusion)
+    // because compose pass always samples it
+    pub fn execute(&mut self, enabled: bool) -> Vec<vk::CommandBuffer> {
+        let clear_values = vec![
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [1.0, 0.0, 0.0, 0.0],
+                },
+            },
+        ];
+
+        if !enabled {
+            let clear_cmd_buf = self.blur_pass_draw_command.execute_secondary(
+                clear_values,
+                self.blur_buffer.framebuffer.unwrap(),
+                self.blur_buffer.render_pass,
+                &[],
+            );
+            return vec![clear_cmd_buf];
+        }
+
+        let ssao_cmd_buf = self.ssao_pass_draw_command.execute_secondary(
+            clear_values.clone(),
+            self.ssao_buffer.framebuffer.unwrap(),
+            self.ssao_buffer.render_pass,
+            &[self.ssao_second_buffer],
+        );
+
+        let blur_cmd_buf = self.blur_pass_draw_command.execute_secondary(
+            clear_values,
+            self.blur_buffer.framebuffer.unwrap(),
+            self.blur_buffer.render_pass,
+            &[self.blur_second_buffer],
+        );
+
+        vec![ssao_cmd_buf, blur_cmd_buf]
+    }
+}
+
+impl Drop for Ssao {
+    fn drop(&mut self) {
+        unsafe {
+            self.env.device().free_command_buffers(
+                self.env.command_pool(), &[self.ssao_second_buffer, self.blur_second_buffer]);
+
+            self.env.device().destroy_sampler(self.sampler, None);
+            self.env.device().destroy_sampler(self.noise_sampler, None);
+            self.env.device().destroy_image_view(self.noise_view, None);
+            self.env.device().destroy_image(self.noise_image, None);
+            self.env.device().free_memory(self.noise_image_memory, None);
+        }
+
+        self.ssao_buffer.destroy();
+        self.blur_buffer.destroy();
+    }
+}
+
+// xorshift32: noise and kernel must be the same every run (and every frame)
+struct Random(u32);
+
+impl Random {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+// Points in +Z hemisphere, denser near the origin
+fn generate_kernel(sample_count: usize) -> [[f32; 4]; MAX_KERNEL_SIZE] {
+    let mut kernel = [[0.0; 4]; MAX_KERNEL_SIZE];
+    let mut random = Random(0x9E3779B9);
+
+    for i in 0..sample_count {
+        let sample = vec3(random.next() * 2.0 - 1.0, random.next() * 2.0 - 1.0, random.next())
+            .normalize() * random.next();
+
+        let t = i as f32 / sample_count as f32;
+        let scale = 0.1 + 0.9 * t * t;
+
+        let sample = sample * scale;
+        kernel[i] = [sample.x, sample.y, sample.z, 0.0];
+    }
+
+    kernel
+}
+
+// Random rotation vectors around Z, xy packed to [0, 255]
+fn generate_noise() -> Vec<u8> {
+    let mut random = Random(0x2545F491);
+    let mut pixels = Vec::with_capacity((NOISE_SIZE * NOISE_SIZE * 4) as usize);
+
+    for _ in 0..NOISE_SIZE * NOISE_SIZE {
+        let rotation = vec3(random.next() * 2.0 - 1.0, random.next() * 2.0 - 1.0, 0.0).normalize();
+
+        pixels.push(((rotation.x * 0.5 + 0.5) * 255.0) as u8);
+        pixels.push(((rotation.y * 0.5 + 0.5) * 255.0) as u8);
+        pixels.push(0);
+        pixels.push(255);
+    }
+
+    pixels
+}
+
+fn create_noise_sampler(device: &ash::Device) -> vk::Sampler {
+    let sampler_create_info = vk::SamplerCreateInfo::builder()
+        .min_filter(vk::Filter::NEAREST)
+        .mag_filter(vk::Filter::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::REPEAT)
+        .address_mode_v(vk::SamplerAddressMode::REPEAT)
+        .address_mode_w(vk::SamplerAddressMode::REPEAT)
+        .anisotropy_enable(false);
+
+    unsafe {
+        device.create_sampler(&sampler_create_info, None).unwrap()
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use ash_render_env::descriptor_set::{DescriptorSet, DescriptorSetBuilder};
+use ash_render_env::env::RenderEnv;
+use ash_render_env::pipeline_builder::{Pipeline, PipelineBuilder};
+use ash_render_env::shader;
+
+use crate::post_process::{create_linear_sampler, record_fullscreen_pass};
+use crate::shadow_map::uniform_buffer::UniformBuffer;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapOperator {
+    Linear,
+    Reinhard,
+    Aces,
+    Uncharted2,
+    AgX,
+}
+
+impl ToneMapOperator {
+    pub const ALL: [ToneMapOperator; 5] = [
+        ToneMapOperator::Linear,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::Aces,
+        ToneMapOperator::Uncharted2,
+        ToneMapOperator::AgX,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMapOperator::Linear => "Linear (clamp)",
+            ToneMapOperator::Reinhard => "Reinhard",
+            ToneMapOperator::Aces => "ACES",
+            ToneMapOperator::Uncharted2 => "Uncharted 2",
+            ToneMapOperator::AgX => "AgX",
+        }
+    }
+}
+
+// Last stage of the post-process chain
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OutputTransform {
+    // tone curve + sRGB encoding (done by hardware for *_SRGB swapchain formats)
+    Srgb,
+    // no tone curve, linear scene values scaled by exposure: only for extended range (scRGB) swapchain,
+    // see `SwapChain::is_hdr`
+    Hdr,
+}
+
+impl OutputTransform {
+    pub const ALL: [OutputTransform; 2] = [OutputTransform::Srgb, OutputTransform::Hdr];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputTransform::Srgb => "sRGB",
+            OutputTransform::Hdr => "HDR (linear)",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ToneMapSettings {
+    pub operator: ToneMapOperator,
+    pub output: OutputTransform,
+    // exposure compensation in EV stops (applied on top of auto exposure)
+    pub exposure: f32,
+    // smallest luminance mapped to pure white (Reinhard)
+    pub white_point: f32,
+}
+
+impl Default for ToneMapSettings {
+    fn default() -> Self {
+        ToneMapSettings {
+            operator: ToneMapOperator::Aces,
+            output: OutputTransform::Srgb,
+            exposure: 0.0,
+            white_point: 4.0,
+        }
+    }
+}
+
+// GUI for tone map settings, HDR output is offered only on HDR swapchain
+pub fn settings_editor(ui: &mut egui::Ui, settings: &mut ToneMapSettings, hdr_swapchain: bool) {
+    egui::ComboBox::from_label("Tone mapping")
+        .selected_text(settings.operator.name())
+        .show_ui(ui, |ui| {
+            for operator in ToneMapOperator::ALL.iter() {
+                ui.selectable_value(&mut settings.operator, *operator, operator.name());
+            }
+        });
+    egui::ComboBox::from_label("Output")
+        .selected_text(settings.output.name())
+        .show_ui(ui, |ui| {
+            // linear output is clipped and wrongly encoded on SDR swapchain
+            for output in OutputTransform::ALL.iter().filter(|output| **output != OutputTransform::Hdr || hdr_swapchain) {
+                ui.selectable_value(&mut settings.output, *output, output.name());
+            }
+        });
+    ui.add(egui::Slider::new(&mut settings.exposure, -8.0..=8.0).text("Exposure (EV)"));
+    if settings.operator == ToneMapOperator::Reinhard {
+        ui.add(egui::Slider::new(&mut settings.white_point, 1.0..=16.0).text("White point"));
+    }
+}
+
+#[repr(C)]
+struct Uniforms {
+    exposure: f32,
+    white_point: f32,
+    operator: u32,
+    output: u32,
+    auto_exposure: u32,
+    bloom_intensity: f32,
+}
+
+// Swapchain formats without hardware sRGB encoding
+fn format_needs_srgb_encode(format: vk::Format) -> bool {
+    [
+        vk::Format::B8G8R8A8_UNORM,
+        vk::Format::R8G8B8A8_UNORM,
+        vk::Format::A8B8G8R8_UNORM_PACK32,
+        vk::Format::A2B10G10R10_UNORM_PACK32,
+        vk::Format::A2R10G10B10_UNORM_PACK32,
+    ].contains(&format)
+}
+
+// Maps HDR lighting buffer to display range
+pub struct ToneMapRenderer {
+    sampler: vk::Sampler,
+    descriptor_set: DescriptorSet,
+    pipeline: Pipeline,
+    render_pass: vk::RenderPass,
+    pub second_buffer: vk::CommandBuffer,
+    uniform_buffer: UniformBuffer<Uniforms>,
+    exposure_buffer: vk::Buffer,
+    env: Arc<RenderEnv>,
+}
+
+impl ToneMapRenderer {
+    pub fn new(env: Arc<RenderEnv>, hdr_view: vk::ImageView, bloom_view: vk::ImageView, exposure_buffer: vk::Buffer,
+               render_pass: vk::RenderPass, output_format: vk::Format, dimensions: [u32; 2]) -> ToneMapRenderer {
+        let pipeline = {
+            let vert_shader_module = shader::Shader::load(env.device(), "assets/shaders/spv/compose.vert.spv");
+            let frag_shader_module = shader::Shader::load(env.device(), "assets/shaders/spv/post_process/tonemap.frag.spv")
+                .specialize(shader::ConstantsBuilder::new().add_u32(format_needs_srgb_encode(output_format) as u32));
+
+            PipelineBuilder::new(env.device().clone(), render_pass, 0)
+                .fragment_shader(frag_shader_module)
+                .vertex_shader(vert_shader_module)
+                .debug_name(&env, "tone_map")
+                .build()
+        };
+
+        let sampler = create_linear_sampler(env.device());
+        env.set_name(sampler, "tonemap.sampler");
+        let uniform_buffer = UniformBuffer::new(env.clone()).debug_name(&env, "tone_map.uniforms");
+
+        let descriptor_set = DescriptorSetBuilder::new(
+            env.device(), pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(hdr_view, sampler)
+            .add_buffer(uniform_buffer.buffer)
+            .add_buffer(exposure_buffer)
+            .add_image(bloom_view, sampler)
+            .build();
+
+        let second_buffer = record_fullscreen_pass(&env, dimensions, &pipeline, &descriptor_set, render_pass);
+
+        ToneMapRenderer {
+            sampler,
+            descriptor_set,
+            pipeline,
+            render_pass,
+            second_buffer,
+            uniform_buffer,
+            exposure_buffer,
+            env,
+        }
+    }
+
+    // bloom_intensity = 0 disables bloom composite
+    pub fn write_settings(&mut self, settings: &ToneMapSettings, auto_exposure: bool, bloom_intensity: f32) {
+        self.uniform_buffer.write_data(Uniforms {
+            exposure: 2.0_f32.powf(settings.exposure),
+            white_point: settings.white_point,
+            operator: settings.operator as u32,
+            output: settings.output as u32,
+            auto_exposure: auto_exposure as u32,
+            bloom_intensity,
+        });
+    }
+
+    pub fn update_framebuffer(&mut self, hdr_view: vk::ImageView, bloom_view: vk::ImageView, dimensions: [u32; 2]) {
+        unsafe {
+            self.env.device().free_command_buffers(self.env.command_pool(), &[self.second_buffer]);
+        }
+
+        self.descriptor_set = DescriptorSetBuilder::new(
+            self.env.device(), self.pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image(hdr_view, self.sampler)
+            .add_buffer(self.uniform_buffer.buffer)
+            .add_buffer(self.exposure_buffer)
+            .add_image(bloom_view, self.sampler)
+            .build();
+
+        self.second_buffer = record_fullscreen_pass(&self.env, dimensions, &self.pipeline, &self.descriptor_set, self.render_pass);
+    }
+}
+
+impl Drop for ToneMapRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            self.env.device().free_command_buffers(self.env.command_pool(), &[self.second_buffer]);
+            self.env.device().destroy_sampler(self.sampler, None);
+        }
+    }
+}
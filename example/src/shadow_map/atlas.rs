@@ -0,0 +1,468 @@
+use std::f32::consts::FRAC_PI_2;
+use std::ptr;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use cgmath::{Deg, InnerSpace, Matrix4, MetricSpace, Rad, Vector3};
+
+use ash_render_env::camera::Camera;
+use ash_render_env::env::RenderEnv;
+use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+use ash_render_env::projection::vulkan_clip_correction;
+use ash_render_env::utils::texture_utils::{create_image, create_image_view};
+
+use crate::lighting::light_manager::{Light, LightKind};
+use crate::shadow_map::ShadowSettings;
+use crate::shadow_map::caster::{ShadowCaster, ShadowFrustum};
+use crate::shadow_map::render_target::create_render_pass;
+use crate::utils::storage_buffer::StorageBuffer;
+
+pub const ATLAS_SIZE: u32 = 4096;
+// Point light takes 6 views (cube faces), spot light takes 1
+pub const MAX_SHADOW_VIEWS: usize = 64;
+
+const MIN_TILE_SIZE: u32 = 128;
+const MAX_TILE_SIZE: u32 = 1024;
+const NEAR_CLIP: f32 = 0.05;
+
+// Layout of `ShadowView` struct in compose.frag (std430)
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuShadowView {
+    view_proj: Matrix4<f32>,
+    // xy: tile offset, zw: tile size (atlas uv)
+    atlas_rect: [f32; 4],
+}
+
+const EMPTY_VIEW: GpuShadowView = GpuShadowView {
+    view_proj: Matrix4::new(
+        0.0, 0.0, 0.0, 0.0,
+        0.0, 0.0, 0.0, 0.0,
+        0.0, 0.0, 0.0, 0.0,
+        0.0, 0.0, 0.0, 0.0,
+    ),
+    atlas_rect: [0.0; 4],
+};
+
+#[derive(Clone, Copy)]
+struct ShadowView {
+    view_proj: Matrix4<f32>,
+    // x, y, size in texels
+    tile: [u32; 3],
+}
+
+// Shadow maps of spot and point lights packed into one depth texture. Tiles are reallocated every frame,
+// tile size depends on light size on screen. Atlas has depth format of the cascaded shadow map, so
+// shadow caster pipelines are compatible with both render passes.
+pub struct ShadowAtlas {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+    framebuffer: vk::Framebuffer,
+    render_pass: vk::RenderPass,
+
+    views: Vec<ShadowView>,
+    view_buffer: StorageBuffer<[GpuShadowView; MAX_SHADOW_VIEWS]>,
+
+    commands: PrimaryCommandBuffer,
+    env: Arc<RenderEnv>,
+}
+
+impl ShadowAtlas {
+    // Must be recreated when depth format of `settings` changes
+    pub fn new(env: Arc<RenderEnv>, settings: &ShadowSettings, max_frames_in_flight: usize) -> ShadowAtlas {
+        let depth_format = settings.depth_format;
+        let render_pass = create_render_pass(env.device(), depth_format);
+        env.set_name(render_pass, "shadow_atlas.render_pass");
+
+        let (image, memory) = create_image(
+            env.device(),
+            ATLAS_SIZE,
+            ATLAS_SIZE,
+            1,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            depth_format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            &env.mem_properties,
+            Some((env.debug_names(), "shadow_atlas")),
+        );
+        let view = create_image_view(env.device(), image, depth_format, vk::ImageAspectFlags::DEPTH, 1, 1,
+                                     Some((env.debug_names(), "shadow_atlas.view")));
+
+        let attachments = [view];
+        let framebuffer_info = vk::FramebufferCreateInfo {
+            s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: Default::default(),
+            render_pass,
+            attachment_count: attachments.len() as u32,
+            p_attachments: attachments.as_ptr(),
+            width: ATLAS_SIZE,
+            height: ATLAS_SIZE,
+            layers: 1,
+        };
+
+        let framebuffer = unsafe {
+            env.device().create_framebuffer(&framebuffer_info, None).unwrap()
+        };
+        env.set_name(framebuffer, "shadow_atlas.framebuffer");
+
+        ShadowAtlas {
+            image,
+            memory,
+            view,
+            framebuffer,
+            render_pass,
+            views: Vec::new(),
+            view_buffer: StorageBuffer::new(env.clone(), [EMPTY_VIEW; MAX_SHADOW_VIEWS]).debug_name(&env, "shadow_atlas.views"),
+            commands: PrimaryCommandBuffer::new(env.clone(), max_frames_in_flight).debug_name("shadow_atlas"),
+            env,
+        }
+    }
+
+    pub fn view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    // Matrices and atlas rects of all shadow views (`ShadowViews` in compose.frag)
+    pub fn view_buffer(&self) -> vk::Buffer {
+        self.view_buffer.buffer
+    }
+
+    pub fn view_count(&self) -> usize {
+        self.views.len()
+    }
+
+    // Allocates tiles for shadow casting lights. Returns index of the first shadow view for every light
+    // (cube faces of point light are consecutive), None when light has no shadow.
+    pub fn update(&mut self, lights: &[Light], camera: &Camera, viewport_height: u32) -> Vec<Option<u32>> {
+        let mut requests: Vec<(usize, u32)> = lights.iter()
+            .enumerate()
+            .filter(|(_, light)| light.cast_shadows && light.kind != LightKind::Directional)
+            .map(|(idx, light)| (idx, tile_size(light, camera, viewport_height)))
+            .collect();
+
+        // the biggest lights first: they get tiles when atlas is full
+        requests.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let mut allocator = AtlasAllocator::new(ATLAS_SIZE);
+        let mut result = vec![None; lights.len()];
+        self.views.clear();
+
+        for (light_idx, size) in requests {
+            let light = &lights[light_idx];
+            let matrices = view_matrices(light);
+            if self.views.len() + matrices.len() > MAX_SHADOW_VIEWS {
+                continue;
+            }
+
+            if let Some(tiles) = allocator.allocate_shrinking(size, matrices.len(), MIN_TILE_SIZE) {
+                result[light_idx] = Some(self.views.len() as u32);
+                for (view_proj, tile) in matrices.iter().zip(tiles) {
+                    self.views.push(ShadowView { view_proj: *view_proj, tile });
+                }
+            }
+        }
+
+        let mut data = [EMPTY_VIEW; MAX_SHADOW_VIEWS];
+        for (idx, view) in self.views.iter().enumerate() {
+            let scale = 1.0 / ATLAS_SIZE as f32;
+            data[idx] = GpuShadowView {
+                view_proj: view.view_proj,
+                atlas_rect: [
+                    view.tile[0] as f32 * scale,
+                    view.tile[1] as f32 * scale,
+                    view.tile[2] as f32 * scale,
+                    view.tile[2] as f32 * scale,
+                ],
+            };
+        }
+        self.view_buffer.write_data(data);
+
+        result
+    }
+
+    // Renders shadow casters to every allocated tile
+    pub fn execute(&mut self, casters: &[&dyn ShadowCaster]) -> vk::CommandBuffer {
+        let clear_values = [vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            }
+        }];
+
+        let render_pass_begin_info = vk::RenderPassBeginInfo {
+            s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+            p_next: ptr::null(),
+            render_pass: self.render_pass,
+            framebuffer: self.framebuffer,
+            render_area: vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: vk::Extent2D {
+                    width: ATLAS_SIZE,
+                    height: ATLAS_SIZE,
+                },
+            },
+            clear_value_count: clear_values.len() as u32,
+            p_clear_values: clear_values.as_ptr(),
+        };
+
+        let views = &self.views;
+
+        self.commands.execute(|device, cmd_buf| unsafe {
+            device.cmd_begin_render_pass(cmd_buf, &render_pass_begin_info, vk::SubpassContents::INLINE);
+
+            for view in views.iter() {
+                let viewports = [vk::Viewport {
+                    x: view.tile[0] as f32,
+                    y: view.tile[1] as f32,
+                    width: view.tile[2] as f32,
+                    height: view.tile[2] as f32,
+                    min_depth: 0.0,
+                    max_depth: 1.0,
+                }];
+
+                let scissors = [vk::Rect2D {
+                    offset: vk::Offset2D { x: view.tile[0] as i32, y: view.tile[1] as i32 },
+                    extent: vk::Extent2D {
+                        width: view.tile[2],
+                        height: view.tile[2],
+                    },
+                }];
+
+                device.cmd_set_viewport(cmd_buf, 0, &viewports);
+                device.cmd_set_scissor(cmd_buf, 0, &scissors);
+
+                let frustum = ShadowFrustum::new(view.view_proj);
+                for caster in casters.iter() {
+                    caster.record_shadow_draws(device, cmd_buf, &frustum);
+                }
+            }
+
+            device.cmd_end_render_pass(cmd_buf);
+        })
+    }
+}
+
+impl Drop for ShadowAtlas {
+    fn drop(&mut self) {
+        unsafe {
+            self.env.device().destroy_framebuffer(self.framebuffer, None);
+            self.env.device().destroy_image_view(self.view, None);
+            self.env.device().destroy_image(self.image, None);
+            self.env.device().free_memory(self.memory, None);
+            self.env.device().destroy_render_pass(self.render_pass, None);
+        }
+    }
+}
+
+// Tile side from light sphere size on screen (per cube face for point lights)
+fn tile_size(light: &Light, camera: &Camera, viewport_height: u32) -> u32 {
+    let distance = camera.position().distance(light.position);
+
+    let screen_size = if distance <= light.range {
+        MAX_TILE_SIZE as f32
+    } else {
+        light.range * camera.projection().ndc_scale(distance) * viewport_height as f32
+    };
+
+    let size = match light.kind {
+        LightKind::Point => screen_size / 2.0,
+        _ => screen_size,
+    };
+
+    (size.max(MIN_TILE_SIZE as f32) as u32).next_power_of_two().min(MAX_TILE_SIZE)
+}
+
+// Spot light: single perspective view along cone. Point light: 90 degree views in order
+// +X, -X, +Y, -Y, +Z, -Z (face is chosen by major axis in compose.frag).
+fn view_matrices(light: &Light) -> Vec<Matrix4<f32>> {
+    let position = light.position;
+    let far_clip = light.range.max(NEAR_CLIP * 2.0);
+
+    let look_at = |direction: Vector3<f32>, up: Vector3<f32>| {
+        Matrix4::look_at_rh(position, position + direction, up)
+    };
+
+    match light.kind {
+        LightKind::Spot { outer_angle, .. } => {
+            let direction = light.direction.normalize();
+            let up = if direction.y.abs() > 0.99 { Vector3::unit_x() } else { Vector3::unit_y() };
+            // cone must fit into frustum
+            let fov = (outer_angle * 2.0 + 0.1).min(FRAC_PI_2 * 1.9);
+            let proj = cgmath::perspective(Rad(fov), 1.0, NEAR_CLIP, far_clip);
+
+            vec![vulkan_clip_correction() * proj * look_at(direction, up)]
+        }
+        _ => {
+            let proj = vulkan_clip_correction() * cgmath::perspective(Deg(90.0), 1.0, NEAR_CLIP, far_clip);
+            let faces = [
+                (Vector3::unit_x(), Vector3::unit_y()),
+                (-Vector3::unit_x(), Vector3::unit_y()),
+                (Vector3::unit_y(), Vector3::unit_z()),
+                (-Vector3::unit_y(), Vector3::unit_z()),
+                (Vector3::unit_z(), Vector3::unit_y()),
+                (-Vector3::unit_z(), Vector3::unit_y()),
+            ];
+
+            faces.iter().map(|(direction, up)| proj * look_at(*direction, *up)).collect()
+        }
+    }
+}
+
+// Quadtree allocation of square power of two tiles. Best packing when tiles are requested from the largest.
+struct AtlasAllocator {
+    // x, y, size
+    free: Vec<[u32; 3]>,
+}
+
+impl AtlasAllocator {
+    fn new(size: u32) -> AtlasAllocator {
+        AtlasAllocator { free: vec![[0, 0, size]] }
+    }
+
+    fn allocate(&mut self, size: u32) -> Option<[u32; 3]> {
+        let (idx, _) = self.free.iter()
+            .enumerate()
+            .filter(|(_, node)| node[2] >= size)
+            .min_by_key(|(_, node)| node[2])?;
+
+        let mut node = self.free.swap_remove(idx);
+        while node[2] > size {
+            let half = node[2] / 2;
+            self.free.push([node[0] + half, node[1], half]);
+            self.free.push([node[0], node[1] + half, half]);
+            self.free.push([node[0] + half, node[1] + half, half]);
+            node[2] = half;
+        }
+
+        Some(node)
+    }
+
+    // All or nothing: free list is left unchanged on failure
+    fn allocate_all(&mut self, size: u32, count: usize) -> Option<Vec<[u32; 3]>> {
+        let free = self.free.clone();
+        let mut tiles = Vec::with_capacity(count);
+        for _ in 0..count {
+            match self.allocate(size) {
+                Some(tile) => tiles.push(tile),
+                None => {
+                    self.free = free;
+                    return None;
+                }
+            }
+        }
+
+        Some(tiles)
+    }
+
+    // Halves tile size until `count` tiles fit, gives up below `min_size`
+    fn allocate_shrinking(&mut self, mut size: u32, count: usize, min_size: u32) -> Option<Vec<[u32; 3]>> {
+        while size >= min_size {
+            if let Some(tiles) = self.allocate_all(size, count) {
+                return Some(tiles);
+            }
+            size /= 2;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: &[u32; 3], b: &[u32; 3]) -> bool {
+        a[0] < b[0] + b[2] && b[0] < a[0] + a[2] && a[1] < b[1] + b[2] && b[1] < a[1] + a[2]
+    }
+
+    fn sorted(mut nodes: Vec<[u32; 3]>) -> Vec<[u32; 3]> {
+        nodes.sort();
+        nodes
+    }
+
+    #[test]
+    fn exact_fit_takes_whole_atlas() {
+        let mut allocator = AtlasAllocator::new(256);
+
+        assert_eq!(allocator.allocate(256), Some([0, 0, 256]));
+        assert!(allocator.free.is_empty());
+        assert_eq!(allocator.allocate(1), None);
+    }
+
+    #[test]
+    fn split_tiles_do_not_overlap() {
+        let mut allocator = AtlasAllocator::new(256);
+
+        let mut tiles = vec![allocator.allocate(128).unwrap()];
+        for _ in 0..4 {
+            tiles.push(allocator.allocate(64).unwrap());
+        }
+        for _ in 0..2 {
+            tiles.push(allocator.allocate(128).unwrap());
+        }
+
+        for (idx, a) in tiles.iter().enumerate() {
+            assert!(a[0] + a[2] <= 256 && a[1] + a[2] <= 256);
+            for b in tiles[idx + 1..].iter() {
+                assert!(!overlaps(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+        // 3 * 128^2 + 4 * 64^2 covers the whole atlas
+        assert!(allocator.free.is_empty());
+    }
+
+    #[test]
+    fn smallest_fitting_node_is_split() {
+        let mut allocator = AtlasAllocator::new(256);
+        allocator.allocate(64).unwrap();
+
+        // remaining 64 tiles of the split 128 quadrant are used before the other quadrants
+        for _ in 0..3 {
+            let tile = allocator.allocate(64).unwrap();
+            assert!(tile[0] < 128 && tile[1] < 128, "{:?}", tile);
+        }
+    }
+
+    #[test]
+    fn allocate_all_restores_free_list_on_failure() {
+        let mut allocator = AtlasAllocator::new(256);
+        allocator.allocate(128).unwrap();
+        let free = sorted(allocator.free.clone());
+
+        assert_eq!(allocator.allocate_all(128, 4), None);
+        assert_eq!(sorted(allocator.free.clone()), free);
+
+        // partially split nodes are given back too
+        assert_eq!(allocator.allocate_all(64, 13), None);
+        assert_eq!(sorted(allocator.free.clone()), free);
+
+        assert_eq!(allocator.allocate_all(128, 3).map(|tiles| tiles.len()), Some(3));
+    }
+
+    #[test]
+    fn allocate_shrinking_halves_size_when_full() {
+        let mut allocator = AtlasAllocator::new(512);
+        allocator.allocate_all(256, 3).unwrap();
+
+        // 6 cube faces of 256 or 128 do not fit into the last quadrant, 64 do
+        let tiles = allocator.allocate_shrinking(256, 6, 32).unwrap();
+        assert_eq!(tiles.len(), 6);
+        assert!(tiles.iter().all(|tile| tile[2] == 64));
+    }
+
+    #[test]
+    fn allocate_shrinking_gives_up_below_min_size() {
+        let mut allocator = AtlasAllocator::new(512);
+        allocator.allocate_all(256, 3).unwrap();
+        let free = sorted(allocator.free.clone());
+
+        assert_eq!(allocator.allocate_shrinking(256, 6, 128), None);
+        assert_eq!(sorted(allocator.free.clone()), free);
+    }
+}
@@ -0,0 +1,211 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use ash::vk;
+
+use ash_render_env::camera::Camera;
+use ash_render_env::egui::Egui;
+use ash_render_env::env::RenderEnv;
+
+use crate::lighting::directional_light::DirectionalLight;
+use crate::shadow_map::{CascadeInfo, MAX_CASCADE_COUNT, ShadowMapFramebuffer, ShadowSettings};
+use crate::shadow_map::caster::{CascadeShadowPass, ShadowCaster};
+use crate::shadow_map::evsm::EvsmShadowMap;
+use crate::shadow_map::filtering::{ShadowFilter, ShadowFilterParams};
+
+// Sun shadows: cascaded shadow map, caster pass, EVSM moments and their GUI state.
+// Settings edited in GUI are applied by `rebuild`, caster pipelines and compose pass are
+// recreated by the owner after that.
+pub struct CascadedShadows {
+    framebuffer: ShadowMapFramebuffer,
+    settings: ShadowSettings,
+    filter_params: ShadowFilterParams,
+    evsm: Option<EvsmShadowMap>,
+    caster_pass: CascadeShadowPass,
+    cascades: Vec<CascadeInfo>,
+    split_lambda: f32,
+    cascade_blend: f32,
+    // tint sun light by cascade in compose pass
+    show_cascades: bool,
+    // cascade shown in GUI (egui texture id)
+    gui_cascade: u32,
+
+    max_frames_in_flight: usize,
+    env: Arc<RenderEnv>,
+}
+
+impl CascadedShadows {
+    pub fn new(env: Arc<RenderEnv>, egui: &mut Egui, camera: &Camera, sun: &DirectionalLight, max_frames_in_flight: usize) -> CascadedShadows {
+        let settings = ShadowSettings::default();
+        let framebuffer = ShadowMapFramebuffer::new(env.clone(), &settings);
+        register_cascade_textures(egui, &framebuffer);
+        let evsm = create_evsm(&env, &framebuffer, max_frames_in_flight);
+
+        let mut shadows = CascadedShadows {
+            framebuffer,
+            settings,
+            filter_params: ShadowFilterParams::default(),
+            evsm,
+            caster_pass: CascadeShadowPass::new(env.clone(), max_frames_in_flight),
+            cascades: Vec::new(),
+            split_lambda: 0.1,
+            cascade_blend: 0.1,
+            show_cascades: false,
+            gui_cascade: 1,
+            max_frames_in_flight,
+            env,
+        };
+        shadows.update_cascades(camera, sun);
+        shadows
+    }
+
+    pub fn framebuffer(&self) -> &ShadowMapFramebuffer {
+        &self.framebuffer
+    }
+
+    // Settings of current shadow map, GUI changes are not applied before `rebuild`
+    pub fn settings(&self) -> &ShadowSettings {
+        self.framebuffer.settings()
+    }
+
+    pub fn filter_params(&self) -> ShadowFilterParams {
+        self.filter_params
+    }
+
+    pub fn cascade_blend(&self) -> f32 {
+        self.cascade_blend
+    }
+
+    pub fn show_cascades(&self) -> bool {
+        self.show_cascades
+    }
+
+    pub fn cascades(&self) -> &[CascadeInfo] {
+        &self.cascades
+    }
+
+    pub fn moments_view(&self) -> Option<vk::ImageView> {
+        self.evsm.as_ref().map(|evsm| evsm.moments_view())
+    }
+
+    pub fn update_cascades(&mut self, camera: &Camera, sun: &DirectionalLight) {
+        self.cascades = self.framebuffer.update_cascades(camera, sun, self.split_lambda);
+    }
+
+    pub fn needs_rebuild(&self) -> bool {
+        self.settings != *self.framebuffer.settings()
+    }
+
+    // Recreates shadow map and EVSM moments with edited settings. Device must be idle.
+    pub fn rebuild(&mut self, egui: &mut Egui, camera: &Camera, sun: &DirectionalLight) {
+        for cascade_idx in 0..self.framebuffer.cascade_count() {
+            egui.unregister_texture(cascade_idx as u64 + 1);
+        }
+
+        self.framebuffer = ShadowMapFramebuffer::new(self.env.clone(), &self.settings);
+        register_cascade_textures(egui, &self.framebuffer);
+        self.evsm = create_evsm(&self.env, &self.framebuffer, self.max_frames_in_flight);
+
+        self.gui_cascade = self.gui_cascade.min(self.settings.cascade_count as u32);
+        self.update_cascades(camera, sun);
+    }
+
+    // Cascade pass and EVSM moments pass (if enabled), in submission order
+    pub fn execute(&mut self, casters: &[&dyn ShadowCaster]) -> (vk::CommandBuffer, Option<vk::CommandBuffer>) {
+        let cascades_cmd = self.caster_pass.execute(&self.framebuffer, &self.cascades, casters);
+        let blur_radius = self.filter_params.evsm_blur_radius;
+        let evsm_cmd = self.evsm.as_mut().map(|evsm| evsm.execute(blur_radius));
+        (cascades_cmd, evsm_cmd)
+    }
+
+    pub fn gui(&mut self, ui: &mut egui::Ui, camera: &Camera, sun: &DirectionalLight) {
+        egui::ComboBox::from_label("Shadow map data")
+            .selected_text(format!("{}", self.gui_cascade))
+            .show_ui(ui, |ui| {
+                for cascade in 1..=self.framebuffer.cascade_count() as u32 {
+                    ui.selectable_value(&mut self.gui_cascade, cascade, cascade.to_string());
+                }
+            });
+        ui.image(egui::TextureId::User(self.gui_cascade as u64), [200.0, 200.0]);
+
+        let resp = ui.add(egui::DragValue::new(&mut self.split_lambda).speed(0.01).clamp_range(RangeInclusive::new(0.1, 1.0)));
+        if resp.changed() {
+            self.update_cascades(camera, sun);
+        }
+
+        ui.add(egui::Slider::new(&mut self.cascade_blend, 0.0..=0.5).text("Cascade blend"));
+        ui.checkbox(&mut self.show_cascades, "Show cascades");
+
+        // applied at the start of next frame, see `rebuild`
+        ui.collapsing("Shadow settings", |ui| {
+            let settings = &mut self.settings;
+            egui::ComboBox::from_label("Resolution")
+                .selected_text(settings.resolution.to_string())
+                .show_ui(ui, |ui| {
+                    for resolution in ShadowSettings::RESOLUTIONS.iter() {
+                        ui.selectable_value(&mut settings.resolution, *resolution, resolution.to_string());
+                    }
+                });
+            ui.add(egui::Slider::new(&mut settings.cascade_count, 1..=MAX_CASCADE_COUNT).text("Cascades"));
+            egui::ComboBox::from_label("Filter")
+                .selected_text(settings.filter.name())
+                .show_ui(ui, |ui| {
+                    for filter in ShadowFilter::ALL.iter() {
+                        ui.selectable_value(&mut settings.filter, *filter, filter.name());
+                    }
+                });
+            egui::ComboBox::from_label("Depth format")
+                .selected_text(ShadowSettings::depth_format_name(settings.depth_format))
+                .show_ui(ui, |ui| {
+                    for format in ShadowSettings::DEPTH_FORMATS.iter() {
+                        ui.selectable_value(&mut settings.depth_format, *format, ShadowSettings::depth_format_name(*format));
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                if ui.button("Low").clicked() {
+                    *settings = ShadowSettings::low();
+                }
+                if ui.button("High").clicked() {
+                    *settings = ShadowSettings::default();
+                }
+            });
+        });
+
+        let draw_counts: Vec<String> = self.caster_pass.draw_counts().iter().map(|count| count.to_string()).collect();
+        ui.label(format!("Shadow caster draws per cascade: {}", draw_counts.join(" / ")));
+
+        let filter_params = &mut self.filter_params;
+        ui.add(egui::Slider::new(&mut filter_params.bias, 0.0..=0.02).text("Shadow bias"));
+        match self.framebuffer.settings().filter {
+            ShadowFilter::Hard => {}
+            ShadowFilter::Pcf | ShadowFilter::PoissonPcf => {
+                ui.add(egui::Slider::new(&mut filter_params.filter_radius, 0.5..=8.0).text("Filter radius (texels)"));
+            }
+            ShadowFilter::Pcss => {
+                ui.add(egui::Slider::new(&mut filter_params.light_size, 0.001..=0.2).text("Light size"));
+            }
+            ShadowFilter::Evsm => {
+                ui.add(egui::Slider::new(&mut filter_params.evsm_blur_radius, 0..=8).text("Blur radius"));
+                ui.add(egui::Slider::new(&mut filter_params.evsm_bleed_reduction, 0.0..=0.9).text("Light bleeding reduction"));
+            }
+        }
+    }
+}
+
+// Cascade views are shown in egui as textures 1..=cascade_count
+fn register_cascade_textures(egui: &mut Egui, framebuffer: &ShadowMapFramebuffer) {
+    for cascade_idx in 0..framebuffer.cascade_count() {
+        egui.register_texture_layout(cascade_idx as u64 + 1, framebuffer.get_cascade_view(cascade_idx),
+                                     vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL);
+    }
+}
+
+// Moment maps exist only for EVSM filter
+fn create_evsm(env: &Arc<RenderEnv>, framebuffer: &ShadowMapFramebuffer, max_frames_in_flight: usize) -> Option<EvsmShadowMap> {
+    if framebuffer.settings().filter == ShadowFilter::Evsm {
+        Some(EvsmShadowMap::new(env.clone(), framebuffer, max_frames_in_flight))
+    } else {
+        None
+    }
+}
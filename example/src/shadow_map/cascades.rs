@@ -0,0 +1,243 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, MetricSpace, Point3, SquareMatrix, Vector3, Vector4};
+
+use ash_render_env::projection::vulkan_clip_correction;
+
+pub struct CascadeInfo {
+    pub view_proj_mat: Matrix4<f32>,
+    // far split distance in view space (negative z)
+    pub max_z: f32,
+}
+
+// Camera and light parameters, everything cascades depend on
+#[derive(Clone, Copy, Debug)]
+pub struct CascadeParams {
+    pub view: Matrix4<f32>,
+    // OpenGL style projection (NDC depth in [-1, 1]) limited by `far_clip`
+    pub proj: Matrix4<f32>,
+    pub near_clip: f32,
+    pub far_clip: f32,
+    // direction light travels in
+    pub light_direction: Vector3<f32>,
+    // 0: uniform splits, 1: logarithmic splits
+    pub split_lambda: f32,
+    pub cascade_count: usize,
+    // cascade resolution (texels), projection is snapped to texel grid
+    pub shadow_map_size: u32,
+}
+
+// Far plane of each cascade as a fraction of camera clip range.
+// Based on method presented in https://developer.nvidia.com/gpugems/GPUGems3/gpugems3_ch10.html
+pub fn cascade_splits(near_clip: f32, far_clip: f32, split_lambda: f32, cascade_count: usize) -> Vec<f32> {
+    let clip_range = far_clip - near_clip;
+    let ratio = far_clip / near_clip;
+
+    (0..cascade_count)
+        .map(|i| {
+            let p = (i + 1) as f32 / cascade_count as f32;
+            let log = near_clip * ratio.powf(p);
+            let uniform = near_clip + clip_range * p;
+            let d = split_lambda * (log - uniform) + uniform;
+
+            (d - near_clip) / clip_range
+        })
+        .collect()
+}
+
+// Light view-projection matrix for every cascade. Cascades are stable: size does not depend on
+// camera rotation (bounding sphere) and translation is snapped to shadow map texels, so shadow
+// edges don't shimmer when camera moves.
+pub fn compute_cascades(params: &CascadeParams) -> Vec<CascadeInfo> {
+    let clip_range = params.far_clip - params.near_clip;
+    let splits = cascade_splits(params.near_clip, params.far_clip, params.split_lambda, params.cascade_count);
+
+    // camera frustum in NDC, near plane first
+    let ndc_corners = [
+        Vector3::new(-1.0, 1.0, -1.0),
+        Vector3::new(1.0, 1.0, -1.0),
+        Vector3::new(1.0, -1.0, -1.0),
+        Vector3::new(-1.0, -1.0, -1.0),
+        Vector3::new(-1.0, 1.0, 1.0),
+        Vector3::new(1.0, 1.0, 1.0),
+        Vector3::new(1.0, -1.0, 1.0),
+        Vector3::new(-1.0, -1.0, 1.0),
+    ];
+
+    let inv_view_proj = (params.proj * params.view).invert().expect("Camera matrix is not invertible!");
+    let frustum_corners: Vec<Vector3<f32>> = ndc_corners
+        .iter()
+        .map(|corner| {
+            let world: Vector4<f32> = inv_view_proj * corner.extend(1.0);
+            world.truncate() / world.w
+        })
+        .collect();
+
+    let light_dir = params.light_direction.normalize();
+
+    // look_at is undefined when up is parallel to view direction
+    let up = if light_dir.y.abs() > 0.99 {
+        Vector3::new(1.0, 0.0, 0.0)
+    } else {
+        Vector3::new(0.0, 1.0, 0.0)
+    };
+
+    let mut cascades = Vec::with_capacity(params.cascade_count);
+    let mut last_split = 0.0;
+    for split in splits {
+        let mut corners = [Vector3::new(0.0, 0.0, 0.0); 8];
+        for i in 0..4 {
+            let ray = frustum_corners[i + 4] - frustum_corners[i];
+            corners[i] = frustum_corners[i] + ray * last_split;
+            corners[i + 4] = frustum_corners[i] + ray * split;
+        }
+
+        let mut center = Vector3::new(0.0, 0.0, 0.0);
+        for corner in corners.iter() {
+            center += *corner;
+        }
+        center /= corners.len() as f32;
+
+        let mut radius = 0.0 as f32;
+        for corner in corners.iter() {
+            radius = radius.max(corner.distance(center));
+        }
+        radius = (radius * 16.0).ceil() / 16.0;
+
+        let light_pos = center - light_dir * radius;
+        let view = Matrix4::look_at_rh(Point3::from_vec(light_pos), Point3::from_vec(center), up);
+        let mut proj = cgmath::ortho(-radius, radius, -radius, radius, 0.0, 2.0 * radius);
+
+        // move projection so that world origin lands exactly on a texel
+        let ndc_to_texels = params.shadow_map_size as f32 / 2.0;
+        let origin = proj * view * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let origin_x = origin.x * ndc_to_texels;
+        let origin_y = origin.y * ndc_to_texels;
+        proj.w.x += (origin_x.round() - origin_x) / ndc_to_texels;
+        proj.w.y += (origin_y.round() - origin_y) / ndc_to_texels;
+
+        cascades.push(CascadeInfo {
+            view_proj_mat: vulkan_clip_correction() * proj * view,
+            max_z: -(params.near_clip + split * clip_range),
+        });
+
+        last_split = split;
+    }
+
+    cascades
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Vector4};
+
+    const EPSILON: f32 = 1e-5;
+
+    fn params(view: Matrix4<f32>) -> CascadeParams {
+        CascadeParams {
+            view,
+            proj: cgmath::perspective(Deg(45.0), 16.0 / 9.0, 0.1, 50.0),
+            near_clip: 0.1,
+            far_clip: 50.0,
+            light_direction: Vector3::new(-0.3, 1.0, 0.4),
+            split_lambda: 0.8,
+            cascade_count: 4,
+            shadow_map_size: 2048,
+        }
+    }
+
+    fn camera(x: f32) -> Matrix4<f32> {
+        Matrix4::look_at_rh(Point3::new(x, -2.0, 5.0), Point3::new(x, -1.0, 0.0), Vector3::new(0.0, -1.0, 0.0))
+    }
+
+    #[test]
+    fn uniform_splits() {
+        let splits = cascade_splits(1.0, 101.0, 0.0, 4);
+        for (i, split) in splits.iter().enumerate() {
+            assert!((split - (i + 1) as f32 / 4.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn logarithmic_splits() {
+        let (near, far) = (1.0, 16.0);
+        let splits = cascade_splits(near, far, 1.0, 4);
+        // every split is twice as far as the previous one
+        for (split, distance) in splits.iter().zip([2.0, 4.0, 8.0, 16.0]) {
+            assert!((near + split * (far - near) - distance).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn lambda_blends_uniform_and_logarithmic() {
+        let uniform = cascade_splits(0.1, 50.0, 0.0, 4);
+        let log = cascade_splits(0.1, 50.0, 1.0, 4);
+        let blend = cascade_splits(0.1, 50.0, 0.25, 4);
+        for i in 0..4 {
+            let expected = 0.75 * uniform[i] + 0.25 * log[i];
+            assert!((blend[i] - expected).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn splits_increase_up_to_far_plane() {
+        for &lambda in &[0.0, 0.3, 0.8, 1.0] {
+            for count in 1..=6 {
+                let splits = cascade_splits(0.05, 48.0, lambda, count);
+                assert_eq!(splits.len(), count);
+                assert!(splits[0] > 0.0);
+                assert!(splits.windows(2).all(|w| w[0] < w[1]));
+                assert!((splits[count - 1] - 1.0).abs() < EPSILON);
+            }
+        }
+
+        let cascades = compute_cascades(&params(camera(0.0)));
+        assert!((cascades.last().unwrap().max_z + 50.0).abs() < 1e-3);
+        assert!(cascades.windows(2).all(|w| w[0].max_z > w[1].max_z));
+    }
+
+    #[test]
+    fn world_origin_snapped_to_texels() {
+        let params = params(camera(0.0));
+        let ndc_to_texels = params.shadow_map_size as f32 / 2.0;
+        for cascade in compute_cascades(&params) {
+            let origin = cascade.view_proj_mat * Vector4::new(0.0, 0.0, 0.0, 1.0);
+            let x = origin.x * ndc_to_texels;
+            let y = origin.y * ndc_to_texels;
+            assert!((x - x.round()).abs() < 1e-2);
+            assert!((y - y.round()).abs() < 1e-2);
+        }
+    }
+
+    #[test]
+    fn stable_under_subtexel_camera_motion() {
+        let before = compute_cascades(&params(camera(0.0)));
+        // much less than a texel of the smallest cascade
+        let after = compute_cascades(&params(camera(1e-4)));
+
+        let size = 2048.0 / 2.0;
+        for (a, b) in before.iter().zip(after.iter()) {
+            // same world point maps to the same texel
+            for point in [Vector4::new(0.0, 0.0, 0.0, 1.0), Vector4::new(3.0, -1.0, -7.0, 1.0)] {
+                let pa = a.view_proj_mat * point;
+                let pb = b.view_proj_mat * point;
+                assert!(((pa.x - pb.x) * size).abs() < 1e-2);
+                assert!(((pa.y - pb.y) * size).abs() < 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn clip_correction_to_vulkan() {
+        let correction = vulkan_clip_correction();
+
+        let near = correction * Vector4::new(0.5, 0.5, -1.0, 1.0);
+        assert_eq!(near, Vector4::new(0.5, -0.5, 0.0, 1.0));
+
+        let far = correction * Vector4::new(-0.5, -0.5, 1.0, 1.0);
+        assert_eq!(far, Vector4::new(-0.5, 0.5, 1.0, 1.0));
+
+        // homogeneous coordinates keep w
+        let clip = correction * Vector4::new(1.0, 2.0, 0.0, 2.0);
+        assert_eq!(clip, Vector4::new(1.0, -2.0, 1.0, 2.0));
+    }
+}
@@ -0,0 +1,134 @@
+use std::ptr;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+use cgmath::Matrix4;
+
+use ash_render_env::env::RenderEnv;
+use ash_render_env::math::{Aabb, Frustum};
+use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+
+use crate::shadow_map::{CascadeInfo, ShadowMapFramebuffer};
+
+// Renderer that draws its geometry into shadow cascades (depth only). Draws are recorded inside
+// cascade render pass with viewport and scissor already set.
+pub trait ShadowCaster {
+    // (Re)creates depth-only pipeline, called when shadow render pass changes (depth format)
+    fn create_shadow_pipeline(&mut self, render_pass: vk::RenderPass);
+
+    // Records draws of parts visible in `frustum`. Returns number of draw calls.
+    fn record_shadow_draws(&self, device: &ash::Device, cmd_buf: vk::CommandBuffer, frustum: &ShadowFrustum) -> u32;
+}
+
+// Cascade volume. Near plane is not included: shadow pipelines clamp depth, casters between light
+// and cascade still cast shadows into it.
+pub struct ShadowFrustum {
+    pub view_proj: Matrix4<f32>,
+    frustum: Frustum,
+}
+
+impl ShadowFrustum {
+    // `view_proj` maps to Vulkan clip space (depth in [0, 1])
+    pub fn new(view_proj: Matrix4<f32>) -> ShadowFrustum {
+        ShadowFrustum {
+            view_proj,
+            frustum: Frustum::from_matrix(view_proj).without_near_plane(),
+        }
+    }
+
+    pub fn intersects(&self, bounds: &Aabb) -> bool {
+        self.frustum.intersects_aabb(bounds)
+    }
+}
+
+// Renders shadow casters into every cascade of shadow map, one render pass per cascade
+pub struct CascadeShadowPass {
+    commands: PrimaryCommandBuffer,
+    // draw calls of the last frame, per cascade
+    draw_counts: Vec<u32>,
+}
+
+impl CascadeShadowPass {
+    pub fn new(env: Arc<RenderEnv>, max_frames_in_flight: usize) -> CascadeShadowPass {
+        CascadeShadowPass {
+            commands: PrimaryCommandBuffer::new(env, max_frames_in_flight).debug_name("shadow_cascades"),
+            draw_counts: Vec::new(),
+        }
+    }
+
+    pub fn draw_counts(&self) -> &[u32] {
+        &self.draw_counts
+    }
+
+    pub fn execute(&mut self, shadow_map: &ShadowMapFramebuffer, cascades: &[CascadeInfo],
+                   casters: &[&dyn ShadowCaster]) -> vk::CommandBuffer {
+        let resolution = shadow_map.settings().resolution;
+        let clear_values = [vk::ClearValue {
+            depth_stencil: vk::ClearDepthStencilValue {
+                depth: 1.0,
+                stencil: 0,
+            }
+        }];
+
+        let viewports = [vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: resolution as f32,
+            height: resolution as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }];
+
+        let scissors = [vk::Rect2D {
+            offset: vk::Offset2D { x: 0, y: 0 },
+            extent: vk::Extent2D {
+                width: resolution,
+                height: resolution,
+            },
+        }];
+
+        let draw_counts = &mut self.draw_counts;
+        draw_counts.clear();
+
+        self.commands.execute(|device, cmd_buf| unsafe {
+            for (cascade_idx, cascade) in cascades.iter().enumerate() {
+                let render_pass_begin_info = vk::RenderPassBeginInfo {
+                    s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+                    p_next: ptr::null(),
+                    render_pass: shadow_map.render_pass(),
+                    framebuffer: shadow_map.framebuffer(cascade_idx),
+                    render_area: scissors[0],
+                    clear_value_count: clear_values.len() as u32,
+                    p_clear_values: clear_values.as_ptr(),
+                };
+
+                device.cmd_begin_render_pass(cmd_buf, &render_pass_begin_info, vk::SubpassContents::INLINE);
+                device.cmd_set_viewport(cmd_buf, 0, &viewports);
+                device.cmd_set_scissor(cmd_buf, 0, &scissors);
+
+                let frustum = ShadowFrustum::new(cascade.view_proj_mat);
+                let mut draw_count = 0;
+                for caster in casters.iter() {
+                    draw_count += caster.record_shadow_draws(device, cmd_buf, &frustum);
+                }
+                draw_counts.push(draw_count);
+
+                device.cmd_end_render_pass(cmd_buf);
+            }
+        })
+    }
+}
+
+// Light matrix for push constants of depth-only shaders (`mat4` at offset 0)
+pub fn matrix_push_constants(matrix: Matrix4<f32>) -> Vec<u8> {
+    let matrix: [[f32; 4]; 4] = matrix.into();
+    let mut params = Vec::new();
+    for column in matrix.iter() {
+        for value in column.iter() {
+            params.extend(value.to_le_bytes());
+        }
+    }
+
+    params
+}
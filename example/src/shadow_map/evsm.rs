@@ -0,0 +1,268 @@
+use std::ptr;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use ash_render_env::compute_pipeline::ComputePipeline;
+use ash_render_env::descriptor_set::{DescriptorSet, DescriptorSetBuilder};
+use ash_render_env::env::RenderEnv;
+use ash_render_env::primary_cmd_buffer::PrimaryCommandBuffer;
+use ash_render_env::shader;
+use ash_render_env::utils::texture_utils::create_image;
+
+use crate::shadow_map::ShadowMapFramebuffer;
+
+// Moments are filtered, so they don't need full shadow map resolution (and take 4x more memory)
+pub const EVSM_MAX_RESOLUTION: u32 = 1024;
+
+const MOMENTS_FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+const GROUP_SIZE: u32 = 16;
+
+struct MomentsImage {
+    image: vk::Image,
+    memory: vk::DeviceMemory,
+    view: vk::ImageView,
+}
+
+// Exponential variance shadow map: shadow map depth -> warped moments (evsm_moments.comp) ->
+// separable box blur (evsm_blur.comp). Result (`moments_view`, GENERAL layout) is sampled by compose pass.
+pub struct EvsmShadowMap {
+    moments_pipeline: ComputePipeline,
+    blur_pipeline: ComputePipeline,
+    moments_descriptor_set: DescriptorSet,
+    // result -> temp and temp -> result
+    blur_descriptor_sets: [DescriptorSet; 2],
+
+    // `images[0]` holds result, `images[1]` is blur temp
+    images: [MomentsImage; 2],
+    resolution: u32,
+    cascade_count: u32,
+
+    commands: PrimaryCommandBuffer,
+    sampler: vk::Sampler,
+    env: Arc<RenderEnv>,
+}
+
+impl EvsmShadowMap {
+    pub fn new(env: Arc<RenderEnv>, shadow_map: &ShadowMapFramebuffer, max_frames_in_flight: usize) -> EvsmShadowMap {
+        let moments_pipeline = ComputePipeline::new(
+            env.device().clone(),
+            shader::Shader::load(env.device(), "assets/shaders/spv/shadow_map/evsm_moments.comp.spv"),
+        ).debug_name(&env, "evsm_moments");
+
+        let blur_pipeline = ComputePipeline::new(
+            env.device().clone(),
+            shader::Shader::load(env.device(), "assets/shaders/spv/shadow_map/evsm_blur.comp.spv"),
+        ).debug_name(&env, "evsm_blur");
+
+        let resolution = shadow_map.settings().resolution.min(EVSM_MAX_RESOLUTION);
+        let cascade_count = shadow_map.cascade_count() as u32;
+        let images = [
+            create_moments_image(&env, resolution, cascade_count, "evsm.moments0"),
+            create_moments_image(&env, resolution, cascade_count, "evsm.moments1"),
+        ];
+
+        let sampler = create_nearest_sampler(env.device());
+        env.set_name(sampler, "evsm.sampler");
+
+        let moments_descriptor_set = DescriptorSetBuilder::new(
+            env.device(), moments_pipeline.descriptor_set_layouts.get(0).unwrap())
+            .add_image_with_layout(shadow_map.view, sampler, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+            .add_storage_image(images[0].view)
+            .build();
+
+        let blur_descriptor_sets = [
+            DescriptorSetBuilder::new(env.device(), blur_pipeline.descriptor_set_layouts.get(0).unwrap())
+                .add_storage_image(images[0].view)
+                .add_storage_image(images[1].view)
+                .build(),
+            DescriptorSetBuilder::new(env.device(), blur_pipeline.descriptor_set_layouts.get(0).unwrap())
+                .add_storage_image(images[1].view)
+                .add_storage_image(images[0].view)
+                .build(),
+        ];
+
+        EvsmShadowMap {
+            moments_pipeline,
+            blur_pipeline,
+            moments_descriptor_set,
+            blur_descriptor_sets,
+            images,
+            resolution,
+            cascade_count,
+            commands: PrimaryCommandBuffer::new(env.clone(), max_frames_in_flight).debug_name("evsm"),
+            sampler,
+            env,
+        }
+    }
+
+    pub fn moments_view(&self) -> vk::ImageView {
+        self.images[0].view
+    }
+
+    // Must be submitted after shadow map pass and before compose
+    pub fn execute(&mut self, blur_radius: u32) -> vk::CommandBuffer {
+        let moments_pipeline = &self.moments_pipeline;
+        let blur_pipeline = &self.blur_pipeline;
+        let moments_descriptor_set = self.moments_descriptor_set.set;
+        let blur_descriptor_sets = [self.blur_descriptor_sets[0].set, self.blur_descriptor_sets[1].set];
+        let images = [self.images[0].image, self.images[1].image];
+        let cascade_count = self.cascade_count;
+        let group_count = (self.resolution + GROUP_SIZE - 1) / GROUP_SIZE;
+
+        self.commands.execute(|device, cmd_buf| unsafe {
+            // previous content is not needed, every texel is overwritten
+            let to_general: Vec<vk::ImageMemoryBarrier> = images.iter()
+                .map(|image| image_barrier(*image, cascade_count, vk::ImageLayout::UNDEFINED,
+                                           vk::AccessFlags::empty(), vk::AccessFlags::SHADER_WRITE))
+                .collect();
+            device.cmd_pipeline_barrier(
+                cmd_buf,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &to_general,
+            );
+
+            device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::COMPUTE, moments_pipeline.pipeline);
+            device.cmd_bind_descriptor_sets(cmd_buf, vk::PipelineBindPoint::COMPUTE, moments_pipeline.pipeline_layout,
+                                            0, &[moments_descriptor_set], &[]);
+            device.cmd_dispatch(cmd_buf, group_count, group_count, cascade_count);
+
+            // horizontal: result -> temp, vertical: temp -> result
+            device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::COMPUTE, blur_pipeline.pipeline);
+            for (pass, direction) in [[1_i32, 0_i32], [0, 1]].iter().enumerate() {
+                compute_barrier(device, cmd_buf, images[pass], cascade_count, vk::PipelineStageFlags::COMPUTE_SHADER);
+
+                let mut params = Vec::new();
+                params.extend(direction[0].to_le_bytes());
+                params.extend(direction[1].to_le_bytes());
+                params.extend((blur_radius as i32).to_le_bytes());
+
+                device.cmd_bind_descriptor_sets(cmd_buf, vk::PipelineBindPoint::COMPUTE, blur_pipeline.pipeline_layout,
+                                                0, &[blur_descriptor_sets[pass]], &[]);
+                device.cmd_push_constants(cmd_buf, blur_pipeline.pipeline_layout, vk::ShaderStageFlags::COMPUTE, 0, &params);
+                device.cmd_dispatch(cmd_buf, group_count, group_count, cascade_count);
+            }
+
+            // moments are read by compose fragment shader
+            compute_barrier(device, cmd_buf, images[0], cascade_count, vk::PipelineStageFlags::FRAGMENT_SHADER);
+        })
+    }
+}
+
+impl Drop for EvsmShadowMap {
+    fn drop(&mut self) {
+        unsafe {
+            self.env.device().destroy_sampler(self.sampler, None);
+            for image in self.images.iter() {
+                self.env.device().destroy_image_view(image.view, None);
+                self.env.device().destroy_image(image.image, None);
+                self.env.device().free_memory(image.memory, None);
+            }
+        }
+    }
+}
+
+fn image_barrier(image: vk::Image, layer_count: u32, old_layout: vk::ImageLayout,
+                 src_access_mask: vk::AccessFlags, dst_access_mask: vk::AccessFlags) -> vk::ImageMemoryBarrier {
+    vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout: vk::ImageLayout::GENERAL,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count,
+        },
+    }
+}
+
+// Compute shader writes to `image` are visible to reads in `dst_stage`
+unsafe fn compute_barrier(device: &ash::Device, cmd_buf: vk::CommandBuffer, image: vk::Image, layer_count: u32,
+                          dst_stage: vk::PipelineStageFlags) {
+    device.cmd_pipeline_barrier(
+        cmd_buf,
+        vk::PipelineStageFlags::COMPUTE_SHADER,
+        dst_stage,
+        vk::DependencyFlags::empty(),
+        &[],
+        &[],
+        &[image_barrier(image, layer_count, vk::ImageLayout::GENERAL,
+                        vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::SHADER_READ)],
+    );
+}
+
+// Image, memory and view are named "<name>", "<name>.memory" and "<name>.view"
+fn create_moments_image(env: &RenderEnv, resolution: u32, layer_count: u32, name: &str) -> MomentsImage {
+    let (image, memory) = create_image(
+        env.device(),
+        resolution,
+        resolution,
+        layer_count,
+        1,
+        vk::SampleCountFlags::TYPE_1,
+        MOMENTS_FORMAT,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        &env.mem_properties,
+        Some((env.debug_names(), name)),
+    );
+
+    let imageview_create_info = vk::ImageViewCreateInfo {
+        s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::ImageViewCreateFlags::empty(),
+        view_type: vk::ImageViewType::TYPE_2D_ARRAY,
+        format: MOMENTS_FORMAT,
+        components: vk::ComponentMapping {
+            r: vk::ComponentSwizzle::IDENTITY,
+            g: vk::ComponentSwizzle::IDENTITY,
+            b: vk::ComponentSwizzle::IDENTITY,
+            a: vk::ComponentSwizzle::IDENTITY,
+        },
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count,
+        },
+        image,
+    };
+
+    let view = unsafe {
+        env.device()
+            .create_image_view(&imageview_create_info, None)
+            .expect("Failed to create Image View!")
+    };
+    env.set_name(view, &format!("{}.view", name));
+
+    MomentsImage { image, memory, view }
+}
+
+fn create_nearest_sampler(device: &ash::Device) -> vk::Sampler {
+    let sampler_create_info = vk::SamplerCreateInfo::builder()
+        .min_filter(vk::Filter::NEAREST)
+        .mag_filter(vk::Filter::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .anisotropy_enable(false);
+
+    unsafe {
+        device.create_sampler(&sampler_create_info, None).unwrap()
+    }
+}
@@ -0,0 +1,61 @@
+// Value is specialization constant SHADOW_FILTER of compose.frag (SHADOW_FILTER_* defines)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShadowFilter {
+    // single depth test, aliased edges
+    Hard,
+    // 5x5 grid of hardware compare (bilinear) samples
+    Pcf,
+    // 16 tap Poisson disk rotated per pixel
+    PoissonPcf,
+    // percentage-closer soft shadows: penumbra grows with blocker distance
+    Pcss,
+    // exponential variance shadow maps, moments are blurred in compute pass
+    Evsm,
+}
+
+impl ShadowFilter {
+    pub const ALL: [ShadowFilter; 5] = [
+        ShadowFilter::Hard,
+        ShadowFilter::Pcf,
+        ShadowFilter::PoissonPcf,
+        ShadowFilter::Pcss,
+        ShadowFilter::Evsm,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShadowFilter::Hard => "Hard",
+            ShadowFilter::Pcf => "PCF (hardware compare)",
+            ShadowFilter::PoissonPcf => "Poisson PCF",
+            ShadowFilter::Pcss => "PCSS",
+            ShadowFilter::Evsm => "EVSM",
+        }
+    }
+}
+
+// Filter parameters that can change every frame (compose uniforms and EVSM blur)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ShadowFilterParams {
+    // depth offset against shadow acne (light clip space)
+    pub bias: f32,
+    // PCF and Poisson PCF kernel radius in shadow map texels
+    pub filter_radius: f32,
+    // PCSS: tangent of light angular radius, bigger light gives wider penumbra
+    pub light_size: f32,
+    // EVSM: box blur radius in moment texels
+    pub evsm_blur_radius: u32,
+    // EVSM: part of Chebyshev bound cut off against light bleeding
+    pub evsm_bleed_reduction: f32,
+}
+
+impl Default for ShadowFilterParams {
+    fn default() -> Self {
+        ShadowFilterParams {
+            bias: 0.005,
+            filter_radius: 1.5,
+            light_size: 0.05,
+            evsm_blur_radius: 2,
+            evsm_bleed_reduction: 0.2,
+        }
+    }
+}
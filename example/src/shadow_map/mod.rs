@@ -1,4 +1,11 @@
 mod render_target;
+pub mod atlas;
+pub mod cascaded;
+pub mod cascades;
+pub mod caster;
+pub mod evsm;
+pub mod filtering;
 pub mod uniform_buffer;
 
 pub use render_target::*;
+pub use cascades::CascadeInfo;
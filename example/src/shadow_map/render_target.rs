@@ -3,17 +3,59 @@ use std::sync::Arc;
 
 use ash::version::DeviceV1_0;
 use ash::vk;
-use cgmath::{InnerSpace, Matrix4, MetricSpace, Point3, SquareMatrix, Transform, Vector3, Vector4};
 
 use ash_render_env::camera::Camera;
 use ash_render_env::env::RenderEnv;
-use std::ops::{Sub, Add};
 
-pub const CASCADE_COUNT: usize = 4;
+use crate::lighting::directional_light::DirectionalLight;
+use crate::shadow_map::cascades::{CascadeInfo, CascadeParams, compute_cascades};
+use crate::shadow_map::filtering::ShadowFilter;
+
+// Size of cascade arrays in compose.frag uniforms
+pub const MAX_CASCADE_COUNT: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ShadowSettings {
+    // width and height of every cascade
+    pub resolution: u32,
+    pub cascade_count: usize,
+    pub depth_format: vk::Format,
+    // compose pipeline is specialized for the filter
+    pub filter: ShadowFilter,
+}
 
-pub struct CascadeInfo {
-    pub view_proj_mat: Matrix4<f32>,
-    pub max_z: f32,
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            resolution: 4096,
+            cascade_count: 4,
+            depth_format: vk::Format::D32_SFLOAT,
+            filter: ShadowFilter::Pcss,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub const RESOLUTIONS: [u32; 4] = [512, 1024, 2048, 4096];
+    pub const DEPTH_FORMATS: [vk::Format; 2] = [vk::Format::D16_UNORM, vk::Format::D32_SFLOAT];
+
+    // for low-end GPUs
+    pub fn low() -> ShadowSettings {
+        ShadowSettings {
+            resolution: 1024,
+            cascade_count: 2,
+            depth_format: vk::Format::D16_UNORM,
+            filter: ShadowFilter::Pcf,
+        }
+    }
+
+    pub fn depth_format_name(format: vk::Format) -> &'static str {
+        match format {
+            vk::Format::D16_UNORM => "D16 unorm",
+            vk::Format::D32_SFLOAT => "D32 float",
+            _ => "Unknown",
+        }
+    }
 }
 
 struct Cascade {
@@ -33,8 +75,7 @@ impl Drop for Cascade {
 }
 
 pub struct ShadowMapFramebuffer {
-    width: u32,
-    height: u32,
+    settings: ShadowSettings,
 
     cascades: Vec<Cascade>,
 
@@ -47,10 +88,14 @@ pub struct ShadowMapFramebuffer {
 }
 
 impl ShadowMapFramebuffer {
-    pub fn new(env: Arc<RenderEnv>) -> ShadowMapFramebuffer {
-        let (cascade_width, cascade_height) = (4096 as u32, 4096 as u32);
-        let depth_format = vk::Format::D32_SFLOAT;
+    pub fn new(env: Arc<RenderEnv>, settings: &ShadowSettings) -> ShadowMapFramebuffer {
+        assert!(settings.cascade_count >= 1 && settings.cascade_count <= MAX_CASCADE_COUNT,
+                "Cascade count must be in 1..={}", MAX_CASCADE_COUNT);
+
+        let (cascade_width, cascade_height) = (settings.resolution, settings.resolution);
+        let depth_format = settings.depth_format;
         let render_pass = create_render_pass(env.device(), depth_format);
+        env.set_name(render_pass, "shadow_map.render_pass");
 
 
         let image_create_info = vk::ImageCreateInfo {
@@ -65,10 +110,10 @@ impl ShadowMapFramebuffer {
                 depth: 1,
             },
             mip_levels: 1,
-            array_layers: CASCADE_COUNT as u32,
+            array_layers: settings.cascade_count as u32,
             samples: vk::SampleCountFlags::TYPE_1,
             tiling: vk::ImageTiling::OPTIMAL,
-            usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC,
             sharing_mode: vk::SharingMode::EXCLUSIVE,
             queue_family_index_count: 0,
             p_queue_family_indices: ptr::null(),
@@ -106,6 +151,8 @@ impl ShadowMapFramebuffer {
                 .bind_image_memory(shadow_map_image, shadow_map_memory, 0)
                 .expect("Failed to bind Image Memmory!");
         }
+        env.set_name(shadow_map_image, "shadow_map");
+        env.set_name(shadow_map_memory, "shadow_map.memory");
 
         let imageview_create_info = vk::ImageViewCreateInfo {
             s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
@@ -124,7 +171,7 @@ impl ShadowMapFramebuffer {
                 base_mip_level: 0,
                 level_count: 1,
                 base_array_layer: 0,
-                layer_count: CASCADE_COUNT as u32,
+                layer_count: settings.cascade_count as u32,
             },
             image: shadow_map_image,
         };
@@ -134,11 +181,12 @@ impl ShadowMapFramebuffer {
                 .create_image_view(&imageview_create_info, None)
                 .expect("Failed to create Image View!")
         };
+        env.set_name(view, "shadow_map.view");
 
 
         // CREATE CASCADES VIEWS AND FRAMEBUFFERS
-        let mut cascades = Vec::with_capacity(CASCADE_COUNT);
-        for i in 0..CASCADE_COUNT {
+        let mut cascades = Vec::with_capacity(settings.cascade_count);
+        for i in 0..settings.cascade_count {
             let imageview_create_info = vk::ImageViewCreateInfo {
                 s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
                 p_next: ptr::null(),
@@ -183,6 +231,8 @@ impl ShadowMapFramebuffer {
             let cascade_framebuffer = unsafe {
                 env.device().create_framebuffer(&framebuffer_info, None).unwrap()
             };
+            env.set_name(cascade_image_view, &format!("shadow_map.cascade{}.view", i));
+            env.set_name(cascade_framebuffer, &format!("shadow_map.cascade{}.framebuffer", i));
 
             cascades.push(Cascade {
                 device: env.device().clone(),
@@ -194,8 +244,7 @@ impl ShadowMapFramebuffer {
 
         ShadowMapFramebuffer {
             device: env.device().clone(),
-            width: cascade_width,
-            height: cascade_height,
+            settings: *settings,
             render_pass,
             image: shadow_map_image,
             memory: shadow_map_memory,
@@ -204,6 +253,19 @@ impl ShadowMapFramebuffer {
         }
     }
 
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.settings.cascade_count
+    }
+
+    // Array image, layer per cascade, DEPTH_STENCIL_READ_ONLY_OPTIMAL after the shadow pass
+    pub fn image(&self) -> vk::Image {
+        self.image
+    }
+
     pub fn get_cascade_view(&self, index: usize) -> vk::ImageView {
         self.cascades[index].view.clone()
     }
@@ -216,108 +278,17 @@ impl ShadowMapFramebuffer {
         self.render_pass.clone()
     }
 
-    pub fn update_cascades(&mut self, camera: &Camera, cascade_split_lambda: f32) -> Vec<CascadeInfo> {
-        let near_clip = camera.near_clip;
-        let far_clip = camera.far_clip;
-        let clip_range = far_clip - near_clip;
-
-        let min_z = camera.near_clip;
-        let max_z = near_clip + clip_range;
-
-        let z_range = max_z - min_z;
-        let ratio = max_z / min_z;
-
-        // Calculate split depths based on view camera frustum
-        // Based on method presented in https://developer.nvidia.com/gpugems/GPUGems3/gpugems3_ch10.html
-        let mut camera_splits = Vec::new();
-        for i in 0..CASCADE_COUNT {
-            let p = (i + 1) as f32 / CASCADE_COUNT as f32;
-            let log = min_z * ratio.powf(p);
-            let uniform = min_z + z_range * p;
-            let d = cascade_split_lambda * (log - uniform) + uniform;
-
-            camera_splits.push((d - near_clip) / clip_range);
-        }
-
-        let frustum_corners = [
-            cgmath::Vector3::<f32>::new(-1.0, 1.0, -1.0),
-            cgmath::Vector3::<f32>::new(1.0, 1.0, -1.0),
-            cgmath::Vector3::<f32>::new(1.0, -1.0, -1.0),
-            cgmath::Vector3::<f32>::new(-1.0, -1.0, -1.0),
-            cgmath::Vector3::<f32>::new(-1.0, 1.0, 1.0),
-            cgmath::Vector3::<f32>::new(1.0, 1.0, 1.0),
-            cgmath::Vector3::<f32>::new(1.0, -1.0, 1.0),
-            cgmath::Vector3::<f32>::new(-1.0, -1.0, 1.0),
-        ];
-
-        let inv_cam = (camera.proj_matrix() * camera.view_matrix()).invert().unwrap();
-
-        let mut last_split_dist = 0.0;
-        let mut cascades = Vec::<CascadeInfo>::new();
-        for cascade_index in 0..CASCADE_COUNT {
-            let mut camera_corners = vec![];
-            for corner in frustum_corners.iter().cloned() {
-                let inv_corner: Vector4<f32> = inv_cam * corner.extend(1.0);
-                camera_corners.push(inv_corner.truncate() / inv_corner.w)
-            }
-
-            let split_dist = camera_splits[cascade_index];
-            for i in 0..4 {
-                let dist = camera_corners[i + 4].sub(camera_corners[i]);
-
-                camera_corners[i + 4] = camera_corners[i].add(dist * split_dist);
-                camera_corners[i] = camera_corners[i].add(dist * last_split_dist);
-            }
-
-            let mut frustum_center = Vector3::new(0.0, 0.0, 0.0);
-            for v in camera_corners.iter().cloned() {
-                frustum_center += v;
-            }
-            frustum_center /= (camera_corners.len() as f32);
-
-            let mut radius = 0.0 as f32;
-            for v in camera_corners.iter() {
-                let dist = v.distance(frustum_center);
-                radius = radius.max(dist);
-            }
-            radius = (radius * 16.0).ceil() / 16.0;
-
-            let max_extents = cgmath::Vector3::new(radius, radius, radius);
-            let min_extents = -max_extents;
-
-            let light_dir = (Vector3::new(0.70, 0.25, -0.67)).normalize();
-            let light_pos = frustum_center - light_dir * (-min_extents.z);
-
-            let view: Matrix4<f32> = cgmath::Matrix4::look_at_rh(
-                Point3::new(light_pos.x, light_pos.y, light_pos.z),
-                Point3::new(frustum_center.x, frustum_center.y, frustum_center.z),
-                Vector3::new(0.0, 1.0, 0.0),
-            );
-
-            let proj = cgmath::ortho(
-                min_extents.x, max_extents.x,
-                min_extents.y, max_extents.y,
-                0.0, (max_extents.z - min_extents.z),
-            );
-
-            // TODO: c1r1 need to be -1.0 (https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/)
-            let corr_matrix = cgmath::Matrix4::<f32>::new(
-                1.0, 0.0, 0.0, 0.0,
-                0.0, 1.0, 0.0, 0.0,
-                0.0, 0.0, 0.5, 0.0,
-                0.0, 0.0, 0.5, 1.0,
-            );
-            let split_depth = (camera.near_clip + split_dist * clip_range) * -1.0;
-            cascades.push(CascadeInfo {
-                view_proj_mat: corr_matrix * proj * view,
-                max_z: split_depth,
-            });
-            println!("cascade_idx={:?}, min={:?}, max={:?}, split_depth={:?}", cascade_index, min_extents, max_extents, split_depth);
-
-            last_split_dist = camera_splits[cascade_index];
-        }
-
-        cascades
+    pub fn update_cascades(&mut self, camera: &Camera, light: &DirectionalLight, cascade_split_lambda: f32) -> Vec<CascadeInfo> {
+        compute_cascades(&CascadeParams {
+            view: camera.view_matrix(),
+            proj: camera.frustum_proj_matrix(),
+            near_clip: camera.projection().near_clip,
+            far_clip: camera.projection().far_clip,
+            light_direction: light.direction,
+            split_lambda: cascade_split_lambda,
+            cascade_count: self.settings.cascade_count,
+            shadow_map_size: self.settings.resolution,
+        })
     }
 }
 
@@ -332,7 +303,8 @@ impl Drop for ShadowMapFramebuffer {
     }
 }
 
-fn create_render_pass(device: &ash::Device, depth_format: vk::Format) -> vk::RenderPass {
+// Single depth attachment, result is read by fragment shaders
+pub(super) fn create_render_pass(device: &ash::Device, depth_format: vk::Format) -> vk::RenderPass {
     let attachments = [vk::AttachmentDescription {
         flags: vk::AttachmentDescriptionFlags::empty(),
         format: depth_format,
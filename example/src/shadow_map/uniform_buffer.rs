@@ -6,14 +6,8 @@ use ash::vk;
 use ash_render_env::env::RenderEnv;
 use ash_render_env::utils::buffer_utils::create_buffer_;
 use std::marker::PhantomData;
-use cgmath::Matrix4;
 use std::ptr;
 
-#[repr(C)]
-pub struct ShadowMapData {
-    pub light_wp: Matrix4<f32>,
-}
-
 pub struct UniformBuffer<T> {
     pub buffer: vk::Buffer,
     pub buffer_memory: vk::DeviceMemory,
@@ -42,6 +36,7 @@ impl<T> UniformBuffer<T> {
             vk::BufferUsageFlags::UNIFORM_BUFFER,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
             &env.mem_properties,
+            None,
         );
 
         UniformBuffer {
@@ -53,6 +48,13 @@ impl<T> UniformBuffer<T> {
         }
     }
 
+    // Names buffer "<name>" and its memory "<name>.memory"
+    pub fn debug_name(self, env: &RenderEnv, name: &str) -> Self {
+        env.set_name(self.buffer, name);
+        env.set_name(self.buffer_memory, &format!("{}.memory", name));
+        self
+    }
+
     pub fn write_data(&self, data: T) {
         let buffer_size = std::mem::size_of::<T>() as u64;
 
@@ -0,0 +1,200 @@
+use std::io;
+use std::path::Path;
+
+use ash_render_env::camera::Camera;
+use ash_render_env::camera_path::{CameraPath, CameraPathPlayer, CameraPathRecorder};
+use ash_render_env::frame_capture::FrameCapture;
+use ash_render_env::input::InputState;
+
+const CAMERA_PATH_FILE: &str = "camera_path.txt";
+// seconds between recorded keyframes
+const RECORD_INTERVAL: f32 = 0.25;
+// PNG frames of capture started from GUI
+const CAPTURE_DIR: &str = "capture";
+
+// GUI actions that need the application
+pub enum CameraPathEvent {
+    // playback stopped, camera controller continues from the current camera
+    Stopped,
+    // capture started, frames are rendered with fixed timestep until it is finished
+    CaptureStarted,
+}
+
+// Camera path recording, playback, save, load and capture of played frames
+pub struct CameraPathControl {
+    // last recorded or loaded path
+    path: Option<CameraPath>,
+    recorder: Option<CameraPathRecorder>,
+    // drives camera instead of controller while playing
+    player: Option<CameraPathPlayer>,
+    // writes every frame of playback, see `start_capture`
+    capture: Option<FrameCapture>,
+    // frame count or the first error of the last finished capture
+    capture_result: Option<io::Result<u32>>,
+    // camera path time per rendered frame during playback
+    timestep: f32,
+}
+
+impl CameraPathControl {
+    pub fn new(timestep: f32) -> CameraPathControl {
+        CameraPathControl {
+            path: None,
+            recorder: None,
+            player: None,
+            capture: None,
+            capture_result: None,
+            timestep,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.player.is_some()
+    }
+
+    // Plays `path` with fixed `timestep`, recording is stopped
+    pub fn play(&mut self, path: CameraPath, timestep: f32) {
+        self.recorder = None;
+        self.player = Some(CameraPathPlayer::new(path, timestep));
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    // Plays `path` with fixed `timestep`, every played frame is expected in `capture_frame`
+    pub fn start_capture(&mut self, path: CameraPath, timestep: f32, capture: FrameCapture) {
+        self.play(path, timestep);
+        self.capture = Some(capture);
+        self.capture_result = None;
+    }
+
+    // Playback stops at the first write error, `finish_capture` reports it instead of the frame count
+    pub fn capture_frame(&mut self, frame: &image::RgbaImage) {
+        let capture = self.capture.as_mut().expect("Capture is not started!");
+        if let Err(err) = capture.write_frame(frame) {
+            log::error!("Failed to write captured frame: {}", err);
+            self.capture_result = Some(Err(err));
+            self.player = None;
+        }
+    }
+
+    // Called when playback is finished or stopped
+    pub fn finish_capture(&mut self) {
+        if let Some(capture) = self.capture.take() {
+            let result = capture.finish();
+            match &result {
+                Ok(frame_count) => log::info!("captured {} frames", frame_count),
+                Err(err) => log::error!("Failed to finish capture: {}", err),
+            }
+            if self.capture_result.is_none() {
+                self.capture_result = Some(result);
+            }
+        }
+    }
+
+    pub fn take_capture_result(&mut self) -> Option<io::Result<u32>> {
+        self.capture_result.take()
+    }
+
+    // Moves camera along the played path. Returns None if nothing is played (controller moves
+    // camera), false when playback has just finished.
+    pub fn update(&mut self, camera: &mut Camera, input: &mut InputState) -> Option<bool> {
+        let player = self.player.as_mut()?;
+
+        // mouse look and zoom made during playback are dropped
+        input.take_look_delta();
+        input.take_scroll_delta();
+
+        let playing = player.update(camera);
+        if !playing {
+            self.player = None;
+        }
+        Some(playing)
+    }
+
+    // Called after camera is moved
+    pub fn record(&mut self, camera: &Camera, dt: f32) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.update(camera, dt);
+        }
+    }
+
+    // Capture button is shown if `can_capture` (swapchain images can be read back)
+    pub fn gui(&mut self, ui: &mut egui::Ui, camera: &Camera, can_capture: bool) -> Option<CameraPathEvent> {
+        let mut event = None;
+
+        ui.horizontal(|ui| {
+            match self.recorder.take() {
+                Some(recorder) => {
+                    ui.label(format!("Recording: {} keyframes", recorder.keyframe_count()));
+                    if ui.button("Stop").clicked() {
+                        self.path = Some(recorder.finish(camera));
+                    } else {
+                        self.recorder = Some(recorder);
+                    }
+                }
+                None => {
+                    if ui.button("Record").clicked() && self.player.is_none() {
+                        self.recorder = Some(CameraPathRecorder::new(RECORD_INTERVAL));
+                    }
+                }
+            }
+        });
+
+        let mut capture_path = None;
+        ui.horizontal(|ui| {
+            match &self.player {
+                Some(player) => {
+                    let status = if self.capture.is_some() { "Capturing frame" } else { "Frame" };
+                    ui.label(format!("{} {} / {}", status, player.frame(), player.frame_count()));
+                    if ui.button("Stop").clicked() {
+                        self.player = None;
+                        event = Some(CameraPathEvent::Stopped);
+                    }
+                }
+                None => {
+                    let can_play = self.recorder.is_none();
+                    if let (Some(path), true) = (&self.path, can_play) {
+                        ui.label(format!("{} keyframes, {:.1} s", path.keyframes().len(), path.duration()));
+                        if ui.button("Play").clicked() {
+                            self.player = Some(CameraPathPlayer::new(path.clone(), self.timestep));
+                        }
+                        if can_capture && ui.button("Capture").clicked() {
+                            capture_path = Some(path.clone());
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Some(path) = capture_path {
+            match FrameCapture::png_sequence(Path::new(CAPTURE_DIR), "frame") {
+                Ok(capture) => {
+                    self.start_capture(path, self.timestep, capture);
+                    event = Some(CameraPathEvent::CaptureStarted);
+                }
+                Err(err) => log::error!("Failed to start capture: {}", err),
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                if let Some(path) = &self.path {
+                    if let Err(err) = path.save(Path::new(CAMERA_PATH_FILE)) {
+                        log::error!("Failed to save camera path: {}", err);
+                    }
+                }
+            }
+            if ui.button("Load").clicked() {
+                match CameraPath::load(Path::new(CAMERA_PATH_FILE)) {
+                    Ok(path) if !path.is_empty() => self.path = Some(path),
+                    Ok(_) => log::warn!("Camera path {} is empty", CAMERA_PATH_FILE),
+                    Err(err) => log::error!("Failed to load camera path: {}", err),
+                }
+            }
+            ui.label(CAMERA_PATH_FILE);
+        });
+
+        event
+    }
+}
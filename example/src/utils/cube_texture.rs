@@ -3,6 +3,7 @@ use std::path::Path;
 use ash::version::{DeviceV1_0};
 use ash::vk;
 use image::GenericImageView;
+use ash_render_env::debug_names::DebugNames;
 use ash_render_env::utils::texture_utils::{create_texture_image, create_image_view, create_texture_sampler};
 
 
@@ -26,6 +27,7 @@ impl CubeTexture {
         submit_queue: vk::Queue,
         device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
         image_path: &Path,
+        debug_name: Option<(&DebugNames, &str)>,
     ) -> CubeTexture {
         // Face order: +X, -X, +Y, -Y, +Z, -Z
         // FROM: https://www.khronos.org/registry/vulkan/specs/1.2-extensions/man/html/VkImageSubresourceRange.html#_description
@@ -65,24 +67,32 @@ impl CubeTexture {
         }
 
         CubeTexture::from_pixels(device, command_pool, submit_queue, device_memory_properties, vk::Format::R8G8B8A8_SRGB,
-                                 &image_array_data, image_width, image_height, faces.len() as u32, true)
+                                 &image_array_data, image_width, image_height, faces.len() as u32, true, debug_name)
     }
 
+    // Image, view and sampler are named "<name>", "<name>.view" and "<name>.sampler" when `debug_name` is set
     pub fn from_pixels(device: ash::Device,
                        command_pool: vk::CommandPool,
                        submit_queue: vk::Queue,
                        device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
                        format: vk::Format,
-                       pixel_data: &Vec<u8>, width: u32, height: u32, array_size: u32, create_mips: bool) -> CubeTexture
+                       pixel_data: &Vec<u8>, width: u32, height: u32, array_size: u32, create_mips: bool,
+                       debug_name: Option<(&DebugNames, &str)>) -> CubeTexture
     {
         let (texture_image, texture_image_memory, mip_levels) = create_texture_image(
-            &device, command_pool, submit_queue, device_memory_properties, format, pixel_data, width, height, array_size, create_mips);
+            &device, command_pool, submit_queue, device_memory_properties, format, pixel_data, width, height, array_size, create_mips,
+            debug_name);
 
         let texture_image_view = create_image_view(
             &device, texture_image, format,
             vk::ImageAspectFlags::COLOR,
-            mip_levels, array_size);
-        let texture_sampler = create_texture_sampler(&device, mip_levels);
+            mip_levels, array_size, None);
+        let texture_sampler = create_texture_sampler(&device, mip_levels, None);
+
+        if let Some((names, name)) = debug_name {
+            names.set_name(texture_image_view, &format!("{}.view", name));
+            names.set_name(texture_sampler, &format!("{}.sampler", name));
+        }
 
         CubeTexture {
             device,
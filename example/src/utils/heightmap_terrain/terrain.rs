@@ -3,13 +3,20 @@ use std::sync::Arc;
 
 use ash::version::DeviceV1_0;
 use ash::vk;
-use cgmath::{InnerSpace, Vector3};
+use cgmath::{InnerSpace, Point3, Vector3};
 use memoffset::offset_of;
 
 use ash_render_env::env::RenderEnv;
+use ash_render_env::math::{Aabb, Ray};
 use ash_render_env::utils::texture::Texture;
 use ash_render_env::utils::buffer_utils::create_data_buffer;
 
+// Distance between heightmap texels in world space
+pub const TEXEL_SIZE: f32 = 0.1;
+
+// Quads per chunk side. Chunk indices are contiguous, every chunk can be drawn (and culled) separately.
+const CHUNK_SIZE: u32 = 64;
+
 pub struct HeightMap {
     pub w: u32,
     pub h: u32,
@@ -41,7 +48,8 @@ impl HeightMap {
         }
     }
 
-    pub fn get_height(&self, x: i32, y: i32) -> f32 {
+    // Height of texel, coordinates are clamped to map
+    pub fn texel_height(&self, x: i32, y: i32) -> f32 {
         let clamp = |val: i32, min: i32, max: i32| -> i32 {
             if val < min {
                 return min;
@@ -59,6 +67,61 @@ impl HeightMap {
 
         -fn_(xx as u32, yy as u32)
     }
+
+    // Bilinear interpolation between texels, matches rendered surface up to triangulation
+    pub fn get_height(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+
+        let top = self.texel_height(x0, y0) * (1.0 - fx) + self.texel_height(x0 + 1, y0) * fx;
+        let bottom = self.texel_height(x0, y0 + 1) * (1.0 - fx) + self.texel_height(x0 + 1, y0 + 1) * fx;
+
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    // Height at world position, inverse of TerrainData vertex placement (centered, Z grows to -texel Y)
+    pub fn world_height(&self, x: f32, z: f32) -> f32 {
+        self.get_height(
+            x / TEXEL_SIZE + self.w as f32 / 2.0,
+            -z / TEXEL_SIZE + self.h as f32 / 2.0,
+        )
+    }
+
+    // Ray marching with half texel steps, hit is refined by bisection. World -Y is up: points
+    // above ground have `y < world_height`.
+    pub fn intersect_ray(&self, ray: &Ray, max_distance: f32) -> Option<Point3<f32>> {
+        let above = |t: f32| {
+            let point = ray.at(t);
+            point.y < self.world_height(point.x, point.z)
+        };
+
+        if !above(0.0) {
+            return None;
+        }
+
+        let step = TEXEL_SIZE * 0.5;
+        let mut t = 0.0;
+        while t < max_distance {
+            let next = (t + step).min(max_distance);
+            if !above(next) {
+                let (mut low, mut high) = (t, next);
+                for _ in 0..16 {
+                    let mid = (low + high) * 0.5;
+                    if above(mid) {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+
+                return Some(ray.at(high));
+            }
+            t = next;
+        }
+
+        None
+    }
 }
 
 pub struct Vertex {
@@ -102,6 +165,13 @@ impl Vertex {
     }
 }
 
+pub struct TerrainChunk {
+    pub first_index: u32,
+    pub index_count: u32,
+    // world space
+    pub bounds: Aabb,
+}
+
 pub struct TerrainData {
     device: ash::Device,
     pub vertex_buffer: vk::Buffer,
@@ -109,13 +179,13 @@ pub struct TerrainData {
 
     pub index_buffer: vk::Buffer,
     pub index_buffer_memory: vk::DeviceMemory,
-    pub index_count: usize,
+    pub chunks: Vec<TerrainChunk>,
 
     pub(super) texture: Texture,
 }
 
 impl TerrainData {
-    pub fn new(env: Arc<RenderEnv>, height_map: HeightMap) -> TerrainData {
+    pub fn new(env: Arc<RenderEnv>, height_map: &HeightMap) -> TerrainData {
         let w = height_map.w;
         let h = height_map.h;
 
@@ -123,8 +193,8 @@ impl TerrainData {
         let mut indices = Vec::with_capacity((h * (w - 1) * 6) as usize);
 
         let get_pos = |x: i32, y: i32| -> Vector3<f32> {
-            let height = height_map.get_height(x, y);
-            let scale = 0.1 as f32;
+            let height = height_map.texel_height(x, y);
+            let scale = TEXEL_SIZE;
             let start_x = -(w as f32) / 2.0;
             let start_y = -(h as f32) / 2.0;
 
@@ -159,20 +229,36 @@ impl TerrainData {
             }
         }
 
-        for y in 1..(h) {
-            for x in 0..(w - 1) {
-                indices.push((y - 1) * w + x);
-                indices.push((y - 1) * w + x + 1);
-                indices.push((y) * w + x);
-
-                indices.push((y) * w + x);
-                indices.push((y - 1) * w + x + 1);
-                indices.push((y) * w + x + 1);
+        let mut chunks = Vec::new();
+        for chunk_y in (1..h).step_by(CHUNK_SIZE as usize) {
+            for chunk_x in (0..(w - 1)).step_by(CHUNK_SIZE as usize) {
+                let first_index = indices.len() as u32;
+                let mut bounds = Aabb::empty();
+
+                for y in chunk_y..(chunk_y + CHUNK_SIZE).min(h) {
+                    for x in chunk_x..(chunk_x + CHUNK_SIZE).min(w - 1) {
+                        indices.push((y - 1) * w + x);
+                        indices.push((y - 1) * w + x + 1);
+                        indices.push((y) * w + x);
+
+                        indices.push((y) * w + x);
+                        indices.push((y - 1) * w + x + 1);
+                        indices.push((y) * w + x + 1);
+
+                        for idx in [(y - 1) * w + x, (y - 1) * w + x + 1, y * w + x, y * w + x + 1].iter() {
+                            bounds.extend(Point3::from(vertices[*idx as usize].position));
+                        }
+                    }
+                }
+
+                chunks.push(TerrainChunk {
+                    first_index,
+                    index_count: indices.len() as u32 - first_index,
+                    bounds,
+                });
             }
         }
 
-        let index_count = indices.len();
-
         let (vertex_buffer, vertex_buffer_memory) = create_data_buffer(
             env.instance(),
             env.physical_device(),
@@ -180,7 +266,8 @@ impl TerrainData {
             env.command_pool(),
             env.queue(),
             vk::BufferUsageFlags::VERTEX_BUFFER,
-            vertices);
+            vertices,
+            Some((env.debug_names(), "terrain.vertices")));
 
         let (index_buffer, index_buffer_memory) = create_data_buffer(
             env.instance(),
@@ -189,7 +276,8 @@ impl TerrainData {
             env.command_pool(),
             env.queue(),
             vk::BufferUsageFlags::INDEX_BUFFER,
-            indices);
+            indices,
+            Some((env.debug_names(), "terrain.indices")));
 
         let texture = Texture::new(
             env.device().clone(),
@@ -197,6 +285,7 @@ impl TerrainData {
             env.queue(),
             &env.mem_properties,
             Path::new("./assets/terrain/ground.png"),
+            Some((env.debug_names(), "terrain.texture")),
         );
 
         TerrainData {
@@ -207,7 +296,7 @@ impl TerrainData {
             index_buffer,
             index_buffer_memory,
 
-            index_count,
+            chunks,
             texture,
         }
     }
@@ -225,3 +314,67 @@ impl Drop for TerrainData {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ground at world height `height` (-Y up) everywhere
+    fn flat(height: f32) -> HeightMap {
+        HeightMap {
+            w: 64,
+            h: 64,
+            height_fn: Box::new(move |_, _| height),
+        }
+    }
+
+    #[test]
+    fn hit_flat_ground() {
+        let map = flat(1.0);
+        let ray = Ray::new(Point3::new(0.0, -3.0, 0.0), Vector3::new(0.0, 1.0, 1.0));
+
+        let hit = map.intersect_ray(&ray, 20.0).unwrap();
+        assert!((hit.y + 1.0).abs() < 1e-3);
+        assert!((hit.z - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn vertical_ray() {
+        let map = flat(0.5);
+        let hit = map.intersect_ray(&Ray::new(Point3::new(0.3, -2.0, 0.7), Vector3::new(0.0, 1.0, 0.0)), 5.0).unwrap();
+        assert!((hit.y + 0.5).abs() < 1e-3);
+        assert!((hit.x - 0.3).abs() < 1e-5 && (hit.z - 0.7).abs() < 1e-5);
+    }
+
+    #[test]
+    fn slope_hit_is_closest() {
+        // ground rises along texel x: a ray going along +X hits the slope, not a later texel
+        let map = HeightMap {
+            w: 64,
+            h: 64,
+            height_fn: Box::new(|x, _| x as f32 * 0.1),
+        };
+        let ray = Ray::new(Point3::new(-3.0, -2.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+
+        let hit = map.intersect_ray(&ray, 10.0).unwrap();
+        assert!((hit.y - map.world_height(hit.x, hit.z)).abs() < 1e-3);
+        // first point where the ground reaches 2 units above zero
+        assert!((hit.x - (20.0 - 32.0) * TEXEL_SIZE).abs() < 1e-2);
+    }
+
+    #[test]
+    fn misses() {
+        let map = flat(1.0);
+
+        // pointing up, parallel to ground, too short
+        assert_eq!(map.intersect_ray(&Ray::new(Point3::new(0.0, -3.0, 0.0), Vector3::new(0.0, -1.0, 0.0)), 20.0), None);
+        assert_eq!(map.intersect_ray(&Ray::new(Point3::new(0.0, -3.0, 0.0), Vector3::new(1.0, 0.0, 0.0)), 20.0), None);
+        assert_eq!(map.intersect_ray(&Ray::new(Point3::new(0.0, -3.0, 0.0), Vector3::new(0.0, 1.0, 0.0)), 1.0), None);
+    }
+
+    #[test]
+    fn origin_below_ground() {
+        let map = flat(1.0);
+        assert_eq!(map.intersect_ray(&Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)), 20.0), None);
+    }
+}
@@ -7,22 +7,33 @@ use cgmath::{Matrix4, SquareMatrix};
 
 use ash_render_env::descriptor_set::DescriptorSet;
 use ash_render_env::env::RenderEnv;
+use ash_render_env::math::Frustum;
 use ash_render_env::pipeline_builder::{Pipeline, PipelineBuilder};
 use ash_render_env::shader;
+use crate::shadow_map::caster::{matrix_push_constants, ShadowCaster, ShadowFrustum};
+use crate::shadow_map::uniform_buffer::UniformBuffer;
+use crate::utils::material::{Material, MaterialUniforms};
 use crate::utils::uniform_buffer::UboBuffers;
 
 use super::terrain::{TerrainData, Vertex};
 
 pub struct TerrainRenderer {
+    // recorded every frame with chunks inside camera frustum
     cmd_bufs: Vec<vk::CommandBuffer>,
+    dimensions: [u32; 2],
+    visible_chunk_count: usize,
 
     vertex_buffer: TerrainData,
 
     render_pass: vk::RenderPass,
     pipeline: Pipeline,
+    // depth only, created by `create_shadow_pipeline`
+    shadow_pipeline: Option<Pipeline>,
 
     descriptor_sets: Vec<DescriptorSet>,
     uniforms: UboBuffers,
+    material: Material,
+    material_buffer: UniformBuffer<MaterialUniforms>,
     env: Arc<RenderEnv>,
 
     current_frame: usize,
@@ -31,7 +42,7 @@ pub struct TerrainRenderer {
 
 impl TerrainRenderer {
     pub fn new(env: Arc<RenderEnv>, render_pass: vk::RenderPass, color_attachment_count: usize,
-               terrain: TerrainData, msaa_samples: vk::SampleCountFlags, max_inflight_frames: usize,
+               terrain: TerrainData, msaa_samples: vk::SampleCountFlags, reverse_z: bool, max_inflight_frames: usize,
                dimensions: [u32; 2]) -> TerrainRenderer
     {
         let pipeline = {
@@ -45,7 +56,9 @@ impl TerrainRenderer {
                 .msaa(msaa_samples)
                 .color_attachment_count(color_attachment_count)
                 .with_depth_func(vk::CompareOp::LESS_OR_EQUAL)
+                .reverse_z(reverse_z)
                 .cull_mode(vk::CullModeFlags::FRONT)
+                .debug_name(&env, "terrain")
                 .build()
         };
 
@@ -56,6 +69,13 @@ impl TerrainRenderer {
             max_inflight_frames,
         );
 
+        let material = Material {
+            roughness: 0.95,
+            ..Material::default()
+        };
+        let material_buffer = UniformBuffer::new(env.clone()).debug_name(&env, "terrain.material");
+        material_buffer.write_data(material.uniforms());
+
         let mut cmd_bufs = vec![];
         let mut descriptor_sets = vec![];
         for i in 0..max_inflight_frames {
@@ -63,19 +83,23 @@ impl TerrainRenderer {
                 DescriptorSet::builder(env.device(), pipeline.descriptor_set_layouts.get(0).unwrap())
                     .add_buffer(uniforms.uniform_buffers[i])
                     .add_image(terrain.texture.texture_image_view, terrain.texture.texture_sampler)
+                    .add_buffer(material_buffer.buffer)
                     .build()
             );
-            cmd_bufs.push(
-                Self::build_cmd_buf(&env, render_pass, &pipeline, &descriptor_sets[i], &terrain, dimensions)
-            );
+            cmd_bufs.push(env.create_secondary_command_buffer());
         }
 
         TerrainRenderer {
             env: env.clone(),
             pipeline,
+            shadow_pipeline: None,
             cmd_bufs,
+            dimensions,
+            visible_chunk_count: 0,
             render_pass,
             uniforms,
+            material,
+            material_buffer,
             descriptor_sets,
             vertex_buffer: terrain,
             current_frame: 0,
@@ -83,14 +107,18 @@ impl TerrainRenderer {
         }
     }
 
-    fn build_cmd_buf(env: &RenderEnv, render_pass: vk::RenderPass, pipeline: &Pipeline, descriptor_set: &DescriptorSet, vertex_buffer: &TerrainData, dimensions: [u32; 2]) -> vk::CommandBuffer {
-        let command_buffer = env.create_secondary_command_buffer();
-        let device = env.device();
+    // Records draws of chunks inside `frustum`, returns number of drawn chunks
+    fn record_cmd_buf(&self, frame: usize, frustum: &Frustum) -> usize {
+        let command_buffer = self.cmd_bufs[frame];
+        let device = self.env.device();
+        let dimensions = self.dimensions;
+        let pipeline = &self.pipeline;
+        let vertex_buffer = &self.vertex_buffer;
 
         let inheritance_info = vk::CommandBufferInheritanceInfo {
             s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
             p_next: ptr::null(),
-            render_pass,
+            render_pass: self.render_pass,
             subpass: 0,
             framebuffer: vk::Framebuffer::null(),
             occlusion_query_enable: 0,
@@ -102,10 +130,13 @@ impl TerrainRenderer {
             s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
             p_next: ptr::null(),
             p_inheritance_info: &inheritance_info,
-            flags: vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE | vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
+            flags: vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE | vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
         };
 
         unsafe {
+            device
+                .reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::empty())
+                .expect("Failed to reset Command Buffer!");
             device
                 .begin_command_buffer(command_buffer, &command_buffer_begin_info)
                 .expect("Failed to begin recording Command Buffer at beginning!");
@@ -129,6 +160,7 @@ impl TerrainRenderer {
             },
         }];
 
+        let mut draw_count = 0;
         unsafe {
             device.cmd_set_viewport(command_buffer, 0, viewports.as_ref());
             device.cmd_set_scissor(command_buffer, 0, scissors.as_ref());
@@ -139,7 +171,7 @@ impl TerrainRenderer {
                 pipeline.graphics_pipeline,
             );
 
-            let descriptor_sets_to_bind = [descriptor_set.set];
+            let descriptor_sets_to_bind = [self.descriptor_sets[frame].set];
             device.cmd_bind_descriptor_sets(
                 command_buffer,
                 vk::PipelineBindPoint::GRAPHICS,
@@ -154,36 +186,45 @@ impl TerrainRenderer {
             device.cmd_bind_vertex_buffers(command_buffer, 0, &vertex_buffers, &offsets);
             device.cmd_bind_index_buffer(command_buffer, vertex_buffer.index_buffer, 0, vk::IndexType::UINT32);
 
-            device.cmd_draw_indexed(command_buffer, vertex_buffer.index_count as u32, 1, 0, 0, 0);
+            for chunk in vertex_buffer.chunks.iter().filter(|chunk| frustum.intersects_aabb(&chunk.bounds)) {
+                device.cmd_draw_indexed(command_buffer, chunk.index_count, 1, chunk.first_index, 0, 0);
+                draw_count += 1;
+            }
 
             device
                 .end_command_buffer(command_buffer)
                 .expect("Failed to record Command Buffer at Ending!");
         }
 
-        command_buffer
+        draw_count
     }
 
     pub fn resize_framebuffer(&mut self, dimensions: [u32; 2]) {
-        unsafe {
-            self.env.device().free_command_buffers(self.env.command_pool(), &self.cmd_bufs);
-        }
+        self.dimensions = dimensions;
+    }
 
-        let mut cmd_bufs = vec![];
+    // Chunks drawn in the last frame
+    pub fn visible_chunk_count(&self) -> usize {
+        self.visible_chunk_count
+    }
 
-        for i in 0..self.max_inflight_frames {
-            cmd_bufs.push(
-                Self::build_cmd_buf(&self.env, self.render_pass, &self.pipeline,
-                                    &self.descriptor_sets[i], &self.vertex_buffer, dimensions)
-            );
-        }
+    pub fn chunk_count(&self) -> usize {
+        self.vertex_buffer.chunks.len()
+    }
+
+    pub fn material(&self) -> Material {
+        self.material
+    }
 
-        self.cmd_bufs = cmd_bufs;
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+        self.material_buffer.write_data(material.uniforms());
     }
 
-    pub fn draw(&mut self, view: Matrix4<f32>, proj: Matrix4<f32>) -> vk::CommandBuffer
+    pub fn draw(&mut self, view: Matrix4<f32>, proj: Matrix4<f32>, frustum: &Frustum) -> vk::CommandBuffer
     {
         self.uniforms.update_uniform_buffer(self.current_frame, Matrix4::identity(), view, proj);
+        self.visible_chunk_count = self.record_cmd_buf(self.current_frame, frustum);
 
         let current_frame = self.current_frame;
         self.current_frame = (self.current_frame + 1) % self.max_inflight_frames;
@@ -192,6 +233,46 @@ impl TerrainRenderer {
     }
 }
 
+impl ShadowCaster for TerrainRenderer {
+    fn create_shadow_pipeline(&mut self, render_pass: vk::RenderPass) {
+        let device = self.env.device();
+
+        self.shadow_pipeline = Some(PipelineBuilder::new(device.clone(), render_pass, 0)
+            .vertex_shader(shader::Shader::load(device, "assets/shaders/spv/heightmap_terrain/terrain_shadow.vert.spv"))
+            .fragment_shader(shader::Shader::load(device, "assets/shaders/spv/mesh/shadow_map.frag.spv"))
+            .vertex_input(Vertex::binding_descriptions(), Vertex::attribute_descriptions())
+            .with_depth_func(vk::CompareOp::LESS_OR_EQUAL)
+            .depth_clamp_enable(true)
+            // heightfield has no back side: both faces are rendered, so hills shadow any light direction
+            .disable_culling()
+            .color_attachment_count(0)
+            .debug_name(&self.env, "terrain_shadow")
+            .build());
+    }
+
+    // One draw per chunk inside cascade
+    fn record_shadow_draws(&self, device: &ash::Device, cmd_buf: vk::CommandBuffer, frustum: &ShadowFrustum) -> u32 {
+        let pipeline = self.shadow_pipeline.as_ref().expect("Shadow pipeline is not created!");
+        let terrain = &self.vertex_buffer;
+
+        let mut draw_count = 0;
+        unsafe {
+            device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::GRAPHICS, pipeline.graphics_pipeline);
+            device.cmd_push_constants(cmd_buf, pipeline.pipeline_layout, vk::ShaderStageFlags::VERTEX, 0,
+                                      &matrix_push_constants(frustum.view_proj));
+            device.cmd_bind_vertex_buffers(cmd_buf, 0, &[terrain.vertex_buffer], &[0]);
+            device.cmd_bind_index_buffer(cmd_buf, terrain.index_buffer, 0, vk::IndexType::UINT32);
+
+            for chunk in terrain.chunks.iter().filter(|chunk| frustum.intersects(&chunk.bounds)) {
+                device.cmd_draw_indexed(cmd_buf, chunk.index_count, 1, chunk.first_index, 0, 0);
+                draw_count += 1;
+            }
+        }
+
+        draw_count
+    }
+}
+
 impl Drop for TerrainRenderer {
     fn drop(&mut self) {
         unsafe {
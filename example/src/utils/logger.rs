@@ -0,0 +1,26 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// Prints log records to stdout (validation messages come through `log`)
+struct StdoutLogger;
+
+static LOGGER: StdoutLogger = StdoutLogger;
+
+impl Log for StdoutLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            println!("[{}][{}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init() {
+    log::set_logger(&LOGGER)
+        .map(|_| log::set_max_level(LevelFilter::Info))
+        .expect("Failed to set logger!");
+}
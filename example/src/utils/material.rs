@@ -0,0 +1,81 @@
+// Metallic-roughness material parameters (glTF convention)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Material {
+    // multiplies albedo texture
+    pub base_color: [f32; 4],
+    pub roughness: f32,
+    pub metallic: f32,
+    // baked ambient occlusion, multiplies SSAO
+    pub occlusion: f32,
+    // linear HDR color, added after lighting
+    pub emissive: [f32; 3],
+    pub emissive_intensity: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            roughness: 0.8,
+            metallic: 0.0,
+            occlusion: 1.0,
+            emissive: [0.0, 0.0, 0.0],
+            emissive_intensity: 1.0,
+        }
+    }
+}
+
+// Layout of `Material` uniform block in mesh.frag and terrain.frag
+#[repr(C)]
+pub struct MaterialUniforms {
+    base_color: [f32; 4],
+    emissive: [f32; 4],
+    roughness: f32,
+    metallic: f32,
+    occlusion: f32,
+    _padding: f32,
+}
+
+impl Material {
+    pub fn uniforms(&self) -> MaterialUniforms {
+        MaterialUniforms {
+            base_color: self.base_color,
+            emissive: [
+                self.emissive[0] * self.emissive_intensity,
+                self.emissive[1] * self.emissive_intensity,
+                self.emissive[2] * self.emissive_intensity,
+                0.0,
+            ],
+            roughness: self.roughness,
+            metallic: self.metallic,
+            occlusion: self.occlusion,
+            _padding: 0.0,
+        }
+    }
+}
+
+// Returns true if material was changed
+pub fn material_editor(ui: &mut egui::Ui, label: &str, material: &mut Material) -> bool {
+    let mut changed = false;
+
+    ui.collapsing(label, |ui| {
+        let mut base_color = [material.base_color[0], material.base_color[1], material.base_color[2]];
+        ui.horizontal(|ui| {
+            changed |= ui.color_edit_button_rgb(&mut base_color).changed();
+            ui.label("Base color");
+        });
+        material.base_color[..3].copy_from_slice(&base_color);
+
+        changed |= ui.add(egui::Slider::new(&mut material.roughness, 0.0..=1.0).text("Roughness")).changed();
+        changed |= ui.add(egui::Slider::new(&mut material.metallic, 0.0..=1.0).text("Metallic")).changed();
+        changed |= ui.add(egui::Slider::new(&mut material.occlusion, 0.0..=1.0).text("Occlusion")).changed();
+
+        ui.horizontal(|ui| {
+            changed |= ui.color_edit_button_rgb(&mut material.emissive).changed();
+            ui.label("Emissive");
+        });
+        changed |= ui.add(egui::Slider::new(&mut material.emissive_intensity, 0.0..=50.0).text("Emissive intensity")).changed();
+    });
+
+    changed
+}
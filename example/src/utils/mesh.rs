@@ -8,8 +8,10 @@ use memoffset::offset_of;
 use tobj;
 
 use ash_render_env::env::RenderEnv;
+use ash_render_env::math::{Aabb, Ray};
 use ash_render_env::utils::buffer_utils::create_data_buffer;
 use ash_render_env::utils::texture::Texture;
+use cgmath::{Matrix4, Point3, SquareMatrix};
 
 #[repr(C)]
 #[derive(Debug, Clone)]
@@ -114,6 +116,11 @@ pub struct Mesh {
     pub index_buffer: vk::Buffer,
     pub index_buffer_memory: vk::DeviceMemory,
     pub index_count: usize,
+    // model space
+    pub bounds: Aabb,
+    // model space copy of geometry for picking
+    positions: Vec<Point3<f32>>,
+    indices: Vec<u32>,
 
     pub(super) texture: Texture,
 }
@@ -127,6 +134,12 @@ impl Mesh {
 
         let index_count = indices.len();
 
+        let positions: Vec<Point3<f32>> = vertices.iter()
+            .map(|vertex| Point3::new(vertex.pos[0], vertex.pos[1], vertex.pos[2]))
+            .collect();
+        let bounds = Aabb::from_points(positions.iter().cloned());
+        let cpu_indices = indices.clone();
+
         let (vertex_buffer, vertex_buffer_memory) = create_data_buffer(
             env.instance(),
             env.physical_device(),
@@ -134,7 +147,8 @@ impl Mesh {
             env.command_pool(),
             env.queue(),
             vk::BufferUsageFlags::VERTEX_BUFFER,
-            vertices);
+            vertices,
+            Some((env.debug_names(), "mesh.vertices")));
 
         let (index_buffer, index_buffer_memory) = create_data_buffer(
             env.instance(),
@@ -143,7 +157,8 @@ impl Mesh {
             env.command_pool(),
             env.queue(),
             vk::BufferUsageFlags::INDEX_BUFFER,
-            indices);
+            indices,
+            Some((env.debug_names(), "mesh.indices")));
 
         println!("Model uploaded: {}", t1.elapsed().as_secs_f32());
 
@@ -153,6 +168,7 @@ impl Mesh {
             env.queue(),
             &env.mem_properties,
             Path::new("assets/chalet.jpg"),
+            Some((env.debug_names(), "mesh.texture")),
         );
         Mesh {
             device: env.device().clone(),
@@ -164,10 +180,22 @@ impl Mesh {
             index_buffer_memory,
 
             index_count,
+            bounds,
+            positions,
+            indices: cpu_indices,
 
             texture,
         }
     }
+
+    // Distance to the closest triangle hit by world space `ray`
+    pub fn intersect_ray(&self, ray: &Ray, world: &Matrix4<f32>) -> Option<f32> {
+        let inv_world = world.invert().expect("Mesh world matrix is not invertible!");
+        let model_ray = ray.transform(&inv_world);
+
+        model_ray.intersect_aabb(&self.bounds)?;
+        model_ray.intersect_triangles(&self.positions, &self.indices)
+    }
 }
 
 impl Drop for Mesh {
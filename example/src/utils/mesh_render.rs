@@ -7,19 +7,33 @@ use cgmath::{Matrix4, Deg, Rad, Vector3};
 
 use ash_render_env::descriptor_set::DescriptorSet;
 use ash_render_env::env::RenderEnv;
+use ash_render_env::math::Frustum;
 use ash_render_env::pipeline_builder::{Pipeline, PipelineBuilder};
 use ash_render_env::shader;
+use crate::shadow_map::caster::{matrix_push_constants, ShadowCaster, ShadowFrustum};
+use crate::shadow_map::uniform_buffer::UniformBuffer;
+use crate::utils::material::{Material, MaterialUniforms};
 use crate::utils::uniform_buffer::UboBuffers;
 use crate::utils::mesh;
 use crate::utils::mesh::Mesh;
 
+// Placement of the demo mesh in the scene, shared by all mesh passes
+pub fn mesh_world_matrix() -> Matrix4<f32> {
+    let w1 = Matrix4::<f32>::from_angle_x(Rad::from(Deg(90.0)));
+    Matrix4::<f32>::from_translation(Vector3::new(0.0, 0.01, -10.0)) * w1
+}
+
 pub struct MeshRenderer {
     render_cmds: Vec<vk::CommandBuffer>,
 
     render_pass: vk::RenderPass,
     pipeline: Pipeline,
+    // depth only, created by `create_shadow_pipeline`
+    shadow_pipeline: Option<Pipeline>,
     descriptor_sets: Vec<DescriptorSet>,
     uniforms: UboBuffers,
+    material: Material,
+    material_buffer: UniformBuffer<MaterialUniforms>,
 
     mesh: Arc<Mesh>,
 
@@ -31,7 +45,7 @@ pub struct MeshRenderer {
 
 impl MeshRenderer {
     pub fn new(env: Arc<RenderEnv>, render_pass: vk::RenderPass, mesh: Arc<Mesh>, color_attachment_count: usize,
-               msaa_samples: vk::SampleCountFlags, max_inflight_frames: usize,
+               msaa_samples: vk::SampleCountFlags, reverse_z: bool, max_inflight_frames: usize,
                dimensions: [u32; 2]) -> MeshRenderer
     {
         let pipeline = {
@@ -44,7 +58,9 @@ impl MeshRenderer {
                 .vertex_input(mesh::Vertex::binding_descriptions(), mesh::Vertex::attribute_descriptions())
                 .msaa(msaa_samples)
                 .with_depth_test()
+                .reverse_z(reverse_z)
                 .color_attachment_count(color_attachment_count)
+                .debug_name(&env, "mesh")
                 .build()
         };
 
@@ -55,6 +71,10 @@ impl MeshRenderer {
             max_inflight_frames,
         );
 
+        let material = Material::default();
+        let material_buffer = UniformBuffer::new(env.clone()).debug_name(&env, "mesh.material");
+        material_buffer.write_data(material.uniforms());
+
         let mut cmd_bufs = vec![];
         let mut descriptor_sets = vec![];
         for i in 0..max_inflight_frames {
@@ -62,6 +82,7 @@ impl MeshRenderer {
                 DescriptorSet::builder(env.device(), pipeline.descriptor_set_layouts.get(0).unwrap())
                     .add_buffer(uniforms.uniform_buffers[i])
                     .add_image(mesh.texture.texture_image_view, mesh.texture.texture_sampler)
+                    .add_buffer(material_buffer.buffer)
                     .build()
             );
             cmd_bufs.push(
@@ -72,9 +93,12 @@ impl MeshRenderer {
         MeshRenderer {
             env: env.clone(),
             pipeline: pipeline,
+            shadow_pipeline: None,
             render_cmds: cmd_bufs,
             render_pass: render_pass,
             uniforms,
+            material,
+            material_buffer,
             descriptor_sets: descriptor_sets,
             mesh,
             current_frame: 0,
@@ -180,16 +204,67 @@ impl MeshRenderer {
         self.render_cmds = cmd_bufs;
     }
 
-    pub fn draw(&mut self, view: Matrix4<f32>, proj: Matrix4<f32>) -> vk::CommandBuffer {
-        let w1 = Matrix4::<f32>::from_angle_x(Rad::from(Deg(90.0)));
-        let world = Matrix4::<f32>::from_translation(Vector3::new(0.0, 0.01, -10.0 )) * w1;
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+        self.material_buffer.write_data(material.uniforms());
+    }
+
+    // None when mesh is outside of `frustum`
+    pub fn draw(&mut self, view: Matrix4<f32>, proj: Matrix4<f32>, frustum: &Frustum) -> Option<vk::CommandBuffer> {
+        let world = mesh_world_matrix();
 
         self.uniforms.update_uniform_buffer(self.current_frame, world, view, proj);
 
         let current_frame = self.current_frame;
         self.current_frame = (self.current_frame + 1) % self.max_inflight_frames;
 
-        self.render_cmds[current_frame]
+        if !frustum.intersects_aabb(&self.mesh.bounds.transform(&world)) {
+            return None;
+        }
+
+        Some(self.render_cmds[current_frame])
+    }
+}
+
+impl ShadowCaster for MeshRenderer {
+    fn create_shadow_pipeline(&mut self, render_pass: vk::RenderPass) {
+        let device = self.env.device();
+
+        self.shadow_pipeline = Some(PipelineBuilder::new(device.clone(), render_pass, 0)
+            .vertex_shader(shader::Shader::load(device, "assets/shaders/spv/mesh/shadow_depth.vert.spv"))
+            .fragment_shader(shader::Shader::load(device, "assets/shaders/spv/mesh/shadow_map.frag.spv"))
+            .vertex_input(mesh::Vertex::binding_descriptions(), mesh::Vertex::attribute_descriptions())
+            .with_depth_func(vk::CompareOp::LESS_OR_EQUAL)
+            .depth_clamp_enable(true)
+            // cascade matrices flip Y (Vulkan clip space), winding is flipped too
+            .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+            .color_attachment_count(0)
+            .debug_name(&self.env, "mesh_shadow")
+            .build());
+    }
+
+    fn record_shadow_draws(&self, device: &ash::Device, cmd_buf: vk::CommandBuffer, frustum: &ShadowFrustum) -> u32 {
+        let pipeline = self.shadow_pipeline.as_ref().expect("Shadow pipeline is not created!");
+        let world = mesh_world_matrix();
+
+        if !frustum.intersects(&self.mesh.bounds.transform(&world)) {
+            return 0;
+        }
+
+        unsafe {
+            device.cmd_bind_pipeline(cmd_buf, vk::PipelineBindPoint::GRAPHICS, pipeline.graphics_pipeline);
+            device.cmd_push_constants(cmd_buf, pipeline.pipeline_layout, vk::ShaderStageFlags::VERTEX, 0,
+                                      &matrix_push_constants(frustum.view_proj * world));
+            device.cmd_bind_vertex_buffers(cmd_buf, 0, &[self.mesh.vertex_buffer], &[0]);
+            device.cmd_bind_index_buffer(cmd_buf, self.mesh.index_buffer, 0, vk::IndexType::UINT32);
+            device.cmd_draw_indexed(cmd_buf, self.mesh.index_count as u32, 1, 0, 0, 0);
+        }
+
+        1
     }
 }
 
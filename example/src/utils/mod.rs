@@ -8,4 +8,8 @@ pub mod skybox;
 pub mod skybox_render;
 pub mod cube_texture;
 pub mod heightmap_terrain;
-pub mod mesh_shadowmap_render;
+pub mod storage_buffer;
+pub mod material;
+pub mod logger;
+pub mod screenshot;
+pub mod camera_path_control;
@@ -6,44 +6,74 @@ use ash::vk;
 use cgmath::{Matrix4, SquareMatrix};
 
 use ash_render_env::{descriptor_set, pipeline_builder, shader};
-use ash_render_env::descriptor_set::{DescriptorSet, DescriptorSetBuilder};
+use ash_render_env::descriptor_set::DescriptorSetBuilder;
 use ash_render_env::env::RenderEnv;
 use ash_render_env::frame_buffer::Framebuffer;
-use ash_render_env::pipeline_builder::{Pipeline, PipelineBuilder};
+use ash_render_env::pipeline_builder::PipelineBuilder;
 
+use crate::lighting::directional_light::DirectionalLight;
+use crate::post_process::record_fullscreen_pass;
 use crate::shadow_map::uniform_buffer::UniformBuffer;
-use crate::shadow_map::{CASCADE_COUNT, CascadeInfo};
+use crate::shadow_map::{CascadeInfo, MAX_CASCADE_COUNT, ShadowSettings};
+use crate::shadow_map::atlas::ShadowAtlas;
+use crate::shadow_map::filtering::ShadowFilterParams;
 
 #[repr(C)]
 struct Uniforms {
-    cascade_splits: [f32; CASCADE_COUNT],
+    cascade_splits: [f32; MAX_CASCADE_COUNT],
     view: Matrix4<f32>,
-    cascade_vp: [Matrix4<f32>; CASCADE_COUNT],
+    cascade_vp: [Matrix4<f32>; MAX_CASCADE_COUNT],
+    sun_direction: [f32; 4],
+    sun_radiance: [f32; 4],
+    ssao_debug: u32,
+    light_heatmap: u32,
+    debug_cascades: u32,
+    cascade_blend: f32,
+    shadow_bias: f32,
+    shadow_filter_radius: f32,
+    shadow_light_size: f32,
+    evsm_bleed_reduction: f32,
 }
 
 
 pub struct QuadRenderer {
     sampler: vk::Sampler,
     shadow_sampler: vk::Sampler,
+    shadow_compare_sampler: vk::Sampler,
     descriptor_set: descriptor_set::DescriptorSet,
     pipeline: pipeline_builder::Pipeline,
     pub render_pass: vk::RenderPass,
     pub second_buffer: vk::CommandBuffer,
     uniform_buffer: UniformBuffer<Uniforms>,
+    ssao_debug: bool,
+    light_heatmap: bool,
+    debug_cascades: bool,
+    cascade_blend: f32,
+    shadow_filter_params: ShadowFilterParams,
+    light_buffer: vk::Buffer,
+    shadow_atlas_view: vk::ImageView,
+    shadow_view_buffer: vk::Buffer,
     env: Arc<RenderEnv>,
 }
 
 impl QuadRenderer {
-    pub fn new(env: Arc<RenderEnv>, framebuffer: &Framebuffer, shadow_map_view: vk::ImageView, render_pass: vk::RenderPass, input_samples: vk::SampleCountFlags, dimensions: [u32; 2]) -> QuadRenderer {
+    // `shadow_moments_view`: EVSM moments, required for ShadowFilter::Evsm only
+    pub fn new(env: Arc<RenderEnv>, framebuffer: &Framebuffer, shadow_map_view: vk::ImageView, shadow_moments_view: Option<vk::ImageView>,
+               ssao_view: vk::ImageView, light_buffer: vk::Buffer, tile_light_buffer: vk::Buffer, shadow_atlas: &ShadowAtlas,
+               render_pass: vk::RenderPass, input_samples: vk::SampleCountFlags, shadow_settings: &ShadowSettings, dimensions: [u32; 2]) -> QuadRenderer {
         let pipeline = {
             let vert_shader_module = shader::Shader::load(env.device(), "assets/shaders/spv/compose.vert.spv");
             let frag_shader_module = shader::Shader::load(env.device(), "assets/shaders/spv/compose.frag.spv")
-                .specialize(shader::ConstantsBuilder::new().add_u32(input_samples.as_raw()));
+                .specialize(shader::ConstantsBuilder::new()
+                    .add_u32(input_samples.as_raw())
+                    .add_u32(shadow_settings.cascade_count as u32)
+                    .add_u32(shadow_settings.filter as u32));
 
 
             PipelineBuilder::new(env.device().clone(), render_pass, 0)
                 .fragment_shader(frag_shader_module)
                 .vertex_shader(vert_shader_module)
+                .debug_name(&env, "compose")
                 .build()
         };
 
@@ -58,6 +88,7 @@ impl QuadRenderer {
         let sampler = unsafe {
             env.device().create_sampler(&sampler_create_info, None).unwrap()
         };
+        env.set_name(sampler, "compose.sampler");
 
         let sampler_create_info = vk::SamplerCreateInfo {
             s_type: vk::StructureType::SAMPLER_CREATE_INFO,
@@ -83,9 +114,23 @@ impl QuadRenderer {
         let shadow_sampler = unsafe {
             env.device().create_sampler(&sampler_create_info, None).unwrap()
         };
+        env.set_name(shadow_sampler, "compose.shadow_sampler");
+
+        // depth test in sampler, LINEAR gives bilinear filtered result of 4 tests
+        let sampler_create_info = vk::SamplerCreateInfo {
+            compare_enable: vk::TRUE,
+            compare_op: vk::CompareOp::LESS_OR_EQUAL,
+            ..sampler_create_info
+        };
+
+        let shadow_compare_sampler = unsafe {
+            env.device().create_sampler(&sampler_create_info, None).unwrap()
+        };
+        env.set_name(shadow_compare_sampler, "compose.shadow_compare_sampler");
 
 
-        let uniform_buffer = UniformBuffer::new(env.clone());
+        let uniform_buffer = UniformBuffer::new(env.clone()).debug_name(&env, "compose.uniforms");
+        let (moments_view, moments_layout) = moments_binding(shadow_map_view, shadow_moments_view);
 
         let descriptor_set = DescriptorSetBuilder::new(
             env.device(), pipeline.descriptor_set_layouts.get(0).unwrap())
@@ -94,121 +139,96 @@ impl QuadRenderer {
             .add_image(framebuffer.attachments.get(2).unwrap().view, sampler)
             .add_image_with_layout(shadow_map_view, shadow_sampler.clone(), vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
             .add_buffer(uniform_buffer.buffer)
+            .add_image(ssao_view, sampler)
+            .add_image(framebuffer.attachments.get(3).unwrap().view, sampler)
+            .add_image(framebuffer.attachments.get(4).unwrap().view, sampler)
+            .add_buffer(light_buffer)
+            .add_buffer(tile_light_buffer)
+            .add_image_with_layout(shadow_map_view, shadow_compare_sampler, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+            .add_image_with_layout(moments_view, sampler, moments_layout)
+            .add_image_with_layout(shadow_atlas.view(), shadow_compare_sampler, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+            .add_buffer(shadow_atlas.view_buffer())
             .build();
 
-        let second_buffer = Self::render_quad(&env, dimensions, &pipeline, &descriptor_set, render_pass);
+        let second_buffer = record_fullscreen_pass(&env, dimensions, &pipeline, &descriptor_set, render_pass);
 
         QuadRenderer {
             pipeline,
             render_pass,
             shadow_sampler,
+            shadow_compare_sampler,
 
             sampler,
             descriptor_set,
             second_buffer,
 
             uniform_buffer,
+            ssao_debug: false,
+            light_heatmap: false,
+            debug_cascades: false,
+            cascade_blend: 0.0,
+            shadow_filter_params: ShadowFilterParams::default(),
+            light_buffer,
+            shadow_atlas_view: shadow_atlas.view(),
+            shadow_view_buffer: shadow_atlas.view_buffer(),
             env: env.clone(),
         }
     }
 
-    pub fn write_shadowmap_ubo(&mut self, view: Matrix4<f32>, cascades: &Vec<CascadeInfo>) {
-        let mut cascade_splits = [0.0; CASCADE_COUNT];
-        let mut cascade_vp = [Matrix4::<f32>::identity(); CASCADE_COUNT];
+    // output ambient occlusion only
+    pub fn set_ssao_debug(&mut self, ssao_debug: bool) {
+        self.ssao_debug = ssao_debug;
+    }
+
+    // output number of lights per tile
+    pub fn set_light_heatmap(&mut self, light_heatmap: bool) {
+        self.light_heatmap = light_heatmap;
+    }
+
+    // tint sun light by shadow cascade
+    pub fn set_debug_cascades(&mut self, debug_cascades: bool) {
+        self.debug_cascades = debug_cascades;
+    }
+
+    // fraction of cascade depth range blended with next cascade, 0 disables blending
+    pub fn set_cascade_blend(&mut self, cascade_blend: f32) {
+        self.cascade_blend = cascade_blend;
+    }
+
+    pub fn set_shadow_filter_params(&mut self, params: ShadowFilterParams) {
+        self.shadow_filter_params = params;
+    }
+
+    pub fn write_uniforms(&mut self, view: Matrix4<f32>, cascades: &[CascadeInfo], sun: &DirectionalLight) {
+        let mut cascade_splits = [0.0; MAX_CASCADE_COUNT];
+        let mut cascade_vp = [Matrix4::<f32>::identity(); MAX_CASCADE_COUNT];
 
         for (idx, cascade) in cascades.iter().enumerate() {
             cascade_splits[idx] = cascade.max_z;
             cascade_vp[idx] = cascade.view_proj_mat;
         }
 
+        let sun_radiance = sun.radiance();
         self.uniform_buffer.write_data(Uniforms {
             view,
             cascade_vp,
-            cascade_splits
+            cascade_splits,
+            sun_direction: [sun.direction.x, sun.direction.y, sun.direction.z, 0.0],
+            sun_radiance: [sun_radiance[0], sun_radiance[1], sun_radiance[2], 0.0],
+            ssao_debug: self.ssao_debug as u32,
+            light_heatmap: self.light_heatmap as u32,
+            debug_cascades: self.debug_cascades as u32,
+            cascade_blend: self.cascade_blend,
+            shadow_bias: self.shadow_filter_params.bias,
+            shadow_filter_radius: self.shadow_filter_params.filter_radius,
+            shadow_light_size: self.shadow_filter_params.light_size,
+            evsm_bleed_reduction: self.shadow_filter_params.evsm_bleed_reduction,
         })
     }
-    fn render_quad(env: &RenderEnv, dimensions: [u32; 2], pipeline: &Pipeline, descriptor_set: &DescriptorSet, render_pass: vk::RenderPass) -> vk::CommandBuffer {
-        let device = env.device();
-        let create_info = vk::CommandBufferAllocateInfo {
-            s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
-            p_next: ptr::null(),
-            command_pool: env.command_pool(),
-            level: vk::CommandBufferLevel::SECONDARY,
-            command_buffer_count: 1,
-        };
-
-        let cmd_buf = unsafe {
-            device.allocate_command_buffers(&create_info).unwrap().pop().unwrap()
-        };
-
-        let viewports = [vk::Viewport {
-            x: 0.0,
-            y: 0.0,
-            width: dimensions[0] as f32,
-            height: dimensions[1] as f32,
-            min_depth: 0.0,
-            max_depth: 1.0,
-        }];
-
-        let scissors = [vk::Rect2D {
-            offset: vk::Offset2D { x: 0, y: 0 },
-            extent: vk::Extent2D {
-                width: dimensions[0],
-                height: dimensions[1],
-            },
-        }];
-
-        unsafe {
-            let inheritance_info = vk::CommandBufferInheritanceInfo {
-                s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
-                p_next: ptr::null(),
-                render_pass,
-                subpass: 0,
-                framebuffer: vk::Framebuffer::null(),
-                occlusion_query_enable: 0,
-                query_flags: Default::default(),
-                pipeline_statistics: Default::default(),
-            };
-
-            let command_buffer_begin_info = vk::CommandBufferBeginInfo {
-                s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
-                p_next: ptr::null(),
-                p_inheritance_info: &inheritance_info,
-                flags: vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE | vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
-            };
-
-            device
-                .begin_command_buffer(cmd_buf, &command_buffer_begin_info)
-                .expect("Failed to begin recording Command Buffer at beginning!");
-
-            device.cmd_set_viewport(cmd_buf, 0, viewports.as_ref());
-            device.cmd_set_scissor(cmd_buf, 0, scissors.as_ref());
-
-            device.cmd_bind_pipeline(
-                cmd_buf,
-                vk::PipelineBindPoint::GRAPHICS,
-                pipeline.graphics_pipeline,
-            );
-
-            let descriptor_sets_to_bind = [descriptor_set.set];
-            device.cmd_bind_descriptor_sets(
-                cmd_buf,
-                vk::PipelineBindPoint::GRAPHICS,
-                pipeline.pipeline_layout,
-                0,
-                &descriptor_sets_to_bind,
-                &[],
-            );
-
-            device.cmd_draw(cmd_buf, 3, 1, 0, 0);
-
-            device.end_command_buffer(cmd_buf).unwrap();
-        }
-
-        cmd_buf
-    }
 
-    pub fn update_framebuffer(&mut self, framebuffer: &Framebuffer, shadow_map_view: vk::ImageView, dimensions: [u32; 2]) {
+    pub fn update_framebuffer(&mut self, framebuffer: &Framebuffer, shadow_map_view: vk::ImageView, shadow_moments_view: Option<vk::ImageView>,
+                              ssao_view: vk::ImageView, tile_light_buffer: vk::Buffer, dimensions: [u32; 2]) {
+        let (moments_view, moments_layout) = moments_binding(shadow_map_view, shadow_moments_view);
         self.descriptor_set = DescriptorSetBuilder::new(
             self.env.device(), self.pipeline.descriptor_set_layouts.get(0).unwrap())
             .add_image(framebuffer.attachments.get(0).unwrap().view, self.sampler)
@@ -216,9 +236,18 @@ impl QuadRenderer {
             .add_image(framebuffer.attachments.get(2).unwrap().view, self.sampler)
             .add_image_with_layout(shadow_map_view, self.shadow_sampler, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
             .add_buffer(self.uniform_buffer.buffer)
+            .add_image(ssao_view, self.sampler)
+            .add_image(framebuffer.attachments.get(3).unwrap().view, self.sampler)
+            .add_image(framebuffer.attachments.get(4).unwrap().view, self.sampler)
+            .add_buffer(self.light_buffer)
+            .add_buffer(tile_light_buffer)
+            .add_image_with_layout(shadow_map_view, self.shadow_compare_sampler, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+            .add_image_with_layout(moments_view, self.sampler, moments_layout)
+            .add_image_with_layout(self.shadow_atlas_view, self.shadow_compare_sampler, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+            .add_buffer(self.shadow_view_buffer)
             .build();
 
-        self.second_buffer = Self::render_quad(&self.env, dimensions, &self.pipeline, &self.descriptor_set, self.render_pass);
+        self.second_buffer = record_fullscreen_pass(&self.env, dimensions, &self.pipeline, &self.descriptor_set, self.render_pass);
     }
 }
 
@@ -227,6 +256,15 @@ impl Drop for QuadRenderer {
         unsafe {
             self.env.device().destroy_sampler(self.sampler, None);
             self.env.device().destroy_sampler(self.shadow_sampler, None);
+            self.env.device().destroy_sampler(self.shadow_compare_sampler, None);
         }
     }
 }
+
+// Moments binding must be valid for every filter: shadow map is bound when there are no moments
+fn moments_binding(shadow_map_view: vk::ImageView, shadow_moments_view: Option<vk::ImageView>) -> (vk::ImageView, vk::ImageLayout) {
+    match shadow_moments_view {
+        Some(view) => (view, vk::ImageLayout::GENERAL),
+        None => (shadow_map_view, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL),
+    }
+}
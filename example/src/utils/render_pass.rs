@@ -3,8 +3,9 @@ use std::ptr;
 use ash::version::DeviceV1_0;
 use ash::vk;
 
+// final_layout: PRESENT_SRC_KHR for swapchain, TRANSFER_SRC_OPTIMAL for offscreen readback
 pub fn create_quad_render_pass(
-    device: &ash::Device, surface_format: vk::Format) -> vk::RenderPass {
+    device: &ash::Device, surface_format: vk::Format, final_layout: vk::ImageLayout) -> vk::RenderPass {
     let color_attachment = vk::AttachmentDescription {
         format: surface_format,
         flags: vk::AttachmentDescriptionFlags::empty(),
@@ -14,7 +15,7 @@ pub fn create_quad_render_pass(
         stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
         stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
         initial_layout: vk::ImageLayout::UNDEFINED,
-        final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+        final_layout,
     };
 
     let color_attachment_ref = vk::AttachmentReference {
@@ -0,0 +1,168 @@
+use std::path::{Path, PathBuf};
+
+use ash::vk;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
+
+use ash_render_env::env::RenderEnv;
+use ash_render_env::utils::{exr, texture_utils};
+
+const SCREENSHOT_KEY: VirtualKeyCode = VirtualKeyCode::F12;
+const SCREENSHOT_DIR: &str = "screenshots";
+
+// Image saved with screenshot hotkey
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ScreenshotSource {
+    Swapchain,
+    // attachment index
+    GBuffer(usize),
+    Hdr,
+    ShadowCascade(usize),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ScreenshotFormat {
+    // 8-bit for color targets, 16-bit for float and depth
+    Png,
+    // raw float values
+    Exr,
+}
+
+impl ScreenshotFormat {
+    pub const ALL: [ScreenshotFormat; 2] = [ScreenshotFormat::Png, ScreenshotFormat::Exr];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "PNG",
+            ScreenshotFormat::Exr => "EXR",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Exr => "exr",
+        }
+    }
+}
+
+// What to read: level 0 of `layer`, image is in `layout`
+pub struct ScreenshotImage {
+    pub image: vk::Image,
+    pub layout: vk::ImageLayout,
+    pub format: vk::Format,
+    pub size: [u32; 2],
+    pub samples: vk::SampleCountFlags,
+    pub layer: u32,
+}
+
+impl ScreenshotImage {
+    // Format is decoded by `texture_utils`. Both screenshot formats accept every readable image.
+    pub fn is_readable(&self) -> bool {
+        texture_utils::is_readback_supported(self.format)
+    }
+}
+
+// Saves image to "<dir>/<name>.png|exr" and returns the path
+pub fn save_screenshot(env: &RenderEnv, source: &ScreenshotImage, format: ScreenshotFormat, dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(format!("{}.{}", name, format.extension()));
+
+    match format {
+        ScreenshotFormat::Png => {
+            texture_utils::read_image(env, source.image, source.layout, source.format, source.size, source.samples, source.layer)
+                .save(&path)
+                .expect("Failed to save screenshot!");
+        }
+        ScreenshotFormat::Exr => {
+            let image = texture_utils::read_image_hdr(env, source.image, source.layout, source.format, source.size, source.samples, source.layer);
+            exr::save_exr(&path, &image).expect("Failed to save screenshot!");
+        }
+    }
+
+    path
+}
+
+// Selected source and format, request from hotkey or GUI. Sources and their images are provided
+// by the application.
+pub struct Screenshots {
+    source: ScreenshotSource,
+    format: ScreenshotFormat,
+    // saved after the next frame is rendered
+    requested: bool,
+    count: u32,
+    last_path: Option<String>,
+}
+
+impl Screenshots {
+    pub fn new() -> Screenshots {
+        Screenshots {
+            source: ScreenshotSource::Swapchain,
+            format: ScreenshotFormat::Png,
+            requested: false,
+            count: 0,
+            last_path: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, input: &KeyboardInput) {
+        if input.state == ElementState::Pressed && input.virtual_keycode == Some(SCREENSHOT_KEY) {
+            self.requested = true;
+        }
+    }
+
+    // True once per request
+    pub fn take_request(&mut self) -> bool {
+        std::mem::replace(&mut self.requested, false)
+    }
+
+    // Selection falls back to the first source when it is gone (e.g. fewer cascades)
+    pub fn select_source(&mut self, sources: &[ScreenshotSource]) -> ScreenshotSource {
+        if !sources.contains(&self.source) {
+            self.source = sources[0];
+        }
+        self.source
+    }
+
+    // `sources` with their names, only readable sources should be offered
+    pub fn gui(&mut self, ui: &mut egui::Ui, sources: &[(ScreenshotSource, String)]) {
+        let source_list: Vec<ScreenshotSource> = sources.iter().map(|(source, _)| *source).collect();
+        let mut source = self.select_source(&source_list);
+        let name = |source: ScreenshotSource| sources.iter()
+            .find(|(candidate, _)| *candidate == source)
+            .map(|(_, name)| name.as_str())
+            .unwrap_or("");
+
+        egui::ComboBox::from_label("Source")
+            .selected_text(name(source))
+            .show_ui(ui, |ui| {
+                for (candidate, candidate_name) in sources.iter() {
+                    ui.selectable_value(&mut source, *candidate, candidate_name.as_str());
+                }
+            });
+        self.source = source;
+
+        ui.horizontal(|ui| {
+            for format in ScreenshotFormat::ALL.iter() {
+                ui.radio_value(&mut self.format, *format, format.name());
+            }
+        });
+
+        if ui.button(format!("Save ({:?})", SCREENSHOT_KEY)).clicked() {
+            self.requested = true;
+        }
+        if let Some(path) = &self.last_path {
+            ui.label(path.as_str());
+        }
+    }
+
+    // Saves `image` of source `name` to SCREENSHOT_DIR in selected format
+    pub fn save(&mut self, env: &RenderEnv, image: &ScreenshotImage, name: &str) {
+        let dir = Path::new(SCREENSHOT_DIR);
+        std::fs::create_dir_all(dir).expect("Failed to create screenshot dir!");
+        self.count += 1;
+        let file_name = format!("screenshot_{:03}_{}", self.count, name);
+        let path = save_screenshot(env, image, self.format, dir, &file_name);
+
+        log::info!("saved {}", path.display());
+        self.last_path = Some(path.display().to_string());
+    }
+}
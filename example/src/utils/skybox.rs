@@ -134,7 +134,8 @@ impl SkyboxVertexData {
             env.command_pool(),
             env.queue(),
             vk::BufferUsageFlags::VERTEX_BUFFER,
-            vertices);
+            vertices,
+            Some((env.debug_names(), "skybox.vertices")));
 
         let (index_buffer, index_buffer_memory) = create_data_buffer(
             env.instance(),
@@ -143,7 +144,8 @@ impl SkyboxVertexData {
             env.command_pool(),
             env.queue(),
             vk::BufferUsageFlags::INDEX_BUFFER,
-            indices);
+            indices,
+            Some((env.debug_names(), "skybox.indices")));
 
         let texture = CubeTexture::new(
             env.device().clone(),
@@ -151,6 +153,7 @@ impl SkyboxVertexData {
             env.queue(),
             &env.mem_properties,
             Path::new("./assets/skybox"),
+            Some((env.debug_names(), "skybox.texture")),
         );
 
         SkyboxVertexData {
@@ -32,11 +32,13 @@ pub struct SkyboxRenderer {
 
 impl SkyboxRenderer {
     pub fn new(env: Arc<RenderEnv>, render_pass: vk::RenderPass, color_attachment_count: usize,
-               msaa_samples: vk::SampleCountFlags, max_inflight_frames: usize,
+               msaa_samples: vk::SampleCountFlags, reverse_z: bool, max_inflight_frames: usize,
                dimensions: [u32; 2]) -> SkyboxRenderer
     {
         let pipeline = {
-            let vert_shader_module = shader::Shader::load(env.device(), "assets/shaders/spv/skybox.vert.spv");
+            // sky is drawn at far plane depth
+            let vert_shader_module = shader::Shader::load(env.device(), "assets/shaders/spv/skybox.vert.spv")
+                .specialize(shader::ConstantsBuilder::new().add_u32(reverse_z as u32));
             let frag_shader_module = shader::Shader::load(env.device(), "assets/shaders/spv/skybox.frag.spv");
 
             PipelineBuilder::new(env.device().clone(), render_pass, 0)
@@ -46,7 +48,9 @@ impl SkyboxRenderer {
                 .msaa(msaa_samples)
                 .color_attachment_count(color_attachment_count)
                 .with_depth_func(vk::CompareOp::LESS_OR_EQUAL)
+                .reverse_z(reverse_z)
                 .cull_mode(vk::CullModeFlags::BACK)
+                .debug_name(&env, "skybox")
                 .build()
         };
 
@@ -0,0 +1,87 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use ash_render_env::env::RenderEnv;
+use ash_render_env::utils::buffer_utils::create_buffer_;
+
+// Host visible storage buffer: written by compute shaders, may be read back for debug output
+pub struct StorageBuffer<T> {
+    pub buffer: vk::Buffer,
+    pub buffer_memory: vk::DeviceMemory,
+    size: u64,
+    device: ash::Device,
+
+    phantom: PhantomData<T>,
+}
+
+impl<T> Drop for StorageBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_buffer(self.buffer, None);
+            self.device.free_memory(self.buffer_memory, None);
+        }
+    }
+}
+
+impl<T: Copy> StorageBuffer<T> {
+    pub fn new(env: Arc<RenderEnv>, initial: T) -> StorageBuffer<T> {
+        let buffer_size = std::mem::size_of::<T>();
+
+        let (buffer, buffer_memory, size) = create_buffer_(
+            env.device(),
+            buffer_size as u64,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            &env.mem_properties,
+            None,
+        );
+
+        let storage_buffer = StorageBuffer {
+            buffer,
+            buffer_memory,
+            size,
+            device: env.device().clone(),
+            phantom: PhantomData,
+        };
+        storage_buffer.write_data(initial);
+
+        storage_buffer
+    }
+
+    // Names buffer "<name>" and its memory "<name>.memory"
+    pub fn debug_name(self, env: &RenderEnv, name: &str) -> Self {
+        env.set_name(self.buffer, name);
+        env.set_name(self.buffer_memory, &format!("{}.memory", name));
+        self
+    }
+
+    pub fn write_data(&self, data: T) {
+        unsafe {
+            let data_ptr = self.device
+                .map_memory(self.buffer_memory, 0, self.size, vk::MemoryMapFlags::empty())
+                .expect("Failed to Map Memory") as *mut T;
+
+            data_ptr.copy_from_nonoverlapping(&data, 1);
+
+            self.device.unmap_memory(self.buffer_memory);
+        }
+    }
+
+    // Caller must be sure that GPU finished writing (frame fence is signaled)
+    pub fn read_data(&self) -> T {
+        unsafe {
+            let data_ptr = self.device
+                .map_memory(self.buffer_memory, 0, self.size, vk::MemoryMapFlags::empty())
+                .expect("Failed to Map Memory") as *const T;
+
+            let data = data_ptr.read();
+
+            self.device.unmap_memory(self.buffer_memory);
+
+            data
+        }
+    }
+}
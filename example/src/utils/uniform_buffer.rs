@@ -40,6 +40,7 @@ impl UboBuffers {
                 vk::BufferUsageFlags::UNIFORM_BUFFER,
                 vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
                 &mem_properties,
+                None,
             );
             uniform_buffers.push(uniform_buffer);
             uniform_buffers_memory.push(uniform_buffer_memory);
@@ -0,0 +1,148 @@
+// Golden-image regression tests: renders fixed camera views with `ash-test --headless` and compares
+// them with reference PNGs in tests/golden.
+//
+// Needs downloaded assets, compiled shaders and a Vulkan driver, so tests are ignored by default:
+//   cargo test --package ash-test --test golden -- --ignored
+// Software rendering: VK_ICD_FILENAMES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json
+// Updating references: UPDATE_GOLDEN=1 cargo test --package ash-test --test golden -- --ignored
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use image::{Rgba, RgbaImage};
+
+const UPDATE_COMMAND: &str = "UPDATE_GOLDEN=1 cargo test --package ash-test --test golden -- --ignored";
+
+const SIZE: &str = "320x240";
+const FRAMES: &str = "30";
+
+// per-pixel color distance threshold (0..1, YIQ), like pixelmatch
+const PIXEL_THRESHOLD: f32 = 0.1;
+// fraction of pixels allowed to differ (driver / rasterization differences)
+const MAX_DIFF_FRACTION: f32 = 0.005;
+
+struct GoldenView {
+    name: &'static str,
+    // x,y,z,yaw,pitch
+    camera: &'static str,
+}
+
+const VIEWS: &[GoldenView] = &[
+    GoldenView { name: "terrain_overview", camera: "0,-20,-30,90,-30" },
+    GoldenView { name: "chalet_close", camera: "-3,-2,-3,45,-15" },
+    GoldenView { name: "horizon", camera: "0,-5,0,0,5" },
+];
+
+fn workspace_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf()
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn output_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join("ash-test-golden");
+    std::fs::create_dir_all(&dir).expect("Failed to create output dir!");
+    dir
+}
+
+fn render(view: &GoldenView, output: &Path) {
+    let status = Command::new(env!("CARGO_BIN_EXE_ash-test"))
+        .current_dir(workspace_dir())
+        .args(&["--headless", "--strict-validation", "--size", SIZE, "--frames", FRAMES, "--camera", view.camera])
+        .arg("--output")
+        .arg(output)
+        .status()
+        .expect("Failed to run ash-test!");
+
+    assert!(status.success(), "{}: headless render failed ({}), see validation errors above", view.name, status);
+}
+
+fn yiq(pixel: &Rgba<u8>) -> [f32; 3] {
+    let [r, g, b] = [pixel[0] as f32 / 255.0, pixel[1] as f32 / 255.0, pixel[2] as f32 / 255.0];
+    [
+        0.298_895_3 * r + 0.586_622_5 * g + 0.114_482_2 * b,
+        0.595_977_9 * r - 0.274_176_0 * g - 0.321_801_9 * b,
+        0.211_470_2 * r - 0.522_617_0 * g + 0.311_146_8 * b,
+    ]
+}
+
+// Normalized perceptual distance, 0 for same colors, 1 for black / white
+fn color_distance(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let (a, b) = (yiq(a), yiq(b));
+    let (y, i, q) = (a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / 0.5053).sqrt()
+}
+
+// Returns differing pixel count and diff image (differences in red over faded actual image)
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> (usize, RgbaImage) {
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut count = 0;
+
+    for ((e, a), d) in expected.pixels().zip(actual.pixels()).zip(diff.pixels_mut()) {
+        if color_distance(e, a) > PIXEL_THRESHOLD {
+            count += 1;
+            *d = Rgba([255, 0, 0, 255]);
+        } else {
+            let gray = (yiq(a)[0] * 255.0 * 0.3 + 255.0 * 0.7) as u8;
+            *d = Rgba([gray, gray, gray, 255]);
+        }
+    }
+
+    (count, diff)
+}
+
+#[test]
+#[ignore]
+fn golden_images() {
+    let update = std::env::var("UPDATE_GOLDEN").map_or(false, |v| v == "1");
+
+    // missing reference is a setup error, not a mismatch: fail before rendering anything
+    if !update {
+        let missing: Vec<String> = VIEWS
+            .iter()
+            .map(|view| golden_dir().join(format!("{}.png", view.name)))
+            .filter(|path| !path.is_file())
+            .map(|path| path.display().to_string())
+            .collect();
+        assert!(missing.is_empty(),
+                "Golden reference images are missing:\n{}\nCreate them with\n  {}\nthen check them visually and commit.",
+                missing.join("\n"), UPDATE_COMMAND);
+    }
+
+    let mut failures = vec![];
+
+    for view in VIEWS {
+        let actual_path = output_dir().join(format!("{}.png", view.name));
+        let golden_path = golden_dir().join(format!("{}.png", view.name));
+        render(view, &actual_path);
+
+        if update {
+            std::fs::copy(&actual_path, &golden_path).expect("Failed to update golden image!");
+            println!("{}: updated {}", view.name, golden_path.display());
+            continue;
+        }
+
+        let expected = image::open(&golden_path)
+            .unwrap_or_else(|err| panic!("Failed to open golden image {}: {}", golden_path.display(), err))
+            .to_rgba8();
+        let actual = image::open(&actual_path).expect("Failed to open rendered image!").to_rgba8();
+
+        if expected.dimensions() != actual.dimensions() {
+            failures.push(format!("{}: size {:?} != golden {:?}", view.name, actual.dimensions(), expected.dimensions()));
+            continue;
+        }
+
+        let (diff_count, diff) = compare(&expected, &actual);
+        let diff_fraction = diff_count as f32 / (actual.width() * actual.height()) as f32;
+        if diff_fraction > MAX_DIFF_FRACTION {
+            let diff_path = output_dir().join(format!("{}.diff.png", view.name));
+            diff.save(&diff_path).expect("Failed to save diff image!");
+            failures.push(format!("{}: {} pixels ({:.2}%) differ, actual {}, diff {}",
+                                  view.name, diff_count, diff_fraction * 100.0, actual_path.display(), diff_path.display()));
+        }
+    }
+
+    assert!(failures.is_empty(), "Golden image mismatch:\n{}", failures.join("\n"));
+}
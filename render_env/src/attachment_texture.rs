@@ -9,9 +9,13 @@ use crate::utils::format_has_depth;
 pub struct AttachmentImage {
     device: ash::Device,
     memory: vk::DeviceMemory,
-    image: vk::Image,
+    pub image: vk::Image,
+    // view over the whole mip chain (for sampling)
     pub view: vk::ImageView,
+    // single level views (render targets), empty if image has one level
+    mip_views: Vec<vk::ImageView>,
     pub format: vk::Format,
+    pub mip_levels: u32,
 }
 
 impl AttachmentImage {
@@ -78,32 +82,14 @@ impl AttachmentImage {
             vk::ImageAspectFlags::COLOR
         };
 
-        let imageview_create_info = vk::ImageViewCreateInfo {
-            s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::ImageViewCreateFlags::empty(),
-            view_type: vk::ImageViewType::TYPE_2D,
-            format,
-            components: vk::ComponentMapping {
-                r: vk::ComponentSwizzle::IDENTITY,
-                g: vk::ComponentSwizzle::IDENTITY,
-                b: vk::ComponentSwizzle::IDENTITY,
-                a: vk::ComponentSwizzle::IDENTITY,
-            },
-            subresource_range: vk::ImageSubresourceRange {
-                aspect_mask,
-                base_mip_level: 0,
-                level_count: mip_levels,
-                base_array_layer: 0,
-                layer_count: 1,
-            },
-            image: texture_image,
-        };
+        let image_view = create_view(env.device(), texture_image, format, aspect_mask, 0, mip_levels);
 
-        let image_view = unsafe {
-            env.device()
-                .create_image_view(&imageview_create_info, None)
-                .expect("Failed to create Image View!")
+        let mip_views = if mip_levels > 1 {
+            (0..mip_levels)
+                .map(|level| create_view(env.device(), texture_image, format, aspect_mask, level, 1))
+                .collect()
+        } else {
+            vec![]
         };
 
         AttachmentImage {
@@ -111,14 +97,69 @@ impl AttachmentImage {
             memory: texture_image_memory,
             image: texture_image,
             view: image_view,
+            mip_views,
             format,
+            mip_levels,
+        }
+    }
+
+    pub fn mip_view(&self, level: u32) -> vk::ImageView {
+        if self.mip_views.is_empty() {
+            assert_eq!(level, 0, "Image has only one mip level");
+            return self.view;
+        }
+
+        self.mip_views[level as usize]
+    }
+
+    // Image, memory and views ("<name>.mip1", ...)
+    pub fn set_debug_name(&self, env: &RenderEnv, name: &str) {
+        env.set_name(self.image, name);
+        env.set_name(self.memory, &format!("{}.memory", name));
+        env.set_name(self.view, &format!("{}.view", name));
+        for (level, mip_view) in self.mip_views.iter().enumerate() {
+            env.set_name(*mip_view, &format!("{}.mip{}", name, level));
         }
     }
 }
 
+fn create_view(device: &ash::Device, image: vk::Image, format: vk::Format, aspect_mask: vk::ImageAspectFlags,
+               base_mip_level: u32, level_count: u32) -> vk::ImageView {
+    let imageview_create_info = vk::ImageViewCreateInfo {
+        s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
+        p_next: ptr::null(),
+        flags: vk::ImageViewCreateFlags::empty(),
+        view_type: vk::ImageViewType::TYPE_2D,
+        format,
+        components: vk::ComponentMapping {
+            r: vk::ComponentSwizzle::IDENTITY,
+            g: vk::ComponentSwizzle::IDENTITY,
+            b: vk::ComponentSwizzle::IDENTITY,
+            a: vk::ComponentSwizzle::IDENTITY,
+        },
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask,
+            base_mip_level,
+            level_count,
+            base_array_layer: 0,
+            layer_count: 1,
+        },
+        image,
+    };
+
+    unsafe {
+        device
+            .create_image_view(&imageview_create_info, None)
+            .expect("Failed to create Image View!")
+    }
+}
+
 impl Drop for AttachmentImage {
     fn drop(&mut self) {
         unsafe {
+            for mip_view in self.mip_views.iter() {
+                self.device.destroy_image_view(*mip_view, None);
+            }
             self.device.destroy_image_view(self.view, None);
             self.device.destroy_image(self.image, None);
             self.device.free_memory(self.memory, None);
@@ -3,24 +3,23 @@ use std::f32;
 use cgmath::{Deg, EuclideanSpace, Matrix4, Point3, SquareMatrix, vec3, Vector3};
 use cgmath::{Angle, Rad};
 use cgmath::InnerSpace;
-use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
 
+use crate::math::{Frustum, Ray};
+use crate::projection::Projection;
+
+// View and projection. Position and orientation are driven by `CameraController`.
 pub struct Camera {
     position: Point3<f32>,
     proj: Matrix4<f32>,
+    // degrees
     yaw: f32,
     pitch: f32,
 
-    mouse_pressed: bool,
-    last_mouse_position: [i32; 2],
-
     view_dir: Vector3<f32>,
     up_dir: Vector3<f32>,
 
     viewport: [u32; 2],
-
-    pub near_clip: f32,
-    pub far_clip: f32,
+    projection: Projection,
 }
 
 impl Camera {
@@ -28,26 +27,44 @@ impl Camera {
         Camera {
             position: Point3::new(0.0, -0.4, 0.0),
             proj: Matrix4::identity(),
-            mouse_pressed: false,
-            last_mouse_position: [0, 0],
             viewport: [0, 0],
+            projection: Projection::default(),
             view_dir: vec3(0.0, 0.0, -1.0),
             up_dir: vec3(0.0, 1.0, 0.0),
             yaw: -90.0,
             pitch: 0.0,
-            near_clip: 0.05,
-            far_clip: 48.0,
         }
     }
 
     pub fn set_viewport(&mut self, w: u32, h: u32) {
         self.viewport = [w, h];
-        self.proj = cgmath::perspective(
-            Rad::from(Deg(45.0)),
-            w as f32 / h as f32,
-            self.near_clip,
-            self.far_clip,
-        );
+        self.update_projection();
+    }
+
+    pub fn fov_y(&self) -> f32 {
+        self.projection.fov_y
+    }
+
+    pub fn set_fov_y(&mut self, fov_y: f32) {
+        self.projection.fov_y = fov_y;
+        self.update_projection();
+    }
+
+    pub fn projection(&self) -> &Projection {
+        &self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.update_projection();
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.viewport[0] as f32 / self.viewport[1].max(1) as f32
+    }
+
+    fn update_projection(&mut self) {
+        self.proj = self.projection.matrix(self.aspect());
     }
 
     pub fn view_matrix(&self) -> Matrix4<f32> {
@@ -62,81 +79,85 @@ impl Camera {
         self.proj
     }
 
+    // View volume in world space for culling
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(self.proj * self.view_matrix())
+    }
+
+    pub fn inverse_view_proj(&self) -> Matrix4<f32> {
+        (self.proj * self.view_matrix()).invert().expect("Camera matrix is not invertible!")
+    }
+
+    // NDC (xy in [-1, 1], Vulkan depth) to world space. Depth must be finite for infinite projection.
+    pub fn unproject(&self, ndc: Vector3<f32>) -> Point3<f32> {
+        let world = self.inverse_view_proj() * ndc.extend(1.0);
+        Point3::from_vec(world.truncate() / world.w)
+    }
+
+    // Ray from near plane through window pixel (origin at top left, physical pixels)
+    pub fn screen_ray(&self, x: f32, y: f32) -> Ray {
+        let ndc_x = x / self.viewport[0].max(1) as f32 * 2.0 - 1.0;
+        // projection has no Y flip: top of the screen is NDC -1 in Vulkan
+        let ndc_y = y / self.viewport[1].max(1) as f32 * 2.0 - 1.0;
+
+        let near_depth = if self.projection.reverse_z { 1.0 } else { 0.0 };
+        let origin = self.unproject(vec3(ndc_x, ndc_y, near_depth));
+        let target = self.unproject(vec3(ndc_x, ndc_y, 0.5));
+
+        Ray::new(origin, target - origin)
+    }
+
+    // Finite OpenGL style projection up to `far_clip`, see `Projection::frustum_matrix`
+    pub fn frustum_proj_matrix(&self) -> Matrix4<f32> {
+        self.projection.frustum_matrix(self.aspect())
+    }
+
     pub fn position(&self) -> Point3<f32> {
         self.position
     }
 
+    pub fn set_position(&mut self, position: Point3<f32>) {
+        self.position = position;
+    }
+
     pub fn view_dir(&self) -> Vector3<f32> {
         self.view_dir
     }
 
-    fn handle_keyboard(&mut self, input: KeyboardInput) {
-        if input.state == ElementState::Pressed {
-            match input.virtual_keycode {
-                Some(VirtualKeyCode::W) => self.position += self.view_dir * 0.3,
-                Some(VirtualKeyCode::S) => self.position -= self.view_dir * 0.3,
+    pub fn up_dir(&self) -> Vector3<f32> {
+        self.up_dir
+    }
 
-                Some(VirtualKeyCode::H) => self.position += self.view_dir * 0.03,
-                Some(VirtualKeyCode::J) => self.position -= self.view_dir * 0.03,
+    pub fn right_dir(&self) -> Vector3<f32> {
+        self.view_dir.cross(self.up_dir).normalize()
+    }
 
-                Some(VirtualKeyCode::A) => self.position -= self.view_dir.cross(self.up_dir) * 0.3,
-                Some(VirtualKeyCode::D) => self.position += self.view_dir.cross(self.up_dir) * 0.3,
-                Some(VirtualKeyCode::Space) => self.position.y += 0.1,
-                Some(VirtualKeyCode::LShift) => self.position.y -= 0.1,
-                _ => (),
-            }
-        }
+    pub fn yaw(&self) -> f32 {
+        self.yaw
     }
 
-    pub fn mouse_acquired(&self) -> bool {
-        self.mouse_pressed
-    }
-
-    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
-        let mut changed = false;
-        match event {
-            &WindowEvent::KeyboardInput { input, .. } => {
-                self.handle_keyboard(input);
-                changed = true;
-            }
-
-            &WindowEvent::MouseInput { state, button, .. } => {
-                self.mouse_pressed = (state == ElementState::Pressed) && (button == MouseButton::Left);
-            }
-            &WindowEvent::CursorMoved { position, .. } => {
-                if !self.mouse_pressed {
-                    self.last_mouse_position = position.into();
-                    return changed;
-                }
-
-                let pos: [i32; 2] = position.into();
-                let sensitivity = 0.5;
-                let dx = (pos[0] - self.last_mouse_position[0]) as f32 * sensitivity;
-                let dy = (pos[1] - self.last_mouse_position[1]) as f32 * sensitivity;
-                self.last_mouse_position = position.into();
-
-                self.yaw += dx;
-                self.pitch += dy;
-
-                if self.pitch > 89.0 {
-                    self.pitch = 89.0;
-                }
-
-                if self.pitch < -89.0 {
-                    self.pitch = -89.0;
-                }
-
-                self.view_dir = Vector3::new(
-                    Rad::from(Deg(self.yaw)).cos() * Rad::from(Deg(self.pitch)).cos(),
-                    Rad::from(Deg(self.pitch)).sin(),
-                    Rad::from(Deg(self.yaw)).sin() * Rad::from(Deg(self.pitch)).cos(),
-                ).normalize();
-
-                changed = true
-            }
-            _ => (),
-        }
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    // Angles in degrees, pitch is clamped to avoid flipping over the pole
+    pub fn set_rotation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw;
+        self.pitch = pitch.max(-89.0).min(89.0);
+
+        self.view_dir = Vector3::new(
+            Rad::from(Deg(self.yaw)).cos() * Rad::from(Deg(self.pitch)).cos(),
+            Rad::from(Deg(self.pitch)).sin(),
+            Rad::from(Deg(self.yaw)).sin() * Rad::from(Deg(self.pitch)).cos(),
+        ).normalize();
+    }
+
+    // Rotates camera towards `target`
+    pub fn look_at(&mut self, target: Point3<f32>) {
+        let dir = (target - self.position).normalize();
+        let pitch = Deg::from(Rad(dir.y.max(-1.0).min(1.0).asin())).0;
+        let yaw = Deg::from(Rad(dir.z.atan2(dir.x))).0;
 
-        changed
+        self.set_rotation(yaw, pitch);
     }
 }
@@ -0,0 +1,289 @@
+use cgmath::{Angle, Deg, InnerSpace, Point3, Rad, Vector3, Zero};
+
+use crate::camera::Camera;
+use crate::input::InputState;
+
+// Longer frames (window drag, shader reload) would make the camera jump
+const MAX_DELTA_TIME: f32 = 0.1;
+
+// Moves camera from input state. Controllers can be swapped at runtime, new controller continues
+// from the current camera state.
+pub trait CameraController {
+    fn name(&self) -> &'static str;
+
+    // Called every frame with frame time in seconds. Returns true when camera moved.
+    fn update(&mut self, camera: &mut Camera, input: &mut InputState, dt: f32) -> bool;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraMode {
+    Fly,
+    Orbit,
+    Walk,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 3] = [CameraMode::Fly, CameraMode::Orbit, CameraMode::Walk];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraMode::Fly => "Fly",
+            CameraMode::Orbit => "Orbit (wheel: zoom)",
+            CameraMode::Walk => "Walk on terrain",
+        }
+    }
+
+    // New controller continues from the current camera position and orientation.
+    // `height_query` is used by walk mode only, see `WalkController::new`.
+    pub fn create_controller(&self, camera: &Camera, height_query: Box<dyn Fn(f32, f32) -> f32>) -> Box<dyn CameraController> {
+        match self {
+            CameraMode::Fly => Box::new(FlyController::new()),
+            CameraMode::Orbit => Box::new(OrbitController::new(camera, 3.0)),
+            CameraMode::Walk => Box::new(WalkController::new(height_query)),
+        }
+    }
+}
+
+// Velocity integration: movement keys accelerate, damping slows down. Top speed is reached
+// when acceleration equals damping (`speed`), so movement does not depend on frame rate.
+#[derive(Clone, Copy, Debug)]
+pub struct Movement {
+    velocity: Vector3<f32>,
+    // top speed, units per second
+    pub speed: f32,
+    // speed multiplier while boost key is held
+    pub boost: f32,
+    // velocity falls by e every 1 / damping seconds without input
+    pub damping: f32,
+}
+
+impl Movement {
+    pub fn new(speed: f32, boost: f32, damping: f32) -> Movement {
+        Movement {
+            velocity: Vector3::zero(),
+            speed,
+            boost,
+            damping,
+        }
+    }
+
+    // `direction`: wanted movement direction (not normalized, zero when no key is held).
+    // Returns displacement for this frame.
+    pub fn update(&mut self, direction: Vector3<f32>, boost: bool, dt: f32) -> Vector3<f32> {
+        let mut acceleration = Vector3::zero();
+        if direction.magnitude2() > 1e-6 {
+            let speed = if boost { self.speed * self.boost } else { self.speed };
+            acceleration = direction.normalize() * speed * self.damping;
+        }
+
+        // exact solution of dv/dt = a - damping * v over dt
+        let decay = (-self.damping * dt).exp();
+        let terminal = acceleration / self.damping;
+        let velocity = terminal + (self.velocity - terminal) * decay;
+        let displacement = terminal * dt + (self.velocity - terminal) * (1.0 - decay) / self.damping;
+
+        self.velocity = if velocity.magnitude2() < 1e-8 { Vector3::zero() } else { velocity };
+        displacement
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.velocity != Vector3::zero()
+    }
+}
+
+fn mouse_look(camera: &mut Camera, input: &mut InputState) -> bool {
+    let delta = input.take_look_delta();
+    if delta == [0.0, 0.0] {
+        return false;
+    }
+
+    camera.set_rotation(camera.yaw() + delta[0], camera.pitch() + delta[1]);
+    true
+}
+
+// Free flight along view direction, up/down keys move along Y
+pub struct FlyController {
+    pub movement: Movement,
+}
+
+impl FlyController {
+    pub fn new() -> FlyController {
+        FlyController {
+            movement: Movement::new(3.0, 4.0, 10.0),
+        }
+    }
+}
+
+impl CameraController for FlyController {
+    fn name(&self) -> &'static str {
+        "Fly"
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &mut InputState, dt: f32) -> bool {
+        let dt = dt.min(MAX_DELTA_TIME);
+        let rotated = mouse_look(camera, input);
+
+        let keys = input.settings.bindings;
+        let direction = camera.view_dir() * input.axis(keys.forward, keys.backward)
+            + camera.right_dir() * input.axis(keys.right, keys.left)
+            + Vector3::unit_y() * input.axis(keys.up, keys.down);
+
+        let displacement = self.movement.update(direction, input.boost(), dt);
+        if !self.movement.is_moving() && displacement.magnitude2() < 1e-12 {
+            return rotated;
+        }
+
+        camera.set_position(camera.position() + displacement);
+        true
+    }
+}
+
+// Rotates around `target`: look drag orbits, mouse wheel zooms, movement keys pan target in view plane
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    // pan speed is scaled by distance
+    pub movement: Movement,
+}
+
+impl OrbitController {
+    // Orbits around the point `distance` ahead of camera
+    pub fn new(camera: &Camera, distance: f32) -> OrbitController {
+        OrbitController {
+            target: camera.position() + camera.view_dir() * distance,
+            distance,
+            min_distance: 0.2,
+            max_distance: 40.0,
+            movement: Movement::new(2.0, 3.0, 10.0),
+        }
+    }
+}
+
+impl CameraController for OrbitController {
+    fn name(&self) -> &'static str {
+        "Orbit"
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &mut InputState, dt: f32) -> bool {
+        let dt = dt.min(MAX_DELTA_TIME);
+        mouse_look(camera, input);
+
+        let scroll = input.take_scroll_delta();
+        if scroll != 0.0 {
+            self.distance = (self.distance * 0.9_f32.powf(scroll)).max(self.min_distance).min(self.max_distance);
+        }
+
+        let keys = input.settings.bindings;
+        let forward = camera.up_dir().cross(camera.right_dir());
+        let pan = camera.right_dir() * input.axis(keys.right, keys.left)
+            + forward * input.axis(keys.forward, keys.backward);
+        // faster when zoomed out
+        self.target += self.movement.update(pan, input.boost(), dt) * self.distance.max(1.0);
+
+        let position = self.target - camera.view_dir() * self.distance;
+        if position == camera.position() {
+            return false;
+        }
+
+        camera.set_position(position);
+        true
+    }
+}
+
+// First person walk in horizontal plane, eye follows ground height from `height_query`.
+// World -Y is up: eye is at `height - eye_height`.
+pub struct WalkController {
+    height_query: Box<dyn Fn(f32, f32) -> f32>,
+    pub eye_height: f32,
+    pub movement: Movement,
+}
+
+impl WalkController {
+    // `height_query(x, z)`: ground height at world position
+    pub fn new(height_query: Box<dyn Fn(f32, f32) -> f32>) -> WalkController {
+        WalkController {
+            height_query,
+            eye_height: 0.3,
+            movement: Movement::new(1.5, 3.0, 12.0),
+        }
+    }
+}
+
+impl CameraController for WalkController {
+    fn name(&self) -> &'static str {
+        "Walk"
+    }
+
+    fn update(&mut self, camera: &mut Camera, input: &mut InputState, dt: f32) -> bool {
+        let dt = dt.min(MAX_DELTA_TIME);
+        let rotated = mouse_look(camera, input);
+
+        let keys = input.settings.bindings;
+        // from yaw: horizontal part of view direction vanishes when looking straight up or down
+        let yaw = Rad::from(Deg(camera.yaw()));
+        let forward = Vector3::new(yaw.cos(), 0.0, yaw.sin());
+        let right = forward.cross(camera.up_dir()).normalize();
+        let direction = forward * input.axis(keys.forward, keys.backward)
+            + right * input.axis(keys.right, keys.left);
+
+        let mut position = camera.position() + self.movement.update(direction, input.boost(), dt);
+        let eye = (self.height_query)(position.x, position.z) - self.eye_height;
+        // smooth steps on rough terrain
+        position.y += (eye - position.y) * (1.0 - (-15.0 * dt).exp());
+
+        let moved = position != camera.position();
+        camera.set_position(position);
+        rotated || moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Same key held for one frame of `dt` and for `steps` frames of `dt / steps`
+    fn assert_substeps_match(mut movement: Movement, direction: Vector3<f32>, dt: f32, steps: usize) {
+        let mut single = movement;
+        let single_displacement = single.update(direction, false, dt);
+
+        let mut displacement = Vector3::zero();
+        for _ in 0..steps {
+            displacement += movement.update(direction, false, dt / steps as f32);
+        }
+
+        assert!((displacement - single_displacement).magnitude() < 1e-4,
+                "{:?} != {:?}", displacement, single_displacement);
+        assert!((movement.velocity - single.velocity).magnitude() < 1e-4,
+                "{:?} != {:?}", movement.velocity, single.velocity);
+    }
+
+    #[test]
+    fn accelerating_movement_does_not_depend_on_frame_rate() {
+        let movement = Movement::new(3.0, 4.0, 10.0);
+        for &steps in [2, 7, 60].iter() {
+            assert_substeps_match(movement, Vector3::new(1.0, 0.0, 1.0), 0.1, steps);
+        }
+    }
+
+    #[test]
+    fn damped_movement_does_not_depend_on_frame_rate() {
+        let mut movement = Movement::new(3.0, 4.0, 10.0);
+        movement.velocity = Vector3::new(0.0, 2.0, -1.0);
+        for &steps in [2, 7, 60].iter() {
+            assert_substeps_match(movement, Vector3::zero(), 0.1, steps);
+            assert_substeps_match(movement, Vector3::new(-1.0, 0.0, 0.0), 0.1, steps);
+        }
+    }
+
+    #[test]
+    fn movement_reaches_top_speed() {
+        let mut movement = Movement::new(3.0, 4.0, 10.0);
+        movement.update(Vector3::unit_x(), false, 2.0);
+        assert!((movement.velocity.magnitude() - 3.0).abs() < 1e-4);
+
+        movement.update(Vector3::unit_x(), true, 2.0);
+        assert!((movement.velocity.magnitude() - 12.0).abs() < 1e-4);
+    }
+}
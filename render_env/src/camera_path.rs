@@ -0,0 +1,374 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Point3, Quaternion, Rad, Rotation, Rotation3, Vector3};
+
+use crate::camera::Camera;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CameraKeyframe {
+    // seconds from path start
+    pub time: f32,
+    pub position: Point3<f32>,
+    // degrees, same as `Camera::yaw` / `Camera::pitch`
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl CameraKeyframe {
+    pub fn from_camera(camera: &Camera, time: f32) -> CameraKeyframe {
+        CameraKeyframe {
+            time,
+            position: camera.position(),
+            yaw: camera.yaw(),
+            pitch: camera.pitch(),
+        }
+    }
+
+    // View direction (1, 0, 0) rotated by pitch around Z, then by yaw around Y (see `Camera::set_rotation`)
+    fn rotation(&self) -> Quaternion<f32> {
+        Quaternion::from_angle_y(Deg(-self.yaw)) * Quaternion::from_angle_z(Deg(self.pitch))
+    }
+}
+
+// Keyframes sorted by time. Positions are interpolated with Catmull-Rom spline, rotations with slerp.
+#[derive(Clone, Debug, Default)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+}
+
+impl CameraPath {
+    pub fn new() -> CameraPath {
+        CameraPath { keyframes: Vec::new() }
+    }
+
+    // Keyframe time must not be less than time of the last one
+    pub fn push(&mut self, keyframe: CameraKeyframe) {
+        if let Some(last) = self.keyframes.last() {
+            assert!(keyframe.time >= last.time, "Camera path keyframes must be sorted by time!");
+        }
+        self.keyframes.push(keyframe);
+    }
+
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |last| last.time)
+    }
+
+    // Camera state at `time`, clamped to path duration
+    pub fn sample(&self, time: f32) -> CameraKeyframe {
+        assert!(!self.keyframes.is_empty(), "Camera path is empty!");
+
+        let last = self.keyframes.len() - 1;
+        let segment = self.keyframes.iter().rposition(|keyframe| keyframe.time <= time).unwrap_or(0).min(last);
+        if segment == last {
+            return CameraKeyframe { time, ..self.keyframes[last] };
+        }
+
+        let (k1, k2) = (&self.keyframes[segment], &self.keyframes[segment + 1]);
+        // end points are repeated
+        let k0 = &self.keyframes[segment.saturating_sub(1)];
+        let k3 = &self.keyframes[(segment + 2).min(last)];
+
+        let span = k2.time - k1.time;
+        let t = if span > 0.0 { ((time - k1.time) / span).max(0.0).min(1.0) } else { 0.0 };
+        // keyframe as is, angles are not round-tripped through rotation
+        if t == 0.0 {
+            return CameraKeyframe { time, ..*k1 };
+        }
+
+        let position = catmull_rom(k0.position, k1.position, k2.position, k3.position, t);
+
+        let q1 = k1.rotation();
+        let mut q2 = k2.rotation();
+        // shortest arc
+        if q1.dot(q2) < 0.0 {
+            q2 = -q2;
+        }
+        let direction = q1.slerp(q2, t).rotate_vector(Vector3::unit_x()).normalize();
+
+        CameraKeyframe {
+            time,
+            position,
+            yaw: Deg::from(Rad(direction.z.atan2(direction.x))).0,
+            pitch: Deg::from(Rad(direction.y.max(-1.0).min(1.0).asin())).0,
+        }
+    }
+
+    // Text file, one keyframe per line: time x y z yaw pitch
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for k in self.keyframes.iter() {
+            text += &format!("{} {} {} {} {} {}\n", k.time, k.position.x, k.position.y, k.position.z, k.yaw, k.pitch);
+        }
+
+        fs::write(path, text)
+    }
+
+    pub fn load(path: &Path) -> io::Result<CameraPath> {
+        let text = fs::read_to_string(path)?;
+        let invalid = |line: usize| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid camera keyframe at line {}", line + 1));
+
+        let mut result = CameraPath::new();
+        for (line_idx, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let values = line.split_whitespace()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| invalid(line_idx))?;
+
+            if values.len() != 6 || result.keyframes.last().map_or(false, |last| values[0] < last.time) {
+                return Err(invalid(line_idx));
+            }
+
+            result.push(CameraKeyframe {
+                time: values[0],
+                position: Point3::new(values[1], values[2], values[3]),
+                yaw: values[4],
+                pitch: values[5],
+            });
+        }
+
+        Ok(result)
+    }
+}
+
+// Samples camera at fixed interval of frame time
+pub struct CameraPathRecorder {
+    path: CameraPath,
+    time: f32,
+    next_keyframe: f32,
+    // seconds between keyframes
+    pub interval: f32,
+}
+
+impl CameraPathRecorder {
+    pub fn new(interval: f32) -> CameraPathRecorder {
+        CameraPathRecorder {
+            path: CameraPath::new(),
+            time: 0.0,
+            next_keyframe: 0.0,
+            interval,
+        }
+    }
+
+    // Called every frame with frame time in seconds
+    pub fn update(&mut self, camera: &Camera, dt: f32) {
+        if self.time >= self.next_keyframe {
+            self.path.push(CameraKeyframe::from_camera(camera, self.time));
+            self.next_keyframe += self.interval;
+        }
+        self.time += dt;
+    }
+
+    pub fn keyframe_count(&self) -> usize {
+        self.path.keyframes.len()
+    }
+
+    // Last keyframe is taken at the current camera state
+    pub fn finish(mut self, camera: &Camera) -> CameraPath {
+        self.path.push(CameraKeyframe::from_camera(camera, self.time));
+        self.path
+    }
+}
+
+// Drives camera along path. Time advances by fixed `timestep` per frame, independent of frame
+// time, so every run renders the same camera states.
+pub struct CameraPathPlayer {
+    path: CameraPath,
+    frame: u32,
+    timestep: f32,
+    pub looped: bool,
+}
+
+impl CameraPathPlayer {
+    pub fn new(path: CameraPath, timestep: f32) -> CameraPathPlayer {
+        assert!(!path.is_empty(), "Camera path is empty!");
+
+        CameraPathPlayer {
+            path,
+            frame: 0,
+            timestep,
+            looped: false,
+        }
+    }
+
+    pub fn path(&self) -> &CameraPath {
+        &self.path
+    }
+
+    // Frames played so far
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        (self.path.duration() / self.timestep).ceil() as u32 + 1
+    }
+
+    pub fn time(&self) -> f32 {
+        // from frame index: no accumulated rounding error
+        self.frame as f32 * self.timestep
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looped && self.frame >= self.frame_count()
+    }
+
+    // Moves camera to the next frame. Returns false when playback is finished.
+    pub fn update(&mut self, camera: &mut Camera) -> bool {
+        if self.looped && self.frame >= self.frame_count() {
+            self.frame = 0;
+        }
+        if self.is_finished() {
+            return false;
+        }
+
+        let keyframe = self.path.sample(self.time());
+        camera.set_position(keyframe.position);
+        camera.set_rotation(keyframe.yaw, keyframe.pitch);
+
+        self.frame += 1;
+        true
+    }
+}
+
+// Uniform Catmull-Rom segment between p1 and p2
+fn catmull_rom(p0: Point3<f32>, p1: Point3<f32>, p2: Point3<f32>, p3: Point3<f32>, t: f32) -> Point3<f32> {
+    let (p0, p1, p2, p3) = (p0.to_vec(), p1.to_vec(), p2.to_vec(), p3.to_vec());
+    let (t2, t3) = (t * t, t * t * t);
+
+    let result = (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5;
+
+    Point3::from_vec(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, x: f32, yaw: f32, pitch: f32) -> CameraKeyframe {
+        CameraKeyframe { time, position: Point3::new(x, -1.5, x * 0.5), yaw, pitch }
+    }
+
+    fn test_path() -> CameraPath {
+        let mut path = CameraPath::new();
+        path.push(keyframe(0.0, 0.0, -90.0, 0.0));
+        path.push(keyframe(1.0, 2.0, -45.0, 10.0));
+        path.push(keyframe(2.5, 3.0, 30.0, -20.0));
+        path.push(keyframe(4.0, 7.5, 120.0, 5.0));
+        path
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let path = test_path();
+        let file = std::env::temp_dir().join(format!("ash-test-camera-path-{}.txt", std::process::id()));
+
+        path.save(&file).expect("Failed to save camera path!");
+        let loaded = CameraPath::load(&file).expect("Failed to load camera path!");
+        fs::remove_file(&file).ok();
+
+        assert_eq!(loaded.keyframes(), path.keyframes());
+    }
+
+    #[test]
+    fn load_rejects_invalid_lines() {
+        let file = std::env::temp_dir().join(format!("ash-test-camera-path-invalid-{}.txt", std::process::id()));
+        for text in &["0 1 2 3 4\n", "0 1 2 3 4 x\n", "1 0 0 0 0 0\n0 0 0 0 0 0\n"] {
+            fs::write(&file, text).unwrap();
+            let error = CameraPath::load(&file).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{:?}", text);
+        }
+        fs::remove_file(&file).ok();
+    }
+
+    #[test]
+    fn sample_at_keyframes() {
+        let path = test_path();
+        for keyframe in path.keyframes() {
+            assert_eq!(path.sample(keyframe.time), *keyframe);
+        }
+    }
+
+    #[test]
+    fn sample_between_keyframes() {
+        let path = test_path();
+        let sample = path.sample(0.5);
+        assert!(sample.position.x > 0.0 && sample.position.x < 2.0);
+        assert!(sample.yaw > -90.0 && sample.yaw < -45.0);
+        assert!(sample.pitch > 0.0 && sample.pitch < 10.0);
+    }
+
+    #[test]
+    fn sample_is_clamped() {
+        let path = test_path();
+        let (first, last) = (path.keyframes()[0], path.keyframes()[3]);
+
+        assert_eq!(path.sample(-1.0), CameraKeyframe { time: -1.0, ..first });
+        assert_eq!(path.sample(4.5), CameraKeyframe { time: 4.5, ..last });
+        assert_eq!(path.sample(100.0), CameraKeyframe { time: 100.0, ..last });
+    }
+
+    #[test]
+    fn player_frame_count() {
+        // duration 4 s: frames at 0, 0.5, ..., 4
+        let player = CameraPathPlayer::new(test_path(), 0.5);
+        assert_eq!(player.frame_count(), 9);
+
+        // last frame is exactly at the end, not one past it
+        for &fps in &[24.0, 30.0, 60.0, 144.0] {
+            let player = CameraPathPlayer::new(test_path(), 1.0 / fps);
+            assert_eq!(player.frame_count(), (4.0 * fps) as u32 + 1, "fps={}", fps);
+        }
+
+        // duration is not a multiple of timestep: the end is covered by one more frame
+        let player = CameraPathPlayer::new(test_path(), 1.5);
+        assert_eq!(player.frame_count(), 4);
+    }
+
+    #[test]
+    fn player_finishes() {
+        let mut camera = Camera::new();
+        let mut player = CameraPathPlayer::new(test_path(), 0.5);
+
+        let mut frames = 0;
+        while player.update(&mut camera) {
+            assert!(player.time() <= 4.0 + 0.5);
+            frames += 1;
+        }
+        assert_eq!(frames, 9);
+        assert_eq!(player.frame(), 9);
+        assert!(player.is_finished());
+        assert!(!player.update(&mut camera));
+
+        // camera stays on the last keyframe
+        let last = test_path().keyframes()[3];
+        assert_eq!(camera.position(), last.position);
+        assert_eq!(camera.yaw(), last.yaw);
+        assert_eq!(camera.pitch(), last.pitch);
+    }
+
+    #[test]
+    fn looped_player_restarts() {
+        let mut camera = Camera::new();
+        let mut player = CameraPathPlayer::new(test_path(), 0.5);
+        player.looped = true;
+
+        for _ in 0..20 {
+            assert!(player.update(&mut camera));
+            assert!(!player.is_finished());
+        }
+        assert_eq!(player.frame(), 20 % 9);
+    }
+}
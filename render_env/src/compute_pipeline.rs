@@ -0,0 +1,98 @@
+use std::ptr;
+
+use ash::version::DeviceV1_0;
+use ash::vk;
+
+use crate::env::RenderEnv;
+use crate::pipeline_builder::name_pipeline_layouts;
+use crate::shader;
+use crate::shader::{DescriptorSetLayout, Shader};
+
+pub struct ComputePipeline {
+    pub device: ash::Device,
+    pub descriptor_set_layouts: Vec<DescriptorSetLayout>,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipeline: vk::Pipeline,
+}
+
+impl ComputePipeline {
+    pub fn new(device: ash::Device, shader: Shader) -> ComputePipeline {
+        let descriptor_set_layouts = shader::create_descriptor_set_layout(&device, vec![&shader]);
+
+        let layout_vec: Vec<_> = descriptor_set_layouts
+            .iter()
+            .map(|x| x.layout)
+            .collect();
+
+        let mut push_constant_ranges = Vec::new();
+        if shader.push_constants_range.size > 0 {
+            push_constant_ranges.push(shader.push_constants_range);
+        }
+
+        let pipeline_layout_create_info = vk::PipelineLayoutCreateInfo {
+            s_type: vk::StructureType::PIPELINE_LAYOUT_CREATE_INFO,
+            p_next: ptr::null(),
+            flags: vk::PipelineLayoutCreateFlags::empty(),
+            set_layout_count: layout_vec.len() as u32,
+            p_set_layouts: layout_vec.as_ptr(),
+            push_constant_range_count: push_constant_ranges.len() as u32,
+            p_push_constant_ranges: push_constant_ranges.as_ptr(),
+        };
+
+        let pipeline_layout = unsafe {
+            device
+                .create_pipeline_layout(&pipeline_layout_create_info, None)
+                .expect("Failed to create pipeline layout!")
+        };
+
+        let compute_pipeline_create_infos = [
+            vk::ComputePipelineCreateInfo {
+                s_type: vk::StructureType::COMPUTE_PIPELINE_CREATE_INFO,
+                p_next: ptr::null(),
+                flags: vk::PipelineCreateFlags::empty(),
+                stage: shader.stage(),
+                layout: pipeline_layout,
+                base_pipeline_handle: vk::Pipeline::null(),
+                base_pipeline_index: -1,
+            }
+        ];
+
+        let compute_pipelines = unsafe {
+            device
+                .create_compute_pipelines(
+                    vk::PipelineCache::null(),
+                    &compute_pipeline_create_infos,
+                    None,
+                )
+                .expect("Failed to create Compute Pipeline!")
+        };
+
+        ComputePipeline {
+            device,
+            descriptor_set_layouts,
+            pipeline_layout,
+            pipeline: compute_pipelines[0],
+        }
+    }
+
+    // Pipeline, layout and descriptor set layouts, see `PipelineBuilder::debug_name`
+    pub fn debug_name(self, env: &RenderEnv, name: &str) -> Self {
+        env.set_name(self.pipeline, name);
+        name_pipeline_layouts(env.debug_names(), name, self.pipeline_layout, &self.descriptor_set_layouts);
+        self
+    }
+}
+
+impl Drop for ComputePipeline {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_pipeline(self.pipeline, None);
+
+            for descriptor_set_layout in self.descriptor_set_layouts.iter() {
+                self.device.destroy_descriptor_set_layout(descriptor_set_layout.layout, None);
+            }
+
+            self.device.destroy_pipeline_layout(self.pipeline_layout, None);
+        }
+    }
+}
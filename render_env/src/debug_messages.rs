@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr};
+use std::fmt;
+use std::os::raw::c_char;
+use std::slice;
+use std::sync::Mutex;
+
+use ash::vk;
+use ash::vk::{DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMessageTypeFlagsEXT};
+
+// Log target of validation messages
+pub const LOG_TARGET: &str = "vulkan";
+
+#[derive(Clone, Debug)]
+pub struct DebugObject {
+    pub object_type: vk::ObjectType,
+    pub handle: u64,
+    // set with `RenderEnv::set_name`
+    pub name: Option<String>,
+}
+
+// Copy of the message passed to VK_EXT_debug_utils messenger callback
+#[derive(Clone, Debug)]
+pub struct DebugMessage {
+    pub severity: DebugUtilsMessageSeverityFlagsEXT,
+    pub message_type: DebugUtilsMessageTypeFlagsEXT,
+    // e.g. "VUID-vkCmdDraw-None-02699"
+    pub id_name: String,
+    pub id_number: i32,
+    pub message: String,
+    pub objects: Vec<DebugObject>,
+}
+
+impl DebugMessage {
+    pub fn is_validation_error(&self) -> bool {
+        self.severity.contains(DebugUtilsMessageSeverityFlagsEXT::ERROR)
+            && self.message_type.contains(DebugUtilsMessageTypeFlagsEXT::VALIDATION)
+    }
+
+    unsafe fn from_callback_data(severity: DebugUtilsMessageSeverityFlagsEXT, message_type: DebugUtilsMessageTypeFlagsEXT,
+                                 data: &vk::DebugUtilsMessengerCallbackDataEXT) -> DebugMessage {
+        let objects = if data.object_count > 0 && !data.p_objects.is_null() {
+            slice::from_raw_parts(data.p_objects, data.object_count as usize)
+        } else {
+            &[]
+        };
+
+        DebugMessage {
+            severity,
+            message_type,
+            id_name: c_string(data.p_message_id_name).unwrap_or_default(),
+            id_number: data.message_id_number,
+            message: c_string(data.p_message).unwrap_or_default(),
+            objects: objects
+                .iter()
+                .map(|object| DebugObject {
+                    object_type: object.object_type,
+                    handle: object.object_handle,
+                    name: c_string(object.p_object_name),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for DebugMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.id_name, self.message)?;
+
+        for object in self.objects.iter() {
+            write!(f, "\n    {:?} 0x{:x}", object.object_type, object.handle)?;
+            if let Some(name) = &object.name {
+                write!(f, " \"{}\"", name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Called for every message, repeats included
+pub type DebugCallback = Box<dyn Fn(&DebugMessage) + Send + Sync>;
+
+// Router setup applied before the messenger is installed, so messages of instance and device
+// creation are covered too
+#[derive(Default)]
+pub struct ValidationSettings {
+    // record validation errors, see `DebugMessageRouter::validation_errors`
+    pub strict: bool,
+    pub callback: Option<DebugCallback>,
+}
+
+#[derive(Default)]
+struct RouterState {
+    // times each message was seen, by id and text
+    seen: HashMap<(i32, String), u32>,
+    callback: Option<DebugCallback>,
+    strict: bool,
+    // strict mode: first occurrence of each validation error
+    errors: Vec<DebugMessage>,
+}
+
+// Receives messenger callbacks (the pointer is messenger user data): logs messages through `log`,
+// repeats of a message are logged only at 2, 4, 8, ... occurrences.
+pub struct DebugMessageRouter {
+    state: Mutex<RouterState>,
+}
+
+impl DebugMessageRouter {
+    pub fn new(settings: ValidationSettings) -> DebugMessageRouter {
+        DebugMessageRouter {
+            state: Mutex::new(RouterState {
+                strict: settings.strict,
+                callback: settings.callback,
+                ..RouterState::default()
+            }),
+        }
+    }
+
+    pub fn set_callback(&self, callback: Option<DebugCallback>) {
+        self.state.lock().unwrap().callback = callback;
+    }
+
+    // Strict mode records validation errors, see `validation_errors`
+    pub fn set_strict(&self, strict: bool) {
+        self.state.lock().unwrap().strict = strict;
+    }
+
+    pub fn validation_errors(&self) -> Vec<DebugMessage> {
+        self.state.lock().unwrap().errors.clone()
+    }
+
+    pub fn take_validation_errors(&self) -> Vec<DebugMessage> {
+        std::mem::take(&mut self.state.lock().unwrap().errors)
+    }
+
+    fn route(&self, message: DebugMessage) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(callback) = &state.callback {
+            callback(&message);
+        }
+
+        let count = {
+            let count = state.seen.entry((message.id_number, message.message.clone())).or_insert(0);
+            *count += 1;
+            *count
+        };
+
+        if count.is_power_of_two() {
+            let level = log_level(message.severity);
+            if count == 1 {
+                log::log!(target: LOG_TARGET, level, "{}", message);
+            } else {
+                log::log!(target: LOG_TARGET, level, "(repeated {} times) [{}]", count, message.id_name);
+            }
+        }
+
+        if state.strict && count == 1 && message.is_validation_error() {
+            state.errors.push(message);
+        }
+    }
+}
+
+fn log_level(severity: DebugUtilsMessageSeverityFlagsEXT) -> log::Level {
+    if severity.contains(DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+        log::Level::Error
+    } else if severity.contains(DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+        log::Level::Warn
+    } else if severity.contains(DebugUtilsMessageSeverityFlagsEXT::INFO) {
+        log::Level::Info
+    } else {
+        log::Level::Trace
+    }
+}
+
+unsafe fn c_string(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    }
+}
+
+// `p_user_data` is `*const DebugMessageRouter`
+pub(crate) unsafe extern "system" fn vulkan_debug_utils_callback(
+    message_severity: DebugUtilsMessageSeverityFlagsEXT,
+    message_type: DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    p_user_data: *mut c_void,
+) -> vk::Bool32 {
+    let message = DebugMessage::from_callback_data(message_severity, message_type, &*p_callback_data);
+    match (p_user_data as *const DebugMessageRouter).as_ref() {
+        Some(router) => router.route(message),
+        None => log::log!(target: LOG_TARGET, log_level(message_severity), "{}", message),
+    }
+
+    // never abort the call, strict mode only records errors
+    vk::FALSE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::{Arc, Once};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use log::{Log, Metadata, Record};
+
+    thread_local! {
+        // validation messages logged by the current test thread
+        static LOGGED: RefCell<Vec<String>> = RefCell::new(Vec::new());
+    }
+
+    struct CaptureLogger;
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.target() == LOG_TARGET
+        }
+
+        fn log(&self, record: &Record) {
+            if self.enabled(record.metadata()) {
+                LOGGED.with(|logged| logged.borrow_mut().push(record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger;
+    static INIT_LOGGER: Once = Once::new();
+
+    fn take_logged() -> Vec<String> {
+        INIT_LOGGER.call_once(|| {
+            log::set_logger(&LOGGER).expect("Failed to set test logger!");
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        LOGGED.with(|logged| std::mem::take(&mut *logged.borrow_mut()))
+    }
+
+    fn message(id_number: i32, text: &str, severity: DebugUtilsMessageSeverityFlagsEXT) -> DebugMessage {
+        DebugMessage {
+            severity,
+            message_type: DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+            id_name: format!("VUID-test-{}", id_number),
+            id_number,
+            message: text.to_string(),
+            objects: Vec::new(),
+        }
+    }
+
+    fn error(id_number: i32, text: &str) -> DebugMessage {
+        message(id_number, text, DebugUtilsMessageSeverityFlagsEXT::ERROR)
+    }
+
+    #[test]
+    fn repeats_logged_at_powers_of_two() {
+        take_logged();
+        let router = DebugMessageRouter::new(ValidationSettings::default());
+
+        for _ in 0..20 {
+            router.route(error(1, "same"));
+        }
+
+        let logged = take_logged();
+        // occurrences 1, 2, 4, 8, 16
+        assert_eq!(logged.len(), 5);
+        assert!(logged[0].contains("same"));
+        assert_eq!(logged[1], "(repeated 2 times) [VUID-test-1]");
+        assert_eq!(logged[4], "(repeated 16 times) [VUID-test-1]");
+    }
+
+    #[test]
+    fn repeats_counted_per_message() {
+        take_logged();
+        let router = DebugMessageRouter::new(ValidationSettings::default());
+
+        // same id with different text (e.g. other object) is another message
+        for _ in 0..3 {
+            router.route(error(1, "first"));
+            router.route(error(1, "second"));
+            router.route(error(2, "first"));
+        }
+
+        // 1 and 2 of each of three messages
+        assert_eq!(take_logged().len(), 6);
+    }
+
+    #[test]
+    fn strict_records_first_occurrence() {
+        take_logged();
+        let router = DebugMessageRouter::new(ValidationSettings { strict: true, callback: None });
+
+        router.route(error(1, "a"));
+        router.route(message(3, "warning", DebugUtilsMessageSeverityFlagsEXT::WARNING));
+        router.route(error(1, "a"));
+        router.route(error(2, "b"));
+        router.route(error(1, "a"));
+
+        let errors = router.validation_errors();
+        assert_eq!(errors.iter().map(|e| e.id_number).collect::<Vec<_>>(), vec![1, 2]);
+
+        // taken errors are not reported again, repeats stay deduplicated
+        assert_eq!(router.take_validation_errors().len(), 2);
+        router.route(error(1, "a"));
+        assert!(router.validation_errors().is_empty());
+        router.route(error(4, "c"));
+        assert_eq!(router.validation_errors().len(), 1);
+        take_logged();
+    }
+
+    #[test]
+    fn not_strict_records_nothing() {
+        take_logged();
+        let router = DebugMessageRouter::new(ValidationSettings::default());
+        router.route(error(1, "a"));
+        assert!(router.validation_errors().is_empty());
+
+        router.set_strict(true);
+        router.route(error(2, "b"));
+        assert_eq!(router.validation_errors().len(), 1);
+        take_logged();
+    }
+
+    #[test]
+    fn callback_gets_every_message() {
+        take_logged();
+        let count = Arc::new(AtomicUsize::new(0));
+        let callback_count = count.clone();
+        let router = DebugMessageRouter::new(ValidationSettings {
+            strict: false,
+            callback: Some(Box::new(move |_| {
+                callback_count.fetch_add(1, Ordering::SeqCst);
+            })),
+        });
+
+        for _ in 0..5 {
+            router.route(error(1, "a"));
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+
+        router.set_callback(None);
+        router.route(error(1, "a"));
+        assert_eq!(count.load(Ordering::SeqCst), 5);
+        take_logged();
+    }
+}
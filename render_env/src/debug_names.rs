@@ -0,0 +1,67 @@
+use std::ffi::CString;
+use std::ptr;
+
+use ash::extensions::ext::DebugUtils;
+use ash::vk;
+
+// Label color of `cmd_begin_label`, tools use it for the label bar only
+const LABEL_COLOR: [f32; 4] = [0.4, 0.6, 0.9, 1.0];
+
+// Object names and command buffer labels (VK_EXT_debug_utils): validation messages and frame
+// captures show them instead of raw handles. Cheap to clone, builders keep a copy until build.
+#[derive(Clone)]
+pub struct DebugNames {
+    loader: DebugUtils,
+    device: vk::Device,
+}
+
+impl DebugNames {
+    pub fn new(loader: DebugUtils, device: vk::Device) -> DebugNames {
+        DebugNames { loader, device }
+    }
+
+    pub fn set_name<H: vk::Handle>(&self, handle: H, name: &str) {
+        let name = CString::new(name).expect("Debug name contains nul byte!");
+        let name_info = vk::DebugUtilsObjectNameInfoEXT {
+            s_type: vk::StructureType::DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+            p_next: ptr::null(),
+            object_type: H::TYPE,
+            object_handle: handle.as_raw(),
+            p_object_name: name.as_ptr(),
+        };
+
+        unsafe {
+            if let Err(err) = self.loader.debug_utils_set_object_name(self.device, &name_info) {
+                log::warn!("Failed to set debug name {:?}: {}", name, err);
+            }
+        }
+    }
+
+    pub fn cmd_begin_label(&self, cmd_buf: vk::CommandBuffer, name: &str) {
+        let name = CString::new(name).expect("Debug label contains nul byte!");
+        let label = vk::DebugUtilsLabelEXT {
+            s_type: vk::StructureType::DEBUG_UTILS_LABEL_EXT,
+            p_next: ptr::null(),
+            p_label_name: name.as_ptr(),
+            color: LABEL_COLOR,
+        };
+
+        unsafe {
+            self.loader.cmd_begin_debug_utils_label(cmd_buf, &label);
+        }
+    }
+
+    pub fn cmd_end_label(&self, cmd_buf: vk::CommandBuffer) {
+        unsafe {
+            self.loader.cmd_end_debug_utils_label(cmd_buf);
+        }
+    }
+
+    // Commands recorded by `record` are grouped under `name`
+    pub fn cmd_label<R, F: FnOnce() -> R>(&self, cmd_buf: vk::CommandBuffer, name: &str, record: F) -> R {
+        self.cmd_begin_label(cmd_buf, name);
+        let result = record();
+        self.cmd_end_label(cmd_buf);
+        result
+    }
+}
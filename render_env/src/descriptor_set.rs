@@ -80,7 +80,7 @@ impl DescriptorSetBuilder {
     }
     pub fn add_buffer(&mut self, buffer: vk::Buffer) -> &mut Self {
         let desc = self.binding_desc.get(self.current_binding).unwrap();
-        if desc.descriptor_type != vk::DescriptorType::UNIFORM_BUFFER {
+        if ![vk::DescriptorType::UNIFORM_BUFFER, vk::DescriptorType::STORAGE_BUFFER].contains(&desc.descriptor_type) {
             panic!("Invalid value for descriptor {}: expected {:?}, found buffer", desc.binding, desc.descriptor_type);
         }
 
@@ -138,6 +138,26 @@ impl DescriptorSetBuilder {
         self
     }
 
+    pub fn add_storage_image(&mut self, image_view: vk::ImageView) -> &mut Self {
+        let desc = self.binding_desc.get(self.current_binding).
+            expect(&format!("Shaders don't contains descriptor with index {}. Need to recompile shader?", self.current_binding));
+
+        if desc.descriptor_type != vk::DescriptorType::STORAGE_IMAGE {
+            panic!("Invalid value for descriptor {}: expected {:?}, found storage image", desc.binding, desc.descriptor_type);
+        }
+
+        self.image_writes.push(
+            vk::DescriptorImageInfo {
+                sampler: vk::Sampler::null(),
+                image_view,
+                image_layout: vk::ImageLayout::GENERAL,
+            }
+        );
+
+        self.current_binding += 1;
+
+        self
+    }
 
     pub fn build(&self) -> DescriptorSet {
         let layouts = [self.layout];
@@ -175,12 +195,16 @@ impl DescriptorSetBuilder {
                 ..vk::WriteDescriptorSet::default()
             };
 
-            if binding.descriptor_type == vk::DescriptorType::COMBINED_IMAGE_SAMPLER {
+            if [
+                vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                vk::DescriptorType::SAMPLED_IMAGE,
+                vk::DescriptorType::STORAGE_IMAGE,
+            ].contains(&binding.descriptor_type) {
                 write_desc.p_image_info = self.image_writes.get(cur_img_idx).as_raw_ptr();
                 cur_img_idx += 1;
             }
 
-            if binding.descriptor_type == vk::DescriptorType::UNIFORM_BUFFER {
+            if [vk::DescriptorType::UNIFORM_BUFFER, vk::DescriptorType::STORAGE_BUFFER].contains(&binding.descriptor_type) {
                 write_desc.p_buffer_info = self.buffer_writes.get(cur_buf_idx).as_raw_ptr();
                 cur_buf_idx += 1;
             }
@@ -0,0 +1,17 @@
+use crate::camera_controller::CameraMode;
+
+// Returns true if mode was changed, controller should be recreated
+pub fn camera_mode(ui: &mut egui::Ui, mode: &mut CameraMode) -> bool {
+    let mut selected = *mode;
+    egui::ComboBox::from_label("Camera")
+        .selected_text(selected.name())
+        .show_ui(ui, |ui| {
+            for option in CameraMode::ALL.iter() {
+                ui.selectable_value(&mut selected, *option, option.name());
+            }
+        });
+
+    let changed = selected != *mode;
+    *mode = selected;
+    changed
+}
@@ -4,6 +4,7 @@ use ash::version::DeviceV1_0;
 use ash::vk;
 
 use crate::env::RenderEnv;
+use crate::utils::buffer_utils::create_buffer_;
 
 pub struct CpuBuffer {
     buffer_memory: vk::DeviceMemory,
@@ -13,52 +14,22 @@ pub struct CpuBuffer {
 }
 
 impl CpuBuffer {
-    pub fn from_vec<T>(env: &RenderEnv, usage: vk::BufferUsageFlags, data: &Vec<T>) -> CpuBuffer {
+    // Buffer is named `debug_name`, its memory "<debug_name>.memory"
+    pub fn from_vec<T>(env: &RenderEnv, usage: vk::BufferUsageFlags, data: &Vec<T>, debug_name: &str) -> CpuBuffer {
         let size = (data.len() * mem::size_of::<T>()) as u64;
 
-        let buffer_create_info = vk::BufferCreateInfo {
-            s_type: vk::StructureType::BUFFER_CREATE_INFO,
-            p_next: ptr::null(),
-            flags: vk::BufferCreateFlags::empty(),
+        let (buffer, buffer_memory, memory_size) = create_buffer_(
+            env.device(),
             size,
             usage,
-            sharing_mode: vk::SharingMode::EXCLUSIVE,
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
-        };
-
-        let buffer = unsafe {
-            env.device()
-                .create_buffer(&buffer_create_info, None)
-                .expect("Failed to create Buffer")
-        };
-
-        let mem_requirements = unsafe { env.device().get_buffer_memory_requirements(buffer) };
-        let memory_type = env.find_memory_type(
-            mem_requirements.memory_type_bits,
             vk::MemoryPropertyFlags::HOST_VISIBLE,
+            &env.mem_properties,
+            Some((env.debug_names(), debug_name)),
         );
 
-        let allocate_info = vk::MemoryAllocateInfo {
-            s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-            p_next: ptr::null(),
-            allocation_size: mem_requirements.size,
-            memory_type_index: memory_type,
-        };
-
-        let buffer_memory = unsafe {
-            env.device()
-                .allocate_memory(&allocate_info, None)
-                .expect("Failed to allocate buffer memory!")
-        };
-
-        unsafe {
-            env.device().bind_buffer_memory(buffer, buffer_memory, 0).unwrap();
-        }
-
         unsafe {
             let mem = env.device()
-                .map_memory(buffer_memory, 0, mem_requirements.size, vk::MemoryMapFlags::empty())
+                .map_memory(buffer_memory, 0, memory_size, vk::MemoryMapFlags::empty())
                 .unwrap() as *mut T;
             mem.copy_from_nonoverlapping(data.as_ptr(), data.len());
 
@@ -68,7 +39,7 @@ impl CpuBuffer {
                     p_next: ptr::null(),
                     memory: buffer_memory,
                     offset: 0,
-                    size: mem_requirements.size,
+                    size: memory_size,
                 }
             ]).unwrap();
 
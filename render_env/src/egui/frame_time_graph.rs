@@ -0,0 +1,68 @@
+use egui::{Color32, Pos2, Sense, Shape, Stroke};
+
+use crate::frame_pacer::{FramePacer, FrameTimeStats, HISTORY_SIZE};
+
+// FPS limit, frame time statistics and graph
+pub fn frame_pacer_panel(ui: &mut egui::Ui, frame_pacer: &mut FramePacer) {
+    let mut limit = frame_pacer.target_fps().is_some();
+    let mut target_fps = frame_pacer.target_fps().unwrap_or(60.0);
+    ui.checkbox(&mut limit, "Limit FPS");
+    if limit {
+        ui.add(egui::Slider::new(&mut target_fps, 10.0..=240.0).text("Target FPS"));
+    }
+    let target_fps = if limit { Some(target_fps) } else { None };
+    if target_fps != frame_pacer.target_fps() {
+        frame_pacer.set_target_fps(target_fps);
+    }
+
+    frame_time_graph(ui, frame_pacer.stats());
+    if ui.button("Reset statistics").clicked() {
+        frame_pacer.reset_stats();
+    }
+}
+
+// Frame times of the whole history, newest on the right. Reference lines at 60 and 30 FPS.
+pub fn frame_time_graph(ui: &mut egui::Ui, stats: &FrameTimeStats) {
+    let summary = stats.summary();
+
+    ui.label(format!("Frame time: avg {:.2} ms, min {:.2} ms, max {:.2} ms", summary.avg, summary.min, summary.max));
+    ui.label(format!("FPS: avg {:.1}, 1% low {:.1}, 0.1% low {:.1}",
+                     summary.avg_fps(), summary.low_1_fps(), summary.low_01_fps()));
+
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width().max(200.0), 80.0), Sense::hover());
+    let painter = ui.painter();
+    painter.rect_filled(rect, 0.0, Color32::from_gray(20));
+
+    // scale follows worst frame, but never less than 60 FPS frame
+    let max_ms = summary.max.max(1000.0 / 60.0) * 1.1;
+    let to_y = |ms: f32| rect.bottom() - (ms / max_ms).min(1.0) * rect.height();
+
+    for (fps, color) in [(60.0, Color32::from_rgb(40, 120, 40)), (30.0, Color32::from_rgb(140, 110, 30))].iter() {
+        let ms = 1000.0 / fps;
+        if ms < max_ms {
+            painter.line_segment(
+                [Pos2::new(rect.left(), to_y(ms)), Pos2::new(rect.right(), to_y(ms))],
+                Stroke::new(1.0, *color),
+            );
+        }
+    }
+
+    let step = rect.width() / (HISTORY_SIZE - 1) as f32;
+    let start_x = rect.right() - (stats.len().max(1) - 1) as f32 * step;
+    let points: Vec<Pos2> = stats.samples()
+        .enumerate()
+        .map(|(i, ms)| Pos2::new(start_x + i as f32 * step, to_y(*ms)))
+        .collect();
+
+    if points.len() > 1 {
+        painter.add(Shape::line(points, Stroke::new(1.0, Color32::from_gray(220))));
+    }
+
+    painter.text(
+        rect.left_top() + egui::vec2(2.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{:.1} ms", max_ms),
+        egui::TextStyle::Small,
+        Color32::from_gray(160),
+    );
+}
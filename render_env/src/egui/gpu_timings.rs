@@ -0,0 +1,53 @@
+use std::path::Path;
+
+use egui::{Color32, Sense};
+
+use crate::gpu_profiler::{GpuProfiler, GpuScopeTiming};
+
+// Profiler switch, timings of the last frame and Chrome trace capture of `trace_frames` frames
+pub fn gpu_profiler_panel(ui: &mut egui::Ui, profiler: &mut GpuProfiler, trace_file: &Path, trace_frames: u32) {
+    if !profiler.is_supported() {
+        ui.label("Timestamps are not supported");
+        return;
+    }
+
+    ui.checkbox(&mut profiler.enabled, "Enabled");
+    gpu_timings(ui, profiler.results());
+
+    ui.horizontal(|ui| {
+        if profiler.is_capturing_trace() {
+            ui.label("Capturing trace...");
+        } else if ui.button("Capture trace").clicked() {
+            profiler.capture_trace(trace_file, trace_frames);
+        }
+        ui.label(trace_file.display().to_string());
+    });
+}
+
+// Scope tree with durations, bar length is relative to the longest top level scope
+pub fn gpu_timings(ui: &mut egui::Ui, timings: &[GpuScopeTiming]) {
+    if timings.is_empty() {
+        ui.label("No GPU timings");
+        return;
+    }
+
+    let max_ms = timings.iter()
+        .filter(|timing| timing.depth == 0)
+        .map(|timing| timing.duration_ms)
+        .fold(0.0_f32, f32::max)
+        .max(0.001);
+
+    for timing in timings.iter() {
+        ui.horizontal(|ui| {
+            ui.add_space(timing.depth as f32 * 12.0);
+
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(60.0, 10.0), Sense::hover());
+            let mut bar = rect;
+            bar.set_width(rect.width() * (timing.duration_ms / max_ms).min(1.0));
+            ui.painter().rect_filled(rect, 0.0, Color32::from_gray(20));
+            ui.painter().rect_filled(bar, 0.0, Color32::from_rgb(70, 130, 180));
+
+            ui.label(format!("{}: {:.3} ms", timing.name, timing.duration_ms));
+        });
+    }
+}
@@ -0,0 +1,18 @@
+use winit::event::MouseButton;
+
+use crate::input::{InputSettings, KeyBindings};
+
+pub fn input_settings(ui: &mut egui::Ui, settings: &mut InputSettings) {
+    ui.add(egui::Slider::new(&mut settings.mouse_sensitivity, 0.02..=0.5).text("Mouse sensitivity"));
+    ui.checkbox(&mut settings.invert_y, "Invert Y");
+    ui.horizontal(|ui| {
+        ui.label("Movement keys:");
+        ui.radio_value(&mut settings.bindings, KeyBindings::default(), "WASD");
+        ui.radio_value(&mut settings.bindings, KeyBindings::arrows(), "Arrows");
+    });
+    ui.horizontal(|ui| {
+        ui.label("Look button:");
+        ui.radio_value(&mut settings.bindings.look, MouseButton::Left, "Left");
+        ui.radio_value(&mut settings.bindings.look, MouseButton::Right, "Right");
+    });
+}
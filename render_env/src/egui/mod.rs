@@ -5,13 +5,23 @@ use ash::vk;
 use egui::math::vec2;
 use winit::event::WindowEvent;
 
+pub use camera_mode::camera_mode;
+pub use frame_time_graph::{frame_pacer_panel, frame_time_graph};
+pub use gpu_timings::{gpu_profiler_panel, gpu_timings};
+pub use input_settings::input_settings;
+pub use projection_editor::projection_editor;
 pub use winit_input::egui_to_winit_cursor_icon;
 
 use crate::egui::renderer::EguiRenderer;
 use crate::egui::winit_input::WinitInput;
 use crate::env::RenderEnv;
 
+mod camera_mode;
 mod cpu_buffer;
+mod frame_time_graph;
+mod gpu_timings;
+mod input_settings;
+mod projection_editor;
 mod winit_input;
 mod renderer;
 
@@ -114,4 +124,8 @@ impl Egui {
     pub fn register_texture_layout(&mut self, id: u64, texture: vk::ImageView, layout: vk::ImageLayout) {
         self.renderer.register_texture_layout(id, texture, layout);
     }
+
+    pub fn unregister_texture(&mut self, id: u64) {
+        self.renderer.unregister_texture(id);
+    }
 }
@@ -0,0 +1,19 @@
+use crate::projection::{Projection, ProjectionKind};
+
+pub fn projection_editor(ui: &mut egui::Ui, projection: &mut Projection) {
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut projection.kind, ProjectionKind::Perspective, "Perspective");
+        ui.radio_value(&mut projection.kind, ProjectionKind::Orthographic, "Orthographic");
+    });
+    match projection.kind {
+        ProjectionKind::Perspective => {
+            ui.add(egui::Slider::new(&mut projection.fov_y, 20.0..=120.0).text("FOV"));
+            ui.checkbox(&mut projection.infinite_far, "Infinite far plane");
+        }
+        ProjectionKind::Orthographic => {
+            ui.add(egui::Slider::new(&mut projection.ortho_height, 1.0..=50.0).text("Height"));
+        }
+    }
+    ui.add(egui::Slider::new(&mut projection.far_clip, 5.0..=200.0).text("Far / shadow distance"));
+    ui.checkbox(&mut projection.reverse_z, "Reverse-Z");
+}
@@ -114,6 +114,7 @@ impl EguiRenderer {
                 .create_sampler(&sampler_create_info, None)
                 .expect("Failed to create Sampler!")
         };
+        env.set_name(sampler, "egui.sampler");
 
         let render_pass = create_render_pass(env.device(), output_format);
 
@@ -127,6 +128,7 @@ impl EguiRenderer {
                 .fragment_shader(ps)
                 .disable_culling()
                 .blend()
+                .debug_name(&env, "egui")
                 .build()
         };
 
@@ -141,6 +143,7 @@ impl EguiRenderer {
                 .fragment_shader(ps)
                 .disable_culling()
                 .blend()
+                .debug_name(&env, "egui_msaa")
                 .build()
         };
 
@@ -183,8 +186,8 @@ impl EguiRenderer {
             indices.extend(&mesh.1.indices);
         }
 
-        let vb = CpuBuffer::from_vec(&self.env, vk::BufferUsageFlags::VERTEX_BUFFER, &vertices);
-        let ib = CpuBuffer::from_vec(&self.env, vk::BufferUsageFlags::INDEX_BUFFER, &indices);
+        let vb = CpuBuffer::from_vec(&self.env, vk::BufferUsageFlags::VERTEX_BUFFER, &vertices, "egui.vertices");
+        let ib = CpuBuffer::from_vec(&self.env, vk::BufferUsageFlags::INDEX_BUFFER, &indices, "egui.indices");
 
         let cmd_buf = self.env.create_secondary_command_buffer();
         let device = self.env.device().clone();
@@ -334,6 +337,7 @@ impl EguiRenderer {
             font_tx.width as u32,
             font_tx.height as u32,
             false,
+            Some((env.debug_names(), "egui.font")),
         );
 
         FontTexture(texture, font_tx.version)
@@ -370,6 +374,10 @@ impl EguiRenderer {
             multisampled: false,
         });
     }
+
+    pub fn unregister_texture(&mut self, id: u64) {
+        self.user_textures_descriptors.remove(&id);
+    }
 }
 
 impl Drop for EguiRenderer {
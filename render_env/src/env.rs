@@ -7,6 +7,8 @@ use ash::vk::{ApplicationInfo, DebugUtilsMessageSeverityFlagsEXT, DebugUtilsMess
 use winit::window::Window;
 
 use super::platforms;
+use crate::debug_messages::{DebugCallback, DebugMessage, DebugMessageRouter, ValidationSettings, vulkan_debug_utils_callback};
+use crate::debug_names::DebugNames;
 
 #[allow(dead_code)]
 pub struct RenderEnv {
@@ -16,6 +18,7 @@ pub struct RenderEnv {
     pub(super) physical_device: vk::PhysicalDevice,
     device: ash::Device,
     queue: vk::Queue,
+    queue_family_index: u32,
 
     pub(super) command_pool: vk::CommandPool,
 
@@ -29,37 +32,24 @@ pub struct RenderEnv {
     // debug
     debug_utils_loader: ash::extensions::ext::DebugUtils,
     debug_messenger: vk::DebugUtilsMessengerEXT,
+    debug_names: DebugNames,
+    // messenger user data, boxed so the pointer stays valid when env is moved
+    debug_message_router: Box<DebugMessageRouter>,
 }
 
-unsafe extern "system" fn vulkan_debug_utils_callback(
-    message_severity: DebugUtilsMessageSeverityFlagsEXT,
-    message_type: DebugUtilsMessageTypeFlagsEXT,
-    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _p_user_data: *mut c_void,
-) -> vk::Bool32 {
-    let severity = match message_severity {
-        DebugUtilsMessageSeverityFlagsEXT::VERBOSE => "[Verbose]",
-        DebugUtilsMessageSeverityFlagsEXT::WARNING => "[Warning]",
-        DebugUtilsMessageSeverityFlagsEXT::ERROR => "[Error]",
-        DebugUtilsMessageSeverityFlagsEXT::INFO => "[Info]",
-        _ => "[Unknown]",
-    };
-    let types = match message_type {
-        DebugUtilsMessageTypeFlagsEXT::GENERAL => "[General]",
-        DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => "[Performance]",
-        DebugUtilsMessageTypeFlagsEXT::VALIDATION => "[Validation]",
-        _ => "[Unknown]",
-    };
-    let message = CStr::from_ptr((*p_callback_data).p_message);
-    println!("[Debug]{}{}{:?}", severity, types, message);
-
-    vk::FALSE
-}
-
-
 #[allow(dead_code)]
 impl RenderEnv {
     pub fn new(window: &Window) -> RenderEnv {
+        RenderEnv::create(Some(window), ValidationSettings::default())
+    }
+
+    // No surface and swapchain: rendering goes to offscreen images only (tests, capture).
+    // Software ICD can be selected with VK_ICD_FILENAMES.
+    pub fn headless(validation: ValidationSettings) -> RenderEnv {
+        RenderEnv::create(None, validation)
+    }
+
+    pub fn create(window: Option<&Window>, validation: ValidationSettings) -> RenderEnv {
         unsafe {
             let app_name = CString::new("test").unwrap();
             let engine_name = CString::new("Vulkan Engine").unwrap();
@@ -71,7 +61,25 @@ impl RenderEnv {
                 .engine_version(0)
                 .api_version(vk::make_version(1, 0, 0));
 
-            let extension_names = platforms::required_extension_names();
+            let entry = ash::Entry::new().unwrap();
+
+            let mut extension_names = match window {
+                Some(_) => platforms::required_extension_names(),
+                None => vec![ash::extensions::ext::DebugUtils::name().as_ptr()],
+            };
+            // extended color spaces (scRGB, HDR10) for HDR swapchain, optional
+            let available_extensions = entry
+                .enumerate_instance_extension_properties()
+                .expect("Failed to enumerate instance extensions!");
+            let colorspace_extension = CStr::from_bytes_with_nul(b"VK_EXT_swapchain_colorspace\0").unwrap();
+            let has_colorspace_extension = available_extensions
+                .iter()
+                .any(|ext| CStr::from_ptr(ext.extension_name.as_ptr()) == colorspace_extension);
+            if window.is_some() && has_colorspace_extension {
+                extension_names.push(colorspace_extension.as_ptr());
+            }
+
+            let debug_message_router = Box::new(DebugMessageRouter::new(validation));
 
             let mut debug_utils_create_info = DebugUtilsMessengerCreateInfoEXT {
                 s_type: vk::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
@@ -85,7 +93,7 @@ impl RenderEnv {
                     | DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
                     | DebugUtilsMessageTypeFlagsEXT::VALIDATION,
                 pfn_user_callback: Some(vulkan_debug_utils_callback),
-                p_user_data: ptr::null_mut(),
+                p_user_data: &*debug_message_router as *const DebugMessageRouter as *mut c_void,
             };
 
             let debug_layers = vec![CStr::from_bytes_with_nul(b"VK_LAYER_KHRONOS_validation\0")
@@ -98,7 +106,6 @@ impl RenderEnv {
                 .push_next(&mut debug_utils_create_info)
                 .enabled_layer_names(debug_layers.as_slice());
 
-            let entry = ash::Entry::new().unwrap();
             let instance: ash::Instance = entry
                 .create_instance(&create_info, None)
                 .expect("Failed to create instance!");
@@ -111,7 +118,10 @@ impl RenderEnv {
                 .create_debug_utils_messenger(&debug_utils_create_info, None)
                 .expect("Debug Utils Callback");
 
-            let surface = platforms::create_surface(&entry, &instance, &window).unwrap();
+            let surface = match window {
+                Some(window) => platforms::create_surface(&entry, &instance, window).unwrap(),
+                None => vk::SurfaceKHR::null(),
+            };
             let pdevices = instance.enumerate_physical_devices().unwrap();
             let (physical_device, queue_family_index) = pdevices
                 .iter()
@@ -123,13 +133,13 @@ impl RenderEnv {
                         .filter_map(|(index, ref info)| {
                             let supports_graphic_and_surface =
                                 info.queue_flags.contains(vk::QueueFlags::GRAPHICS)
-                                    && surface_loader
+                                    && (surface == vk::SurfaceKHR::null() || surface_loader
                                     .get_physical_device_surface_support(
                                         *pdevice,
                                         index as u32,
                                         surface,
                                     )
-                                    .unwrap();
+                                    .unwrap());
 
                             if supports_graphic_and_surface {
                                 Some((*pdevice, index))
@@ -150,13 +160,14 @@ impl RenderEnv {
             let queue_priorities = [1.0_f32];
             let queue_ci = vec!(
                 vk::DeviceQueueCreateInfo::builder()
-                    .queue_family_index(0)
+                    .queue_family_index(queue_family_index)
                     .queue_priorities(&queue_priorities).build()
             );
 
-            let enable_extension_names = [
-                ash::extensions::khr::Swapchain::name().as_ptr(), // currently just enable the Swapchain extension.
-            ];
+            let mut enable_extension_names = vec![];
+            if window.is_some() {
+                enable_extension_names.push(ash::extensions::khr::Swapchain::name().as_ptr()); // currently just enable the Swapchain extension.
+            }
             let physical_device_features = vk::PhysicalDeviceFeatures {
                 sampler_anisotropy: vk::TRUE, // enable anisotropy device feature from Chapter-24.
                 sample_rate_shading: vk::TRUE,
@@ -183,6 +194,10 @@ impl RenderEnv {
                 .create_command_pool(&command_pool_create_info, None)
                 .expect("Failed to create Command Pool!");
 
+            let debug_names = DebugNames::new(debug_utils_loader.clone(), device.handle());
+            debug_names.set_name(queue, "env.queue");
+            debug_names.set_name(command_pool, "env.command_pool");
+
             RenderEnv {
                 entry,
                 instance,
@@ -194,11 +209,14 @@ impl RenderEnv {
                 device,
                 mem_properties,
                 queue,
+                queue_family_index,
 
                 command_pool,
 
                 debug_utils_loader,
                 debug_messenger,
+                debug_names,
+                debug_message_router,
             }
         }
     }
@@ -257,6 +275,10 @@ impl RenderEnv {
         self.physical_device
     }
 
+    pub fn is_headless(&self) -> bool {
+        self.surface == vk::SurfaceKHR::null()
+    }
+
     #[inline]
     pub fn surface(&self) -> vk::SurfaceKHR {
         self.surface.clone()
@@ -269,6 +291,51 @@ impl RenderEnv {
     pub fn queue(&self) -> vk::Queue {
         self.queue.clone()
     }
+
+    pub fn queue_family_index(&self) -> u32 {
+        self.queue_family_index
+    }
+
+    pub fn debug_names(&self) -> &DebugNames {
+        &self.debug_names
+    }
+
+    // Name shown by validation layers and capture tools, e.g. "gbuffer.normal"
+    pub fn set_name<H: vk::Handle>(&self, handle: H, name: &str) {
+        self.debug_names.set_name(handle, name);
+    }
+
+    pub fn cmd_begin_label(&self, cmd_buf: vk::CommandBuffer, name: &str) {
+        self.debug_names.cmd_begin_label(cmd_buf, name);
+    }
+
+    pub fn cmd_end_label(&self, cmd_buf: vk::CommandBuffer) {
+        self.debug_names.cmd_end_label(cmd_buf);
+    }
+
+    // Installed callback gets every validation message. It runs under router lock and must not
+    // call debug message methods of env.
+    pub fn set_debug_callback(&self, callback: Option<DebugCallback>) {
+        self.debug_message_router.set_callback(callback);
+    }
+
+    // Strict validation records validation errors, so tests can check there are none. Errors of
+    // env creation are recorded only when enabled by `ValidationSettings` at creation.
+    pub fn set_strict_validation(&self, strict: bool) {
+        self.debug_message_router.set_strict(strict);
+    }
+
+    pub fn validation_errors(&self) -> Vec<DebugMessage> {
+        self.debug_message_router.validation_errors()
+    }
+
+    pub fn take_validation_errors(&self) -> Vec<DebugMessage> {
+        self.debug_message_router.take_validation_errors()
+    }
+
+    pub fn cmd_label<R, F: FnOnce() -> R>(&self, cmd_buf: vk::CommandBuffer, name: &str, record: F) -> R {
+        self.debug_names.cmd_label(cmd_buf, name, record)
+    }
 }
 
 impl Drop for RenderEnv {
@@ -280,7 +347,9 @@ impl Drop for RenderEnv {
                 .destroy_debug_utils_messenger(self.debug_messenger, None);
 
             self.device.destroy_device(None);
-            self.surface_loader.destroy_surface(self.surface, None);
+            if self.surface != vk::SurfaceKHR::null() {
+                self.surface_loader.destroy_surface(self.surface, None);
+            }
 
             self.instance.destroy_instance(None);
         }
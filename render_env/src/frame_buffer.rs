@@ -11,8 +11,12 @@ use crate::utils::format_has_depth;
 
 #[derive(Clone)]
 pub struct AttachmentDesciption {
+    // debug name of the image, "<framebuffer name>.<name>"
+    pub name: &'static str,
     pub format: vk::Format,
     pub samples_count: vk::SampleCountFlags,
+    // only level 0 is rendered, others are left for post-processing (downsample chains etc.)
+    pub mip_levels: u32,
 }
 
 pub struct Framebuffer {
@@ -22,6 +26,7 @@ pub struct Framebuffer {
     pub framebuffer: Option<vk::Framebuffer>,
     pub attachments: Vec<AttachmentImage>,
     dimensions: [u32; 2],
+    debug_name: Option<String>,
 
     env: Arc<env::RenderEnv>,
 }
@@ -37,9 +42,17 @@ impl Framebuffer {
             framebuffer: None,
             attachments: vec![],
             dimensions: [0, 0],
+            debug_name: None,
         }
     }
 
+    // Names render pass now, attachments and framebuffer on every `resize_swapchain`
+    pub fn debug_name(mut self, name: &str) -> Self {
+        self.env.set_name(self.render_pass, &format!("{}.render_pass", name));
+        self.debug_name = Some(name.to_string());
+        self
+    }
+
     fn _create_render_pass(
         device: &ash::Device,
         descriptions: &Vec<AttachmentDesciption>,
@@ -51,11 +64,7 @@ impl Framebuffer {
         let mut depth_attachment_ref: Vec<vk::AttachmentReference> = vec![];
 
         for (attachment_idx, attachment_info) in descriptions.iter().enumerate() {
-            let final_layout = if format_has_depth(attachment_info.format) {
-                vk::ImageLayout::DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL
-            } else {
-                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
-            };
+            let final_layout = attachment_final_layout(attachment_info.format);
 
             attachments.push(vk::AttachmentDescription {
                 flags: Default::default(),
@@ -94,7 +103,11 @@ impl Framebuffer {
                 color_attachment_count: color_attachments_refs.len() as u32,
                 p_color_attachments: color_attachments_refs.as_ptr(),
                 p_resolve_attachments: ptr::null(),
-                p_depth_stencil_attachment: depth_attachment_ref.as_ptr(),
+                p_depth_stencil_attachment: if depth_attachment_ref.is_empty() {
+                    ptr::null()
+                } else {
+                    depth_attachment_ref.as_ptr()
+                },
                 preserve_attachment_count: 0,
                 p_preserve_attachments: ptr::null(),
             }
@@ -110,14 +123,15 @@ impl Framebuffer {
                 dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
                 dependency_flags: vk::DependencyFlags::BY_REGION,
             },
+            // attachments are sampled by the next pass (compose, post-process)
             vk::SubpassDependency {
                 src_subpass: 0,
                 dst_subpass: vk::SUBPASS_EXTERNAL,
                 src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-                dst_stage_mask: vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                dst_stage_mask: vk::PipelineStageFlags::FRAGMENT_SHADER | vk::PipelineStageFlags::COMPUTE_SHADER,
                 src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-                dst_access_mask: vk::AccessFlags::MEMORY_READ,
-                dependency_flags: vk::DependencyFlags::BY_REGION,
+                dst_access_mask: vk::AccessFlags::SHADER_READ,
+                dependency_flags: vk::DependencyFlags::empty(),
             }
         );
 
@@ -150,7 +164,8 @@ impl Framebuffer {
         let mut views = vec!();
 
         for desc in self.attachment_desc.iter() {
-            let mut usage = vk::ImageUsageFlags::INPUT_ATTACHMENT | vk::ImageUsageFlags::SAMPLED;
+            // TRANSFER_SRC: readback for screenshots, see `texture_utils::read_image`
+            let mut usage = vk::ImageUsageFlags::INPUT_ATTACHMENT | vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC;
 
             if format_has_depth(desc.format) {
                 usage |= vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT;
@@ -162,12 +177,16 @@ impl Framebuffer {
                 &self.env,
                 dimensions,
                 desc.format,
-                1,
+                desc.mip_levels,
                 desc.samples_count,
                 usage,
             );
 
-            views.push(img.view);
+            if let Some(name) = &self.debug_name {
+                img.set_debug_name(&self.env, &format!("{}.{}", name, desc.name));
+            }
+
+            views.push(img.mip_view(0));
             images.push(img);
         }
         self.attachments = images;
@@ -188,6 +207,10 @@ impl Framebuffer {
             self.env.device().create_framebuffer(&framebuffer_info, None).unwrap()
         };
 
+        if let Some(name) = &self.debug_name {
+            self.env.set_name(framebuffer, &format!("{}.framebuffer", name));
+        }
+
         self.framebuffer = Some(framebuffer);
         self.dimensions = dimensions;
     }
@@ -206,4 +229,25 @@ impl Framebuffer {
     pub fn render_pass(&self) -> vk::RenderPass {
         self.render_pass
     }
+
+    pub fn attachment_desc(&self) -> &[AttachmentDesciption] {
+        &self.attachment_desc
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    // Layout of attachment after render pass
+    pub fn attachment_layout(&self, index: usize) -> vk::ImageLayout {
+        attachment_final_layout(self.attachment_desc[index].format)
+    }
+}
+
+fn attachment_final_layout(format: vk::Format) -> vk::ImageLayout {
+    if format_has_depth(format) {
+        vk::ImageLayout::DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL
+    } else {
+        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL
+    }
 }
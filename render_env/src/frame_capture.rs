@@ -0,0 +1,70 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Where captured frames go
+pub enum FrameSink {
+    // "<dir>/<prefix>_00000.png", ...
+    PngSequence { dir: PathBuf, prefix: String },
+    // RGBA8 rows top to bottom, frame after frame. Matches `ffmpeg -f rawvideo -pix_fmt rgba -s WxH -i -`
+    Raw(Box<dyn Write>),
+}
+
+// Writes frames of offline capture (fixed timestep rendering, e.g. camera path playback).
+// Frame size must stay the same during capture.
+pub struct FrameCapture {
+    sink: FrameSink,
+    frame_count: u32,
+    size: Option<(u32, u32)>,
+}
+
+impl FrameCapture {
+    pub fn png_sequence(dir: &Path, prefix: &str) -> io::Result<FrameCapture> {
+        std::fs::create_dir_all(dir)?;
+        Ok(FrameCapture::new(FrameSink::PngSequence { dir: dir.to_path_buf(), prefix: prefix.to_string() }))
+    }
+
+    pub fn raw(writer: Box<dyn Write>) -> FrameCapture {
+        FrameCapture::new(FrameSink::Raw(writer))
+    }
+
+    pub fn new(sink: FrameSink) -> FrameCapture {
+        FrameCapture {
+            sink,
+            frame_count: 0,
+            size: None,
+        }
+    }
+
+    // Frames written so far
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    pub fn write_frame(&mut self, frame: &image::RgbaImage) -> io::Result<()> {
+        let size = *self.size.get_or_insert(frame.dimensions());
+        if size != frame.dimensions() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      format!("Frame size {:?} differs from capture size {:?}", frame.dimensions(), size)));
+        }
+
+        match &mut self.sink {
+            FrameSink::PngSequence { dir, prefix } => {
+                let path = dir.join(format!("{}_{:05}.png", prefix, self.frame_count));
+                frame.save(&path).map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+            }
+            FrameSink::Raw(writer) => writer.write_all(frame.as_raw())?,
+        }
+
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // Flushes raw sink, the writer is dropped (pipe is closed)
+    pub fn finish(mut self) -> io::Result<u32> {
+        if let FrameSink::Raw(writer) = &mut self.sink {
+            writer.flush()?;
+        }
+
+        Ok(self.frame_count)
+    }
+}
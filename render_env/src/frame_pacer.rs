@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Frames kept in frame time history
+pub const HISTORY_SIZE: usize = 1000;
+
+// Last part of the wait is spent spinning: sleep wakes up late by up to a scheduler tick
+const SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+// Rolling frame time history, milliseconds
+pub struct FrameTimeStats {
+    samples: VecDeque<f32>,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrameTimeSummary {
+    pub min: f32,
+    pub max: f32,
+    pub avg: f32,
+    // average frame time of the slowest 1% / 0.1% frames
+    pub low_1: f32,
+    pub low_01: f32,
+}
+
+impl FrameTimeSummary {
+    pub fn avg_fps(&self) -> f32 {
+        1000.0 / self.avg
+    }
+
+    pub fn low_1_fps(&self) -> f32 {
+        1000.0 / self.low_1
+    }
+
+    pub fn low_01_fps(&self) -> f32 {
+        1000.0 / self.low_01
+    }
+}
+
+impl FrameTimeStats {
+    pub fn new() -> FrameTimeStats {
+        FrameTimeStats {
+            samples: VecDeque::with_capacity(HISTORY_SIZE),
+        }
+    }
+
+    pub fn push(&mut self, frame_time_ms: f32) {
+        if self.samples.len() == HISTORY_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(frame_time_ms);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    // Oldest first
+    pub fn samples(&self) -> impl Iterator<Item=&f32> {
+        self.samples.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn last(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+
+    pub fn summary(&self) -> FrameTimeSummary {
+        if self.samples.is_empty() {
+            return FrameTimeSummary::default();
+        }
+
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        // slowest first
+        sorted.sort_by(|a, b| b.partial_cmp(a).unwrap());
+
+        // at least one frame, so lows of short history are the worst frame
+        let slowest_avg = |fraction: f32| {
+            let count = ((sorted.len() as f32 * fraction).ceil() as usize).max(1);
+            sorted[..count].iter().sum::<f32>() / count as f32
+        };
+
+        FrameTimeSummary {
+            min: sorted[sorted.len() - 1],
+            max: sorted[0],
+            avg: self.average(),
+            low_1: slowest_avg(0.01),
+            low_01: slowest_avg(0.001),
+        }
+    }
+}
+
+// Measures frame times and optionally holds frame rate at target: sleeps until shortly before
+// the next frame deadline, then spins. Deadlines advance by frame period (no drift), and are
+// reset after a frame longer than one period.
+pub struct FramePacer {
+    target_fps: Option<f32>,
+    last_frame: Instant,
+    next_deadline: Instant,
+    delta_time: Duration,
+    stats: FrameTimeStats,
+}
+
+impl FramePacer {
+    pub fn new() -> FramePacer {
+        let now = Instant::now();
+
+        FramePacer {
+            target_fps: None,
+            last_frame: now,
+            next_deadline: now,
+            delta_time: Duration::from_secs(0),
+            stats: FrameTimeStats::new(),
+        }
+    }
+
+    pub fn target_fps(&self) -> Option<f32> {
+        self.target_fps
+    }
+
+    // None: no limit (vsync of present mode still applies)
+    pub fn set_target_fps(&mut self, target_fps: Option<f32>) {
+        self.target_fps = target_fps.filter(|fps| *fps > 0.0);
+        self.next_deadline = Instant::now();
+    }
+
+    // Call once per frame after present: waits for frame deadline, then records frame time
+    pub fn tick_frame(&mut self) {
+        if let Some(fps) = self.target_fps {
+            let period = Duration::from_secs_f32(1.0 / fps);
+            self.next_deadline += period;
+
+            let now = Instant::now();
+            if self.next_deadline < now {
+                // frame took too long, don't try to catch up
+                self.next_deadline = now;
+            } else {
+                wait_until(self.next_deadline);
+            }
+        }
+
+        let now = Instant::now();
+        self.delta_time = now - self.last_frame;
+        self.last_frame = now;
+        self.stats.push(self.delta_time.as_secs_f32() * 1000.0);
+    }
+
+    // Last frame time in seconds
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time.as_secs_f32()
+    }
+
+    // Average over frame time history
+    pub fn fps(&self) -> f32 {
+        let average = self.stats.average();
+        if average > 0.0 { 1000.0 / average } else { 0.0 }
+    }
+
+    pub fn stats(&self) -> &FrameTimeStats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.clear();
+    }
+}
+
+fn wait_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now + SPIN_THRESHOLD {
+        thread::sleep(deadline - now - SPIN_THRESHOLD);
+    }
+
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-3;
+
+    fn stats_from(samples: &[f32]) -> FrameTimeStats {
+        let mut stats = FrameTimeStats::new();
+        for &sample in samples {
+            stats.push(sample);
+        }
+        stats
+    }
+
+    #[test]
+    fn empty_summary() {
+        let summary = FrameTimeStats::new().summary();
+        assert_eq!(summary.max, 0.0);
+        assert_eq!(summary.avg, 0.0);
+    }
+
+    #[test]
+    fn lows_of_full_history() {
+        // 990 frames of 10 ms, 9 of 30 ms, one of 100 ms, spikes spread over the history
+        let mut samples = vec![10.0; HISTORY_SIZE];
+        for i in 0..9 {
+            samples[i * 100 + 50] = 30.0;
+        }
+        samples[777] = 100.0;
+
+        let summary = stats_from(&samples).summary();
+        assert_eq!(summary.min, 10.0);
+        assert_eq!(summary.max, 100.0);
+        assert!((summary.avg - 10.27).abs() < EPSILON);
+        // slowest 10 frames
+        assert!((summary.low_1 - 37.0).abs() < EPSILON);
+        // slowest frame
+        assert!((summary.low_01 - 100.0).abs() < EPSILON);
+        assert!((summary.low_1_fps() - 1000.0 / 37.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn lows_of_uniform_distribution() {
+        // 1, 2, ..., 1000 ms in shuffled order
+        let samples: Vec<f32> = (0..HISTORY_SIZE).map(|i| ((i * 367) % HISTORY_SIZE + 1) as f32).collect();
+
+        let summary = stats_from(&samples).summary();
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 1000.0);
+        assert!((summary.avg - 500.5).abs() < EPSILON);
+        assert!((summary.low_1 - 995.5).abs() < EPSILON);
+        assert!((summary.low_01 - 1000.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn short_history_falls_back_to_worst_frames() {
+        // 1% of 50 frames is less than one frame: the worst one is taken
+        let mut samples = vec![16.0; 50];
+        samples[10] = 40.0;
+        samples[20] = 25.0;
+        let summary = stats_from(&samples).summary();
+        assert_eq!(summary.low_1, 40.0);
+        assert_eq!(summary.low_01, 40.0);
+
+        // 1% of 150 frames rounds up to two
+        let mut samples = vec![16.0; 150];
+        samples[0] = 40.0;
+        samples[149] = 20.0;
+        let summary = stats_from(&samples).summary();
+        assert!((summary.low_1 - 30.0).abs() < EPSILON);
+        assert_eq!(summary.low_01, 40.0);
+
+        let summary = stats_from(&[12.0]).summary();
+        assert_eq!((summary.min, summary.max, summary.avg, summary.low_1, summary.low_01), (12.0, 12.0, 12.0, 12.0, 12.0));
+    }
+
+    #[test]
+    fn push_drops_oldest_samples() {
+        let mut stats = FrameTimeStats::new();
+        // spikes in the first 500 frames leave the history
+        for _ in 0..500 {
+            stats.push(200.0);
+        }
+        for i in 0..HISTORY_SIZE {
+            stats.push(10.0 + (i % 2) as f32);
+        }
+
+        assert_eq!(stats.len(), HISTORY_SIZE);
+        assert_eq!(stats.samples().next(), Some(&10.0));
+        assert_eq!(stats.last(), Some(11.0));
+
+        let summary = stats.summary();
+        assert_eq!(summary.max, 11.0);
+        assert!((summary.avg - 10.5).abs() < EPSILON);
+        assert_eq!(summary.low_1, 11.0);
+    }
+}
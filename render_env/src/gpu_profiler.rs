@@ -0,0 +1,401 @@
+use std::ffi::c_void;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::Arc;
+
+use ash::version::{DeviceV1_0, InstanceV1_0};
+use ash::vk;
+
+use crate::env::RenderEnv;
+
+// Timestamps per frame (two per scope)
+const MAX_QUERIES: u32 = 128;
+
+// Timing of one scope of a resolved frame, milliseconds
+#[derive(Clone, Debug)]
+pub struct GpuScopeTiming {
+    pub name: String,
+    // nesting level, 0 for top level scopes
+    pub depth: u32,
+    // from the first timestamp of the frame
+    pub start_ms: f32,
+    pub duration_ms: f32,
+}
+
+struct Scope {
+    name: String,
+    depth: u32,
+    // end timestamp is the next query
+    begin_query: u32,
+}
+
+// Queries and timestamp command buffers of one frame in flight. Command buffers are allocated on
+// first use and reused by later frames, so there are as many as timestamps actually written.
+struct FrameQueries {
+    query_pool: vk::QueryPool,
+    primary_cmds: Vec<vk::CommandBuffer>,
+    secondary_cmds: Vec<vk::CommandBuffer>,
+    // command buffers recorded in the current frame
+    primary_used: usize,
+    secondary_used: usize,
+    scopes: Vec<Scope>,
+    query_count: u32,
+}
+
+struct TraceCapture {
+    path: PathBuf,
+    frames_left: u32,
+    events: Vec<String>,
+    // GPU time of the first captured timestamp, milliseconds
+    origin_ms: Option<f64>,
+}
+
+// Per-pass GPU timings. Passes are separate command buffers, so scopes are tiny command buffers
+// with a single timestamp write, submitted in between:
+//
+//     profiler.begin_scope(&mut submit, "Geometry");
+//     submit.push(geometry_cmd);
+//     profiler.end_scope(&mut submit);
+//
+// Scopes inside a render pass (between secondary command buffers) use `*_in_render_pass`.
+// Results are read back `max_frames_in_flight` frames later, when the frame slot is reused.
+pub struct GpuProfiler {
+    env: Arc<RenderEnv>,
+    frames: Vec<FrameQueries>,
+    current_frame: usize,
+    // stack of open scope indices, None when the scope didn't fit in the query pool
+    open_scopes: Vec<Option<usize>>,
+    // `enabled` latched at `begin_frame`, so scopes stay balanced
+    frame_active: bool,
+
+    // nanoseconds per timestamp tick
+    timestamp_period: f32,
+    timestamp_mask: u64,
+    supported: bool,
+    pub enabled: bool,
+
+    results: Vec<GpuScopeTiming>,
+    trace: Option<TraceCapture>,
+}
+
+impl GpuProfiler {
+    pub fn new(env: Arc<RenderEnv>, max_frames_in_flight: usize) -> GpuProfiler {
+        let device = env.device();
+
+        let properties = unsafe { env.instance().get_physical_device_properties(env.physical_device()) };
+        let queue_families = unsafe {
+            env.instance().get_physical_device_queue_family_properties(env.physical_device())
+        };
+        let valid_bits = queue_families[env.queue_family_index() as usize].timestamp_valid_bits;
+        let supported = valid_bits > 0 && properties.limits.timestamp_period > 0.0;
+        if !supported {
+            log::warn!("GPU profiler: timestamps are not supported by graphics queue");
+        }
+
+        let frames = (0..max_frames_in_flight)
+            .map(|_| {
+                let create_info = vk::QueryPoolCreateInfo {
+                    s_type: vk::StructureType::QUERY_POOL_CREATE_INFO,
+                    p_next: ptr::null(),
+                    flags: vk::QueryPoolCreateFlags::empty(),
+                    query_type: vk::QueryType::TIMESTAMP,
+                    query_count: MAX_QUERIES,
+                    pipeline_statistics: vk::QueryPipelineStatisticFlags::empty(),
+                };
+
+                FrameQueries {
+                    query_pool: unsafe {
+                        device.create_query_pool(&create_info, None).expect("Failed to create query pool!")
+                    },
+                    primary_cmds: Vec::new(),
+                    secondary_cmds: Vec::new(),
+                    primary_used: 0,
+                    secondary_used: 0,
+                    scopes: Vec::new(),
+                    query_count: 0,
+                }
+            })
+            .collect();
+
+        GpuProfiler {
+            env: env.clone(),
+            frames,
+            current_frame: 0,
+            open_scopes: Vec::new(),
+            frame_active: false,
+            timestamp_period: properties.limits.timestamp_period,
+            timestamp_mask: if valid_bits >= 64 { u64::MAX } else { (1u64 << valid_bits) - 1 },
+            supported,
+            enabled: true,
+            results: Vec::new(),
+            trace: None,
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    // Call before recording scopes of a frame: reads back results of the frame that used this
+    // slot before
+    pub fn begin_frame(&mut self) {
+        self.current_frame = (self.current_frame + 1) % self.frames.len();
+        assert!(self.open_scopes.is_empty(), "GPU profiler scope is not closed!");
+
+        self.resolve_frame(self.current_frame);
+
+        let frame = &mut self.frames[self.current_frame];
+        frame.scopes.clear();
+        frame.query_count = 0;
+        frame.primary_used = 0;
+        frame.secondary_used = 0;
+        self.frame_active = self.supported && self.enabled;
+    }
+
+    pub fn begin_scope(&mut self, submit: &mut Vec<vk::CommandBuffer>, name: &str) {
+        if let Some(cmd_buf) = self.open_scope(name, None) {
+            submit.push(cmd_buf);
+        }
+    }
+
+    pub fn end_scope(&mut self, submit: &mut Vec<vk::CommandBuffer>) {
+        if let Some(cmd_buf) = self.close_scope(None) {
+            submit.push(cmd_buf);
+        }
+    }
+
+    // Scope between secondary command buffers of `render_pass` (subpass 0)
+    pub fn begin_scope_in_render_pass(&mut self, secondary: &mut Vec<vk::CommandBuffer>, render_pass: vk::RenderPass, name: &str) {
+        if let Some(cmd_buf) = self.open_scope(name, Some(render_pass)) {
+            secondary.push(cmd_buf);
+        }
+    }
+
+    pub fn end_scope_in_render_pass(&mut self, secondary: &mut Vec<vk::CommandBuffer>, render_pass: vk::RenderPass) {
+        if let Some(cmd_buf) = self.close_scope(Some(render_pass)) {
+            secondary.push(cmd_buf);
+        }
+    }
+
+    // Timings of the last resolved frame, scopes in begin order
+    pub fn results(&self) -> &[GpuScopeTiming] {
+        &self.results
+    }
+
+    // Writes Chrome trace (chrome://tracing, Perfetto) of the next `frame_count` resolved frames
+    pub fn capture_trace(&mut self, path: &Path, frame_count: u32) {
+        self.trace = Some(TraceCapture {
+            path: path.to_path_buf(),
+            frames_left: frame_count,
+            events: Vec::new(),
+            origin_ms: None,
+        });
+    }
+
+    pub fn is_capturing_trace(&self) -> bool {
+        self.trace.is_some()
+    }
+
+    fn open_scope(&mut self, name: &str, render_pass: Option<vk::RenderPass>) -> Option<vk::CommandBuffer> {
+        if !self.frame_active {
+            return None;
+        }
+
+        // both queries are reserved here, so an opened scope can always be closed
+        let frame = &mut self.frames[self.current_frame];
+        if frame.query_count + 2 > MAX_QUERIES {
+            self.open_scopes.push(None);
+            return None;
+        }
+        let query = frame.query_count;
+        frame.query_count += 2;
+
+        frame.scopes.push(Scope {
+            name: name.to_string(),
+            depth: self.open_scopes.len() as u32,
+            begin_query: query,
+        });
+        self.open_scopes.push(Some(frame.scopes.len() - 1));
+
+        Some(self.record_timestamp(query, render_pass, vk::PipelineStageFlags::TOP_OF_PIPE))
+    }
+
+    fn close_scope(&mut self, render_pass: Option<vk::RenderPass>) -> Option<vk::CommandBuffer> {
+        if !self.frame_active {
+            return None;
+        }
+
+        let scope_idx = self.open_scopes.pop().expect("GPU profiler scope is not opened!")?;
+        let query = self.frames[self.current_frame].scopes[scope_idx].begin_query + 1;
+
+        Some(self.record_timestamp(query, render_pass, vk::PipelineStageFlags::BOTTOM_OF_PIPE))
+    }
+
+    // Reset is not allowed inside render pass: the first timestamp of a frame (outside of render
+    // pass) resets the whole pool
+    fn record_timestamp(&mut self, query: u32, render_pass: Option<vk::RenderPass>, stage: vk::PipelineStageFlags) -> vk::CommandBuffer {
+        let env = &self.env;
+        let device = env.device();
+        let frame = &mut self.frames[self.current_frame];
+
+        let inheritance_info = vk::CommandBufferInheritanceInfo {
+            s_type: vk::StructureType::COMMAND_BUFFER_INHERITANCE_INFO,
+            p_next: ptr::null(),
+            render_pass: render_pass.unwrap_or(vk::RenderPass::null()),
+            subpass: 0,
+            framebuffer: vk::Framebuffer::null(),
+            occlusion_query_enable: 0,
+            query_flags: Default::default(),
+            pipeline_statistics: Default::default(),
+        };
+
+        let (cmd_buf, begin_info) = match render_pass {
+            Some(_) => (next_cmd_buf(&mut frame.secondary_cmds, &mut frame.secondary_used, || env.create_secondary_command_buffer()), vk::CommandBufferBeginInfo {
+                s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+                p_next: ptr::null(),
+                p_inheritance_info: &inheritance_info,
+                flags: vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE | vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            }),
+            None => (next_cmd_buf(&mut frame.primary_cmds, &mut frame.primary_used, || env.create_primary_command_buffer()), vk::CommandBufferBeginInfo {
+                s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+                p_next: ptr::null(),
+                p_inheritance_info: ptr::null(),
+                flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+            }),
+        };
+
+        unsafe {
+            device
+                .reset_command_buffer(cmd_buf, vk::CommandBufferResetFlags::empty())
+                .expect("Failed to reset Command Buffer!");
+            device
+                .begin_command_buffer(cmd_buf, &begin_info)
+                .expect("Failed to begin recording Command Buffer at beginning!");
+
+            if query == 0 {
+                assert!(render_pass.is_none(), "First GPU profiler scope of a frame must be outside of render pass!");
+                device.cmd_reset_query_pool(cmd_buf, frame.query_pool, 0, MAX_QUERIES);
+            }
+            device.cmd_write_timestamp(cmd_buf, stage, frame.query_pool, query);
+
+            device
+                .end_command_buffer(cmd_buf)
+                .expect("Failed to record Command Buffer at Ending!");
+        }
+
+        cmd_buf
+    }
+
+    fn resolve_frame(&mut self, frame_idx: usize) {
+        let frame = &self.frames[frame_idx];
+        if frame.query_count == 0 {
+            return;
+        }
+
+        // (timestamp, availability) pairs. `Device::get_query_pool_results` can't take 16 byte
+        // stride, so the function pointer is called directly.
+        let mut data = vec![[0u64; 2]; frame.query_count as usize];
+        let result = unsafe {
+            let device = self.env.device();
+            device.fp_v1_0().get_query_pool_results(
+                device.handle(),
+                frame.query_pool,
+                0,
+                frame.query_count,
+                data.len() * mem::size_of::<[u64; 2]>(),
+                data.as_mut_ptr() as *mut c_void,
+                mem::size_of::<[u64; 2]>() as vk::DeviceSize,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY,
+            )
+        };
+        if result != vk::Result::SUCCESS && result != vk::Result::NOT_READY {
+            log::error!("Failed to read GPU timestamps: {}", result);
+            return;
+        }
+
+        // ticks to milliseconds
+        let to_ms = |ticks: u64| (ticks & self.timestamp_mask) as f64 * self.timestamp_period as f64 / 1_000_000.0;
+        let timestamp = |query: u32| {
+            let [value, available] = data[query as usize];
+            if available != 0 { Some(to_ms(value)) } else { None }
+        };
+
+        let frame_start = match frame.scopes.first().and_then(|scope| timestamp(scope.begin_query)) {
+            Some(start) => start,
+            // not finished yet, keep previous results
+            None => return,
+        };
+
+        let mut results = Vec::with_capacity(frame.scopes.len());
+        for scope in frame.scopes.iter() {
+            let begin = timestamp(scope.begin_query);
+            let end = timestamp(scope.begin_query + 1);
+            if let (Some(begin), Some(end)) = (begin, end) {
+                results.push(GpuScopeTiming {
+                    name: scope.name.clone(),
+                    depth: scope.depth,
+                    start_ms: (begin - frame_start) as f32,
+                    duration_ms: (end - begin).max(0.0) as f32,
+                });
+            }
+        }
+
+        if let Some(trace) = self.trace.as_mut() {
+            let origin = *trace.origin_ms.get_or_insert(frame_start);
+            for timing in results.iter() {
+                trace.events.push(format!(
+                    "{{\"name\":\"{}\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":{},\"ts\":{:.3},\"dur\":{:.3}}}",
+                    timing.name.replace('\\', "\\\\").replace('"', "\\\""),
+                    timing.depth,
+                    (frame_start - origin) * 1000.0 + timing.start_ms as f64 * 1000.0,
+                    timing.duration_ms as f64 * 1000.0,
+                ));
+            }
+
+            trace.frames_left = trace.frames_left.saturating_sub(1);
+            if trace.frames_left == 0 {
+                let trace = self.trace.take().unwrap();
+                match write_chrome_trace(&trace.path, &trace.events) {
+                    Ok(_) => log::info!("GPU trace written to {}", trace.path.display()),
+                    Err(err) => log::error!("Failed to write GPU trace: {}", err),
+                }
+            }
+        }
+
+        self.results = results;
+    }
+}
+
+// Next unused command buffer of the frame, allocated when all are in use
+fn next_cmd_buf(cmds: &mut Vec<vk::CommandBuffer>, used: &mut usize, allocate: impl FnOnce() -> vk::CommandBuffer) -> vk::CommandBuffer {
+    if *used == cmds.len() {
+        cmds.push(allocate());
+    }
+    *used += 1;
+
+    cmds[*used - 1]
+}
+
+fn write_chrome_trace(path: &Path, events: &[String]) -> io::Result<()> {
+    fs::write(path, format!("{{\"traceEvents\":[\n{}\n]}}\n", events.join(",\n")))
+}
+
+impl Drop for GpuProfiler {
+    fn drop(&mut self) {
+        unsafe {
+            let device = self.env.device();
+            for frame in self.frames.iter() {
+                if !frame.primary_cmds.is_empty() {
+                    device.free_command_buffers(self.env.command_pool(), &frame.primary_cmds);
+                }
+                if !frame.secondary_cmds.is_empty() {
+                    device.free_command_buffers(self.env.command_pool(), &frame.secondary_cmds);
+                }
+                device.destroy_query_pool(frame.query_pool, None);
+            }
+        }
+    }
+}
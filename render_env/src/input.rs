@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+
+use winit::event::{DeviceEvent, ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+use winit::window::Window;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyBindings {
+    pub forward: VirtualKeyCode,
+    pub backward: VirtualKeyCode,
+    pub left: VirtualKeyCode,
+    pub right: VirtualKeyCode,
+    pub up: VirtualKeyCode,
+    pub down: VirtualKeyCode,
+    // faster movement while held
+    pub boost: VirtualKeyCode,
+    // mouse look (orbit) while held, cursor is grabbed and hidden
+    pub look: MouseButton,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            forward: VirtualKeyCode::W,
+            backward: VirtualKeyCode::S,
+            left: VirtualKeyCode::A,
+            right: VirtualKeyCode::D,
+            up: VirtualKeyCode::Space,
+            down: VirtualKeyCode::LShift,
+            boost: VirtualKeyCode::LControl,
+            look: MouseButton::Left,
+        }
+    }
+}
+
+impl KeyBindings {
+    // Arrow keys for left-handed mouse users
+    pub fn arrows() -> KeyBindings {
+        KeyBindings {
+            forward: VirtualKeyCode::Up,
+            backward: VirtualKeyCode::Down,
+            left: VirtualKeyCode::Left,
+            right: VirtualKeyCode::Right,
+            up: VirtualKeyCode::PageUp,
+            down: VirtualKeyCode::PageDown,
+            boost: VirtualKeyCode::RShift,
+            look: MouseButton::Left,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InputSettings {
+    pub bindings: KeyBindings,
+    // degrees per raw mouse count
+    pub mouse_sensitivity: f32,
+    pub invert_y: bool,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        InputSettings {
+            bindings: KeyBindings::default(),
+            mouse_sensitivity: 0.15,
+            invert_y: false,
+        }
+    }
+}
+
+// Held keys and mouse movement accumulated between frames. Window events give key state,
+// device events give raw mouse motion (not limited by window border, no pointer acceleration).
+pub struct InputState {
+    pub settings: InputSettings,
+
+    pressed_keys: HashSet<VirtualKeyCode>,
+    pressed_buttons: HashSet<MouseButton>,
+    looking: bool,
+    cursor_grabbed: bool,
+    mouse_delta: [f32; 2],
+    scroll_delta: f32,
+    // physical pixels, None when cursor is outside of window
+    cursor_position: Option<[f32; 2]>,
+}
+
+impl InputState {
+    pub fn new(settings: InputSettings) -> InputState {
+        InputState {
+            settings,
+            pressed_keys: HashSet::new(),
+            pressed_buttons: HashSet::new(),
+            looking: false,
+            cursor_grabbed: false,
+            mouse_delta: [0.0, 0.0],
+            scroll_delta: 0.0,
+            cursor_position: None,
+        }
+    }
+
+    // `accept_mouse`: false when pointer is over UI, mouse presses and wheel are ignored then.
+    // Releases are always handled, so nothing stays pressed.
+    pub fn handle_window_event(&mut self, event: &WindowEvent, accept_mouse: bool) {
+        match event {
+            &WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode {
+                    match input.state {
+                        ElementState::Pressed => self.pressed_keys.insert(key),
+                        ElementState::Released => self.pressed_keys.remove(&key),
+                    };
+                }
+            }
+            &WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ElementState::Pressed if accept_mouse => {
+                        self.pressed_buttons.insert(button);
+                    }
+                    ElementState::Released => {
+                        self.pressed_buttons.remove(&button);
+                    }
+                    _ => (),
+                }
+                self.looking = self.pressed_buttons.contains(&self.settings.bindings.look);
+            }
+            &WindowEvent::MouseWheel { delta, .. } if accept_mouse => {
+                self.scroll_delta += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+                };
+            }
+            &WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some([position.x as f32, position.y as f32]);
+            }
+            &WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+            }
+            // keys released outside of window are never reported
+            &WindowEvent::Focused(false) => {
+                self.pressed_keys.clear();
+                self.pressed_buttons.clear();
+                self.looking = false;
+            }
+            _ => (),
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let &DeviceEvent::MouseMotion { delta } = event {
+            if self.looking {
+                self.mouse_delta[0] += delta.0 as f32;
+                self.mouse_delta[1] += delta.1 as f32;
+            }
+        }
+    }
+
+    // Grabs and hides cursor while looking around, call once per frame
+    pub fn update_cursor(&mut self, window: &Window) {
+        if self.looking == self.cursor_grabbed {
+            return;
+        }
+
+        if let Err(err) = window.set_cursor_grab(self.looking) {
+            log::warn!("Failed to grab cursor: {}", err);
+        }
+        window.set_cursor_visible(!self.looking);
+        self.cursor_grabbed = self.looking;
+    }
+
+    // Mouse look is active, UI must not get mouse events
+    pub fn looking(&self) -> bool {
+        self.looking
+    }
+
+    pub fn cursor_position(&self) -> Option<[f32; 2]> {
+        self.cursor_position
+    }
+
+    pub fn is_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    // -1, 0 or 1
+    pub fn axis(&self, positive: VirtualKeyCode, negative: VirtualKeyCode) -> f32 {
+        self.is_pressed(positive) as i32 as f32 - self.is_pressed(negative) as i32 as f32
+    }
+
+    pub fn boost(&self) -> bool {
+        self.is_pressed(self.settings.bindings.boost)
+    }
+
+    // Look rotation (yaw, pitch) in degrees since last call
+    pub fn take_look_delta(&mut self) -> [f32; 2] {
+        let delta = std::mem::replace(&mut self.mouse_delta, [0.0, 0.0]);
+        let invert = if self.settings.invert_y { -1.0 } else { 1.0 };
+
+        [
+            delta[0] * self.settings.mouse_sensitivity,
+            delta[1] * self.settings.mouse_sensitivity * invert,
+        ]
+    }
+
+    // Wheel lines since last call
+    pub fn take_scroll_delta(&mut self) -> f32 {
+        std::mem::replace(&mut self.scroll_delta, 0.0)
+    }
+}
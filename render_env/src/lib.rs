@@ -8,12 +8,22 @@ pub mod env;
 pub mod swapchain;
 
 pub mod shader;
+pub mod debug_names;
+pub mod debug_messages;
 pub mod descriptor_set;
 mod platforms;
 pub mod frame_buffer;
 pub mod pipeline_builder;
+pub mod compute_pipeline;
 pub mod egui;
 pub mod primary_cmd_buffer;
 pub mod utils;
 pub mod camera;
-pub mod fps_limiter;
+pub mod math;
+pub mod projection;
+pub mod camera_controller;
+pub mod camera_path;
+pub mod input;
+pub mod frame_pacer;
+pub mod gpu_profiler;
+pub mod frame_capture;
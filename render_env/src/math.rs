@@ -0,0 +1,595 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, MetricSpace, Point3, Transform, Vector3, Vector4};
+
+// Axis aligned bounding box
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Aabb {
+        Aabb { min, max }
+    }
+
+    // Inverted box, any `extend` makes it valid
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Point3::new(f32::MAX, f32::MAX, f32::MAX),
+            max: Point3::new(f32::MIN, f32::MIN, f32::MIN),
+        }
+    }
+
+    pub fn from_points<I: IntoIterator<Item=Point3<f32>>>(points: I) -> Aabb {
+        let mut result = Aabb::empty();
+        for point in points {
+            result.extend(point);
+        }
+
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn extend(&mut self, point: Point3<f32>) {
+        self.min = Point3::new(self.min.x.min(point.x), self.min.y.min(point.y), self.min.z.min(point.z));
+        self.max = Point3::new(self.max.x.max(point.x), self.max.y.max(point.y), self.max.z.max(point.z));
+    }
+
+    // Empty boxes are ignored (extending by their inverted corners would make the result infinite)
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        if other.is_empty() {
+            return *self;
+        }
+
+        let mut result = *self;
+        result.extend(other.min);
+        result.extend(other.max);
+        result
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    // Half size along each axis
+    pub fn extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        [
+            Point3::new(self.min.x, self.min.y, self.min.z),
+            Point3::new(self.max.x, self.min.y, self.min.z),
+            Point3::new(self.min.x, self.max.y, self.min.z),
+            Point3::new(self.max.x, self.max.y, self.min.z),
+            Point3::new(self.min.x, self.min.y, self.max.z),
+            Point3::new(self.max.x, self.min.y, self.max.z),
+            Point3::new(self.min.x, self.max.y, self.max.z),
+            Point3::new(self.max.x, self.max.y, self.max.z),
+        ]
+    }
+
+    // Box around transformed corners
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Aabb {
+        Aabb::from_points(self.corners().iter().map(|corner| matrix.transform_point(*corner)))
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x
+            && point.y >= self.min.y && point.y <= self.max.y
+            && point.z >= self.min.z && point.z <= self.max.z
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x
+            && self.min.y <= other.max.y && self.max.y >= other.min.y
+            && self.min.z <= other.max.z && self.max.z >= other.min.z
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub fn new(center: Point3<f32>, radius: f32) -> Sphere {
+        Sphere { center, radius }
+    }
+
+    // Sphere around box (not the smallest one for arbitrary points, exact for box)
+    pub fn from_aabb(bounds: &Aabb) -> Sphere {
+        Sphere {
+            center: bounds.center(),
+            radius: bounds.extents().magnitude(),
+        }
+    }
+
+    // Radius is scaled by the largest axis scale of `matrix`
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Sphere {
+        let scale = matrix.x.truncate().magnitude()
+            .max(matrix.y.truncate().magnitude())
+            .max(matrix.z.truncate().magnitude());
+
+        Sphere {
+            center: matrix.transform_point(self.center),
+            radius: self.radius * scale,
+        }
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.center.distance2(point) <= self.radius * self.radius
+    }
+
+    pub fn intersects(&self, other: &Sphere) -> bool {
+        let radius = self.radius + other.radius;
+        self.center.distance2(other.center) <= radius * radius
+    }
+
+    pub fn intersects_aabb(&self, bounds: &Aabb) -> bool {
+        // closest box point to sphere center
+        let closest = Point3::new(
+            self.center.x.max(bounds.min.x).min(bounds.max.x),
+            self.center.y.max(bounds.min.y).min(bounds.max.y),
+            self.center.z.max(bounds.min.z).min(bounds.max.z),
+        );
+
+        self.contains_point(closest)
+    }
+}
+
+// Points with `normal . p + distance >= 0` are inside
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    // Normalized plane from (a, b, c, d) coefficients. Degenerate plane (zero normal, e.g. far
+    // plane of infinite projection) is kept as is: it contains everything when `d` is positive.
+    pub fn from_coefficients(coefficients: Vector4<f32>) -> Plane {
+        let normal = coefficients.truncate();
+        let length = normal.magnitude();
+        if length < 1e-6 {
+            return Plane { normal, distance: coefficients.w };
+        }
+
+        Plane {
+            normal: normal / length,
+            distance: coefficients.w / length,
+        }
+    }
+
+    pub fn signed_distance(&self, point: Point3<f32>) -> f32 {
+        self.normal.dot(point.to_vec()) + self.distance
+    }
+}
+
+// View volume planes extracted from view-projection matrix (Gribb / Hartmann). Matrix must map
+// to Vulkan clip space (depth in [0, 1]), reverse-Z and infinite far plane are supported.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Frustum {
+    // left, right, bottom, top, depth 1, depth 0
+    planes: [Plane; 6],
+    plane_count: usize,
+}
+
+impl Frustum {
+    pub fn from_matrix(view_proj: Matrix4<f32>) -> Frustum {
+        let (r0, r1, r2, r3) = (view_proj.row(0), view_proj.row(1), view_proj.row(2), view_proj.row(3));
+
+        Frustum {
+            planes: [
+                Plane::from_coefficients(r3 + r0),
+                Plane::from_coefficients(r3 - r0),
+                Plane::from_coefficients(r3 + r1),
+                Plane::from_coefficients(r3 - r1),
+                Plane::from_coefficients(r3 - r2),
+                Plane::from_coefficients(r2),
+            ],
+            plane_count: 6,
+        }
+    }
+
+    // Without depth 0 plane (near plane of standard depth projection): volume is open towards
+    // the viewer, used by shadow casters between light and cascade
+    pub fn without_near_plane(mut self) -> Frustum {
+        self.plane_count = 5;
+        self
+    }
+
+    pub fn planes(&self) -> &[Plane] {
+        &self.planes[..self.plane_count]
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.planes().iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes().iter().all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    // Conservative: box near frustum corner may pass while being outside
+    pub fn intersects_aabb(&self, bounds: &Aabb) -> bool {
+        self.planes().iter().all(|plane| {
+            // box corner farthest along plane normal
+            let x = if plane.normal.x > 0.0 { bounds.max.x } else { bounds.min.x };
+            let y = if plane.normal.y > 0.0 { bounds.max.y } else { bounds.min.y };
+            let z = if plane.normal.z > 0.0 { bounds.max.z } else { bounds.min.z };
+
+            plane.signed_distance(Point3::new(x, y, z)) >= 0.0
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    // normalized, hit distances are in world units
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Ray {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    // Ray in space of `matrix` (e.g. inverse world matrix to get into model space). Direction is
+    // not normalized, so distances stay the same as in the original space.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Ray {
+        Ray {
+            origin: matrix.transform_point(self.origin),
+            direction: matrix.transform_vector(self.direction),
+        }
+    }
+
+    // Slab test. Distance of entry point, 0 when origin is inside.
+    pub fn intersect_aabb(&self, bounds: &Aabb) -> Option<f32> {
+        let mut t_min = 0.0_f32;
+        let mut t_max = f32::MAX;
+
+        for axis in 0..3 {
+            let inv_dir = 1.0 / self.direction[axis];
+            let mut t0 = (bounds.min[axis] - self.origin[axis]) * inv_dir;
+            let mut t1 = (bounds.max[axis] - self.origin[axis]) * inv_dir;
+            if t0 > t1 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // NaN (origin on slab border, direction parallel) must not discard the box
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_min > t_max {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+
+    // Möller–Trumbore, both faces
+    pub fn intersect_triangle(&self, a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = self.direction.cross(edge2);
+        let det = edge1.dot(p);
+        if det.abs() < 1e-9 {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = self.origin - a;
+        let u = s.dot(p) * inv_det;
+        if u < 0.0 || u > 1.0 {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(q) * inv_det;
+        if t < 0.0 {
+            return None;
+        }
+
+        Some(t)
+    }
+
+    // Closest hit with indexed triangle list
+    pub fn intersect_triangles(&self, positions: &[Point3<f32>], indices: &[u32]) -> Option<f32> {
+        indices
+            .chunks_exact(3)
+            .filter_map(|tri| {
+                self.intersect_triangle(positions[tri[0] as usize], positions[tri[1] as usize], positions[tri[2] as usize])
+            })
+            .fold(None, |closest: Option<f32>, t| Some(closest.map_or(t, |closest| closest.min(t))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, Matrix4, Rad, SquareMatrix};
+
+    use crate::projection::{Projection, ProjectionKind};
+
+    const EPSILON: f32 = 1e-5;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    fn assert_point_eq(a: Point3<f32>, b: Point3<f32>) {
+        assert!(a.distance(b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    // Looking down -Z from origin, 90 degrees FOV, near 0.1, far 10
+    fn projection(infinite_far: bool, reverse_z: bool) -> Matrix4<f32> {
+        Projection {
+            kind: ProjectionKind::Perspective,
+            fov_y: 90.0,
+            near_clip: 0.1,
+            far_clip: 10.0,
+            infinite_far,
+            reverse_z,
+            ..Projection::default()
+        }.matrix(1.0)
+    }
+
+    #[test]
+    fn aabb_empty() {
+        assert!(Aabb::empty().is_empty());
+        assert!(!unit_box().is_empty());
+        // single point is a valid box
+        assert!(!Aabb::from_points(vec![Point3::new(1.0, 2.0, 3.0)]).is_empty());
+        assert!(Aabb::new(Point3::new(0.0, 1.0, 0.0), Point3::new(1.0, 0.0, 1.0)).is_empty());
+    }
+
+    #[test]
+    fn aabb_union() {
+        let other = Aabb::new(Point3::new(0.0, 2.0, -3.0), Point3::new(0.5, 4.0, -2.0));
+        let union = unit_box().union(&other);
+        assert_point_eq(union.min, Point3::new(-1.0, -1.0, -3.0));
+        assert_point_eq(union.max, Point3::new(1.0, 4.0, 1.0));
+
+        assert_eq!(unit_box().union(&Aabb::empty()), unit_box());
+        assert_eq!(Aabb::empty().union(&unit_box()), unit_box());
+        assert!(Aabb::empty().union(&Aabb::empty()).is_empty());
+    }
+
+    #[test]
+    fn aabb_transform() {
+        let matrix = Matrix4::from_translation(Vector3::new(10.0, 0.0, 0.0)) * Matrix4::from_angle_z(Deg(45.0));
+        let bounds = unit_box().transform(&matrix);
+
+        let half = 2.0_f32.sqrt();
+        assert_point_eq(bounds.min, Point3::new(10.0 - half, -half, -1.0));
+        assert_point_eq(bounds.max, Point3::new(10.0 + half, half, 1.0));
+
+        let scaled = unit_box().transform(&Matrix4::from_nonuniform_scale(2.0, 1.0, 0.5));
+        assert_point_eq(scaled.min, Point3::new(-2.0, -1.0, -0.5));
+        assert_point_eq(scaled.max, Point3::new(2.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn sphere_intersects_aabb() {
+        let bounds = unit_box();
+        assert!(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.1).intersects_aabb(&bounds));
+        assert!(Sphere::new(Point3::new(1.5, 0.0, 0.0), 0.6).intersects_aabb(&bounds));
+        assert!(!Sphere::new(Point3::new(1.5, 0.0, 0.0), 0.4).intersects_aabb(&bounds));
+
+        // within radius of every face plane, but farther than radius from the corner
+        let corner = Sphere::new(Point3::new(1.5, 1.5, 1.5), 0.8);
+        assert!(!corner.intersects_aabb(&bounds));
+        assert!(Sphere::new(Point3::new(1.5, 1.5, 1.5), 0.9).intersects_aabb(&bounds));
+    }
+
+    #[test]
+    fn frustum_plane_signs() {
+        let inside = Point3::new(0.5, -0.5, -5.0);
+        let outside = [
+            Point3::new(-6.0, 0.0, -5.0), // left
+            Point3::new(6.0, 0.0, -5.0), // right
+            Point3::new(0.0, -6.0, -5.0), // bottom
+            Point3::new(0.0, 6.0, -5.0), // top
+        ];
+        let near = Point3::new(0.0, 0.0, -0.05);
+        let beyond_far = Point3::new(0.0, 0.0, -20.0);
+
+        for &(infinite_far, reverse_z) in &[(false, false), (false, true), (true, false), (true, true)] {
+            let frustum = Frustum::from_matrix(projection(infinite_far, reverse_z));
+            let case = format!("infinite_far={} reverse_z={}", infinite_far, reverse_z);
+
+            assert!(frustum.planes().iter().all(|plane| plane.signed_distance(inside) > 0.0), "{}", case);
+            for (plane, point) in frustum.planes()[..4].iter().zip(outside.iter()) {
+                assert!(plane.signed_distance(*point) < 0.0, "{} {:?}", case, point);
+            }
+            assert!(!frustum.contains_point(near), "{}", case);
+            assert_eq!(frustum.contains_point(beyond_far), infinite_far, "{}", case);
+        }
+    }
+
+    #[test]
+    fn frustum_depth_planes() {
+        // depth 0 is near plane for standard depth, far plane for reverse-Z
+        let standard = Frustum::from_matrix(projection(false, false));
+        assert!(standard.planes()[5].signed_distance(Point3::new(0.0, 0.0, -0.05)) < 0.0);
+        assert!(standard.planes()[4].signed_distance(Point3::new(0.0, 0.0, -20.0)) < 0.0);
+
+        let reverse = Frustum::from_matrix(projection(false, true));
+        assert!(reverse.planes()[4].signed_distance(Point3::new(0.0, 0.0, -0.05)) < 0.0);
+        assert!(reverse.planes()[5].signed_distance(Point3::new(0.0, 0.0, -20.0)) < 0.0);
+
+        // far plane at infinity is degenerate and contains everything
+        let infinite = Frustum::from_matrix(projection(true, true));
+        assert_eq!(infinite.planes()[5].normal, Vector3::new(0.0, 0.0, 0.0));
+        assert!(infinite.planes()[5].signed_distance(Point3::new(0.0, 0.0, -1.0e9)) > 0.0);
+    }
+
+    #[test]
+    fn frustum_without_near_plane() {
+        let frustum = Frustum::from_matrix(projection(false, false));
+        let open = frustum.without_near_plane();
+        assert_eq!(open.planes().len(), 5);
+        assert_eq!(open.planes(), &frustum.planes()[..5]);
+
+        // between viewer and near plane
+        let point = Point3::new(0.0, 0.0, -0.05);
+        assert!(!frustum.contains_point(point));
+        assert!(open.contains_point(point));
+    }
+
+    #[test]
+    fn frustum_intersects_aabb() {
+        let frustum = Frustum::from_matrix(projection(false, false));
+
+        let inside = Aabb::new(Point3::new(-0.5, -0.5, -3.0), Point3::new(0.5, 0.5, -2.0));
+        assert!(frustum.intersects_aabb(&inside));
+
+        let crossing_left = Aabb::new(Point3::new(-10.0, -0.5, -3.0), Point3::new(-2.0, 0.5, -2.0));
+        assert!(frustum.intersects_aabb(&crossing_left));
+
+        let behind = Aabb::new(Point3::new(-0.5, -0.5, 1.0), Point3::new(0.5, 0.5, 2.0));
+        assert!(!frustum.intersects_aabb(&behind));
+
+        let right = Aabb::new(Point3::new(4.0, -0.5, -3.0), Point3::new(5.0, 0.5, -2.0));
+        assert!(!frustum.intersects_aabb(&right));
+    }
+
+    #[test]
+    fn frustum_intersects_aabb_conservative_corner() {
+        let frustum = Frustum::from_matrix(projection(false, false));
+
+        // next to the far right edge (x = 10, z = -10): the box is outside of the frustum
+        // (x > -z for all its points with z >= -10), but no single plane separates it
+        let corner = Aabb::new(Point3::new(10.5, -0.5, -10.5), Point3::new(11.5, 0.5, -9.5));
+        assert!(corner.corners().iter().all(|point| !frustum.contains_point(*point)));
+        assert!(frustum.intersects_aabb(&corner));
+    }
+
+    #[test]
+    fn sphere_transform() {
+        let matrix = Matrix4::from_translation(Vector3::new(0.0, 5.0, 0.0))
+            * Matrix4::from_angle_y(Rad(1.0))
+            * Matrix4::from_nonuniform_scale(1.0, 3.0, 2.0);
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0).transform(&matrix);
+        assert_point_eq(sphere.center, Point3::new(0.0, 5.0, 0.0));
+        assert!((sphere.radius - 3.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn ray_intersect_aabb() {
+        let bounds = unit_box();
+
+        let hit = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0)).intersect_aabb(&bounds);
+        assert!((hit.unwrap() - 4.0).abs() < EPSILON);
+
+        let diagonal = Ray::new(Point3::new(-3.0, -3.0, -3.0), Vector3::new(1.0, 1.0, 1.0));
+        assert!((diagonal.intersect_aabb(&bounds).unwrap() - 2.0 * 3.0_f32.sqrt()).abs() < EPSILON);
+
+        // origin inside
+        let inside = Ray::new(Point3::new(0.5, 0.0, 0.0), Vector3::new(1.0, 2.0, 0.0));
+        assert_eq!(inside.intersect_aabb(&bounds), Some(0.0));
+
+        // box behind origin, box to the side
+        assert_eq!(Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0)).intersect_aabb(&bounds), None);
+        assert_eq!(Ray::new(Point3::new(0.0, 3.0, -5.0), Vector3::new(0.0, 0.0, 1.0)).intersect_aabb(&bounds), None);
+    }
+
+    #[test]
+    fn ray_intersect_aabb_axis_parallel() {
+        let bounds = unit_box();
+
+        // origin on max and min slab borders: 0 * inf is NaN
+        for &x in &[1.0, -1.0] {
+            let ray = Ray::new(Point3::new(x, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+            let hit = ray.intersect_aabb(&bounds);
+            assert!((hit.unwrap() - 4.0).abs() < EPSILON, "x={}", x);
+        }
+
+        // on the edge of two slabs
+        let edge = Ray::new(Point3::new(1.0, -1.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((edge.intersect_aabb(&bounds).unwrap() - 4.0).abs() < EPSILON);
+
+        // parallel outside of the slab
+        let outside = Ray::new(Point3::new(1.5, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(outside.intersect_aabb(&bounds), None);
+    }
+
+    #[test]
+    fn ray_intersect_triangle() {
+        let (a, b, c) = (Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, -1.0, 0.0), Point3::new(0.0, 1.0, 0.0));
+
+        let front = Ray::new(Point3::new(0.0, 0.0, 3.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!((front.intersect_triangle(a, b, c).unwrap() - 3.0).abs() < EPSILON);
+
+        // back face
+        let back = Ray::new(Point3::new(0.0, 0.0, -3.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!((back.intersect_triangle(a, b, c).unwrap() - 3.0).abs() < EPSILON);
+        assert!((front.intersect_triangle(a, c, b).unwrap() - 3.0).abs() < EPSILON);
+
+        // miss beside the triangle, triangle behind origin, ray in triangle plane
+        let beside = Ray::new(Point3::new(0.9, 0.9, 3.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(beside.intersect_triangle(a, b, c), None);
+        assert_eq!(Ray::new(Point3::new(0.0, 0.0, 3.0), Vector3::new(0.0, 0.0, 1.0)).intersect_triangle(a, b, c), None);
+        assert_eq!(Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0)).intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn ray_intersect_triangles_closest() {
+        // far quad first in index order, near triangle last
+        let positions = vec![
+            Point3::new(-1.0, -1.0, -5.0),
+            Point3::new(1.0, -1.0, -5.0),
+            Point3::new(1.0, 1.0, -5.0),
+            Point3::new(-1.0, 1.0, -5.0),
+            Point3::new(-1.0, -1.0, -2.0),
+            Point3::new(1.0, -1.0, -2.0),
+            Point3::new(0.0, 1.0, -2.0),
+        ];
+        let indices = [0, 1, 2, 0, 2, 3, 4, 5, 6];
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!((ray.intersect_triangles(&positions, &indices).unwrap() - 2.0).abs() < EPSILON);
+
+        // only the far quad
+        let corner = Ray::new(Point3::new(0.9, 0.9, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!((corner.intersect_triangles(&positions, &indices).unwrap() - 5.0).abs() < EPSILON);
+
+        let miss = Ray::new(Point3::new(3.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(miss.intersect_triangles(&positions, &indices), None);
+        assert_eq!(ray.intersect_triangles(&positions, &[]), None);
+    }
+
+    #[test]
+    fn ray_transform_keeps_distances() {
+        let world = Matrix4::from_translation(Vector3::new(0.0, 0.0, -10.0)) * Matrix4::from_scale(2.0);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        // world box is z in [-12, -8]
+        let model_ray = ray.transform(&world.invert().unwrap());
+        assert!((model_ray.direction.magnitude() - 0.5).abs() < EPSILON);
+        assert!((model_ray.intersect_aabb(&unit_box()).unwrap() - 8.0).abs() < EPSILON);
+
+        let (a, b, c) = (Point3::new(-1.0, -1.0, 1.0), Point3::new(1.0, -1.0, 1.0), Point3::new(0.0, 1.0, 1.0));
+        assert!((model_ray.intersect_triangle(a, b, c).unwrap() - 8.0).abs() < EPSILON);
+        assert_point_eq(ray.at(8.0), world.transform_point(model_ray.at(8.0)));
+    }
+}
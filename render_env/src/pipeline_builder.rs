@@ -3,8 +3,11 @@ use std::ptr;
 use ash::version::DeviceV1_0;
 use ash::vk;
 
+use crate::debug_names::DebugNames;
+use crate::env::RenderEnv;
 use crate::shader::{DescriptorSetLayout, Shader};
 use crate::shader;
+use crate::projection::reverse_compare_op;
 
 pub struct Pipeline {
     pub device: ash::Device,
@@ -45,10 +48,14 @@ pub struct PipelineBuilder {
     rasterization: vk::PipelineRasterizationStateCreateInfo,
     multisampling: vk::PipelineMultisampleStateCreateInfo,
     depth_stencil: vk::PipelineDepthStencilStateCreateInfo,
+    // depth compare op is flipped at build time, see `reverse_z`
+    reverse_z: bool,
     color_blend_attachment_states: Vec<vk::PipelineColorBlendAttachmentState>,
 
     vertex_shader: Option<Shader>,
     fragment_shader: Option<Shader>,
+
+    debug_name: Option<(DebugNames, String)>,
 }
 
 impl PipelineBuilder {
@@ -163,10 +170,13 @@ impl PipelineBuilder {
             rasterization: rasterization_status_create_info,
             multisampling: multisample_state_create_info,
             depth_stencil: depth_state_create_info,
+            reverse_z: false,
             color_blend_attachment_states,
 
             vertex_shader: None,
             fragment_shader: None,
+
+            debug_name: None,
         }
     }
 
@@ -215,6 +225,31 @@ impl PipelineBuilder {
         self
     }
 
+    // dst + src, for accumulating passes (bloom upsample, light volumes)
+    pub fn additive_blend(mut self) -> Self {
+        let color_blend_attachments = vec![
+            vk::PipelineColorBlendAttachmentState::builder()
+                .color_write_mask(
+                    vk::ColorComponentFlags::R
+                        | vk::ColorComponentFlags::G
+                        | vk::ColorComponentFlags::B
+                        | vk::ColorComponentFlags::A,
+                )
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::ONE)
+                .dst_color_blend_factor(vk::BlendFactor::ONE)
+                .color_blend_op(vk::BlendOp::ADD)
+                .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+                .alpha_blend_op(vk::BlendOp::ADD)
+                .build()
+        ];
+
+        self.color_blend_attachment_states = color_blend_attachments;
+
+        self
+    }
+
     pub fn with_depth_test(mut self) -> Self {
         let stencil_state = vk::StencilOpState {
             fail_op: vk::StencilOp::KEEP,
@@ -273,6 +308,14 @@ impl PipelineBuilder {
         self
     }
 
+    // Depth buffer uses reverse-Z (cleared to 0, near is 1): compare ops of `with_depth_test` and
+    // `with_depth_func` are written for standard depth and flipped (LESS_OR_EQUAL -> GREATER_OR_EQUAL)
+    pub fn reverse_z(mut self, enable: bool) -> Self {
+        self.reverse_z = enable;
+
+        self
+    }
+
     pub fn depth_clamp_enable(mut self, enable: bool) -> Self {
         self.rasterization.depth_clamp_enable = if enable {
             vk::TRUE
@@ -295,6 +338,12 @@ impl PipelineBuilder {
         self
     }
 
+    pub fn front_face(mut self, front_face: vk::FrontFace) -> Self {
+        self.rasterization.front_face = front_face;
+
+        self
+    }
+
     pub fn color_attachment_count(mut self, attachment_count: usize) -> Self {
         let mut color_blend_attachments = Vec::with_capacity(attachment_count);
 
@@ -318,7 +367,18 @@ impl PipelineBuilder {
         self
     }
 
+    // Pipeline, layout and descriptor set layouts are named after build ("<name>.layout", "<name>.set0", ...)
+    pub fn debug_name(mut self, env: &RenderEnv, name: &str) -> Self {
+        self.debug_name = Some((env.debug_names().clone(), name.to_string()));
+        self
+    }
+
     pub fn build(&mut self) -> Pipeline {
+        let mut depth_stencil = self.depth_stencil;
+        if self.reverse_z {
+            depth_stencil.depth_compare_op = reverse_compare_op(depth_stencil.depth_compare_op);
+        }
+
         let shader_stages = [
             self.vertex_shader.as_ref().unwrap().stage(),
             self.fragment_shader.as_ref().unwrap().stage(),
@@ -406,7 +466,7 @@ impl PipelineBuilder {
                 p_viewport_state: &self.viewport,
                 p_rasterization_state: &self.rasterization,
                 p_multisample_state: &self.multisampling,
-                p_depth_stencil_state: &self.depth_stencil,
+                p_depth_stencil_state: &depth_stencil,
                 p_color_blend_state: &color_blend,
                 p_dynamic_state: &dynamic_state_info,
                 layout: pipeline_layout,
@@ -429,6 +489,11 @@ impl PipelineBuilder {
                 .expect("Failed to create Graphics Pipeline!.")
         };
 
+        if let Some((names, name)) = &self.debug_name {
+            names.set_name(graphics_pipelines[0], name);
+            name_pipeline_layouts(names, name, pipeline_layout, &descriptor_set_layouts);
+        }
+
         Pipeline {
             device: self.device.clone(),
             graphics_pipeline: graphics_pipelines[0],
@@ -437,3 +502,12 @@ impl PipelineBuilder {
         }
     }
 }
+
+// Shared with `ComputePipeline`
+pub(crate) fn name_pipeline_layouts(names: &DebugNames, name: &str, pipeline_layout: vk::PipelineLayout,
+                                    descriptor_set_layouts: &[DescriptorSetLayout]) {
+    names.set_name(pipeline_layout, &format!("{}.layout", name));
+    for (set_idx, set_layout) in descriptor_set_layouts.iter().enumerate() {
+        names.set_name(set_layout.layout, &format!("{}.set{}", name, set_idx));
+    }
+}
@@ -12,6 +12,8 @@ pub struct PrimaryCommandBuffer {
     cmd_bufs: Vec<vk::CommandBuffer>,
     max_frame_in_flight: usize,
     current_frame: usize,
+    // command buffer name and label around recorded commands
+    debug_name: Option<String>,
 }
 
 impl PrimaryCommandBuffer {
@@ -27,9 +29,18 @@ impl PrimaryCommandBuffer {
             cmd_bufs,
             max_frame_in_flight,
             current_frame: 0,
+            debug_name: None,
         }
     }
 
+    pub fn debug_name(mut self, name: &str) -> Self {
+        for (frame, cmd_buf) in self.cmd_bufs.iter().enumerate() {
+            self.env.set_name(*cmd_buf, &format!("{}.frame{}", name, frame));
+        }
+        self.debug_name = Some(name.to_string());
+        self
+    }
+
     pub fn set_dimensions(&mut self, dims: [u32; 2]) {
         self.dimensions = dims;
     }
@@ -69,6 +80,10 @@ impl PrimaryCommandBuffer {
             p_clear_values: clear_values.as_ptr(),
         };
 
+        if let Some(name) = &self.debug_name {
+            self.env.cmd_begin_label(command_buffer, name);
+        }
+
         unsafe {
             self.env.device().cmd_begin_render_pass(
                 command_buffer,
@@ -76,10 +91,56 @@ impl PrimaryCommandBuffer {
                 vk::SubpassContents::SECONDARY_COMMAND_BUFFERS,
             );
 
-            self.env.device().cmd_execute_commands(command_buffer, second_buffers);
+            // no secondary buffers: render pass only clears attachments
+            if !second_buffers.is_empty() {
+                self.env.device().cmd_execute_commands(command_buffer, second_buffers);
+            }
 
             self.env.device().cmd_end_render_pass(command_buffer);
+        }
+
+        if self.debug_name.is_some() {
+            self.env.cmd_end_label(command_buffer);
+        }
+
+        unsafe {
+            self.env.device()
+                .end_command_buffer(command_buffer)
+                .expect("Failed to record Command Buffer at Ending!");
+        }
+
+        self.current_frame = (self.current_frame + 1) % self.max_frame_in_flight;
+        command_buffer
+    }
 
+    // Record commands outside of render pass (compute dispatches, copies, barriers)
+    pub fn execute<F>(&mut self, record: F) -> vk::CommandBuffer
+        where F: FnOnce(&ash::Device, vk::CommandBuffer)
+    {
+        let command_buffer = self.cmd_bufs.get(self.current_frame).unwrap().clone();
+        unsafe {
+            self.env.device().reset_command_buffer(command_buffer, vk::CommandBufferResetFlags::default()).unwrap()
+        }
+
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+            s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+            p_next: ptr::null(),
+            p_inheritance_info: ptr::null(),
+            flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+        };
+
+        unsafe {
+            self.env.device()
+                .begin_command_buffer(command_buffer, &command_buffer_begin_info)
+                .expect("Failed to begin recording Command Buffer at beginning!");
+        }
+
+        match &self.debug_name {
+            Some(name) => self.env.cmd_label(command_buffer, name, || record(self.env.device(), command_buffer)),
+            None => record(self.env.device(), command_buffer),
+        }
+
+        unsafe {
             self.env.device()
                 .end_command_buffer(command_buffer)
                 .expect("Failed to record Command Buffer at Ending!");
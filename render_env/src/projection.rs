@@ -0,0 +1,200 @@
+use ash::vk;
+use cgmath::{Deg, Matrix4, Rad};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ProjectionKind {
+    Perspective,
+    Orthographic,
+}
+
+// Camera projection. `matrix` maps view space to Vulkan clip space (depth in [0, 1]) without Y flip,
+// see `ClipY::Unchanged`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Projection {
+    pub kind: ProjectionKind,
+    // vertical field of view (perspective), degrees
+    pub fov_y: f32,
+    // visible height in world units (orthographic)
+    pub ortho_height: f32,
+    pub near_clip: f32,
+    // used for depth only when `infinite_far` is false, shadow distance otherwise
+    pub far_clip: f32,
+    // perspective only, far plane at infinity
+    pub infinite_far: bool,
+    // near plane at depth 1, far plane at depth 0: float depth precision is spread evenly over
+    // distance. Depth buffer is cleared to 0 and compared with GREATER_OR_EQUAL.
+    pub reverse_z: bool,
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection {
+            kind: ProjectionKind::Perspective,
+            fov_y: 45.0,
+            ortho_height: 10.0,
+            near_clip: 0.05,
+            far_clip: 48.0,
+            infinite_far: true,
+            reverse_z: true,
+        }
+    }
+}
+
+impl Projection {
+    pub fn matrix(&self, aspect: f32) -> Matrix4<f32> {
+        let opengl = match self.kind {
+            ProjectionKind::Perspective if self.infinite_far => {
+                infinite_perspective(Rad::from(Deg(self.fov_y)), aspect, self.near_clip)
+            }
+            _ => self.frustum_matrix(aspect),
+        };
+
+        clip_correction(ClipY::Unchanged, self.reverse_z) * opengl
+    }
+
+    // OpenGL style matrix limited by `far_clip` (NDC depth in [-1, 1]), for reconstruction of
+    // view frustum corners (shadow cascades)
+    pub fn frustum_matrix(&self, aspect: f32) -> Matrix4<f32> {
+        match self.kind {
+            ProjectionKind::Perspective => {
+                cgmath::perspective(Rad::from(Deg(self.fov_y)), aspect, self.near_clip, self.far_clip)
+            }
+            ProjectionKind::Orthographic => {
+                let half_height = self.ortho_height / 2.0;
+                let half_width = half_height * aspect;
+                cgmath::ortho(-half_width, half_width, -half_height, half_height, self.near_clip, self.far_clip)
+            }
+        }
+    }
+
+    // Projected size (NDC units) of unit length at view distance
+    pub fn ndc_scale(&self, distance: f32) -> f32 {
+        match self.kind {
+            ProjectionKind::Perspective => 1.0 / (Rad::from(Deg(self.fov_y)).0 / 2.0).tan() / distance,
+            ProjectionKind::Orthographic => 2.0 / self.ortho_height,
+        }
+    }
+
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.reverse_z { 0.0 } else { 1.0 }
+    }
+
+    pub fn depth_compare_op(&self) -> vk::CompareOp {
+        if self.reverse_z { vk::CompareOp::GREATER_OR_EQUAL } else { vk::CompareOp::LESS_OR_EQUAL }
+    }
+}
+
+// Y axis of clip space. Vulkan framebuffer Y points down (NDC -1 is the top row), OpenGL Y points up.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClipY {
+    // Camera: the scene is modeled with -Y up, so world up already lands on top of the screen
+    Unchanged,
+    // Light views built with cgmath `look_at` and +Y up (shadow maps)
+    Flipped,
+}
+
+// OpenGL clip space (cgmath, depth in [-1, 1]) to Vulkan clip space (depth in [0, 1], or [1, 0] for
+// reverse-Z). Every projection matrix goes through here.
+// See https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
+pub fn clip_correction(y: ClipY, reverse_z: bool) -> Matrix4<f32> {
+    let y_scale = match y {
+        ClipY::Unchanged => 1.0,
+        ClipY::Flipped => -1.0,
+    };
+    let z_scale = if reverse_z { -0.5 } else { 0.5 };
+
+    Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, y_scale, 0.0, 0.0,
+        0.0, 0.0, z_scale, 0.0,
+        0.0, 0.0, 0.5, 1.0,
+    )
+}
+
+// Shadow map projections: Y flipped, standard depth
+pub fn vulkan_clip_correction() -> Matrix4<f32> {
+    clip_correction(ClipY::Flipped, false)
+}
+
+// OpenGL style perspective with far plane at infinity (limit of `cgmath::perspective`)
+fn infinite_perspective(fov_y: Rad<f32>, aspect: f32, near_clip: f32) -> Matrix4<f32> {
+    let focal = 1.0 / (fov_y.0 / 2.0).tan();
+
+    Matrix4::new(
+        focal / aspect, 0.0, 0.0, 0.0,
+        0.0, focal, 0.0, 0.0,
+        0.0, 0.0, -1.0, -1.0,
+        0.0, 0.0, -2.0 * near_clip, 0.0,
+    )
+}
+
+// Compare op for reverse-Z depth buffer: near and far are swapped, so less becomes greater
+pub fn reverse_compare_op(op: vk::CompareOp) -> vk::CompareOp {
+    match op {
+        vk::CompareOp::LESS => vk::CompareOp::GREATER,
+        vk::CompareOp::LESS_OR_EQUAL => vk::CompareOp::GREATER_OR_EQUAL,
+        vk::CompareOp::GREATER => vk::CompareOp::LESS,
+        vk::CompareOp::GREATER_OR_EQUAL => vk::CompareOp::LESS_OR_EQUAL,
+        op => op,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn depth(proj: Matrix4<f32>, view_z: f32) -> f32 {
+        let clip = proj * Vector4::new(0.0, 0.0, view_z, 1.0);
+        clip.z / clip.w
+    }
+
+    fn projection(kind: ProjectionKind, infinite_far: bool, reverse_z: bool) -> Projection {
+        Projection { kind, infinite_far, reverse_z, near_clip: 0.1, far_clip: 100.0, ..Projection::default() }
+    }
+
+    #[test]
+    fn near_and_far_depth() {
+        for &kind in &[ProjectionKind::Perspective, ProjectionKind::Orthographic] {
+            for &reverse_z in &[false, true] {
+                let proj = projection(kind, false, reverse_z).matrix(1.5);
+                let (near, far) = if reverse_z { (1.0, 0.0) } else { (0.0, 1.0) };
+                assert!((depth(proj, -0.1) - near).abs() < EPSILON, "{:?} reverse_z={}", kind, reverse_z);
+                assert!((depth(proj, -100.0) - far).abs() < 1e-4, "{:?} reverse_z={}", kind, reverse_z);
+            }
+        }
+    }
+
+    #[test]
+    fn infinite_far_depth() {
+        let proj = projection(ProjectionKind::Perspective, true, false).matrix(1.5);
+        assert!(depth(proj, -0.1).abs() < EPSILON);
+        assert!(depth(proj, -1.0e6) < 1.0);
+        assert!((depth(proj, -1.0e6) - 1.0).abs() < 1e-4);
+
+        let proj = projection(ProjectionKind::Perspective, true, true).matrix(1.5);
+        assert!((depth(proj, -0.1) - 1.0).abs() < EPSILON);
+        assert!(depth(proj, -1.0e6) > 0.0);
+        assert!(depth(proj, -1.0e6) < 1e-4);
+    }
+
+    #[test]
+    fn camera_keeps_y() {
+        let proj = Projection::default().matrix(1.0);
+        let clip = proj * Vector4::new(0.0, 1.0, -5.0, 1.0);
+        assert!(clip.y > 0.0);
+
+        let clip = vulkan_clip_correction() * Vector4::new(0.0, 1.0, 0.0, 1.0);
+        assert_eq!(clip.y, -1.0);
+    }
+
+    #[test]
+    fn reverse_compare() {
+        let proj = Projection::default();
+        assert!(proj.reverse_z);
+        assert_eq!(proj.depth_compare_op(), reverse_compare_op(vk::CompareOp::LESS_OR_EQUAL));
+        assert_eq!(proj.depth_clear_value(), 0.0);
+    }
+}
@@ -16,19 +16,33 @@ pub struct SwapChain {
     pub image_views: Vec<vk::ImageView>,
     pub framebuffers: Vec<vk::Framebuffer>,
     pub format: vk::Format,
+    pub color_space: vk::ColorSpaceKHR,
     pub size: vk::Extent2D,
+    // TRANSFER_SRC is not guaranteed for surface images
+    pub supports_readback: bool,
+
+    // offscreen: images are owned, no swapchain
+    image_memories: Vec<vk::DeviceMemory>,
 }
 
 impl SwapChain {
+    // `hdr`: use scRGB surface format if available, see `is_hdr`
     pub fn new(
-        env: &RenderEnv, size: PhysicalSize<u32>,
+        env: &RenderEnv, size: PhysicalSize<u32>, hdr: bool,
     ) -> SwapChain
     {
         let swapchain_support = utils::SwapChainSupportDetail::load(&env);
 
-        let swapchain_format = swapchain_support.format();
+        let swapchain_format = match swapchain_support.hdr_format() {
+            Some(hdr_format) if hdr => hdr_format,
+            _ => swapchain_support.format(),
+        };
         let extent = swapchain_support.adjust_extent(size);
 
+        // screenshots copy swapchain image before present
+        let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | (swapchain_support.capabilities.supported_usage_flags & vk::ImageUsageFlags::TRANSFER_SRC);
+
         let queue_family_indices = vec![];
         let swapchain_ci = vk::SwapchainCreateInfoKHR {
             s_type: vk::StructureType::SWAPCHAIN_CREATE_INFO_KHR,
@@ -39,7 +53,7 @@ impl SwapChain {
             image_color_space: swapchain_format.color_space,
             image_format: swapchain_format.format,
             image_extent: extent,
-            image_usage: vk::ImageUsageFlags::COLOR_ATTACHMENT,
+            image_usage,
             image_sharing_mode: vk::SharingMode::EXCLUSIVE,
             p_queue_family_indices: queue_family_indices.as_ptr(),
             queue_family_index_count: 0,
@@ -92,13 +106,60 @@ impl SwapChain {
             swapchain_api,
             swapchain,
             format: swapchain_format.format,
+            color_space: swapchain_format.color_space,
             size: extent,
             images: swapchain_images,
             image_views,
             framebuffers: vec![],
+            supports_readback: image_usage.contains(vk::ImageUsageFlags::TRANSFER_SRC),
+            image_memories: vec![],
         }
     }
 
+    // Single owned image in place of swapchain images for headless env. Final pass should leave it
+    // in TRANSFER_SRC_OPTIMAL for readback with `texture_utils::read_image`.
+    pub fn offscreen(env: &RenderEnv, size: vk::Extent2D, format: vk::Format) -> SwapChain {
+        let (image, image_memory) = utils::texture_utils::create_image(
+            env.device(),
+            size.width,
+            size.height,
+            1,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            &env.mem_properties,
+            Some((env.debug_names(), "offscreen_swapchain.image")),
+        );
+        let image_view = utils::texture_utils::create_image_view(env.device(), image, format, vk::ImageAspectFlags::COLOR, 1, 1,
+                                                                 Some((env.debug_names(), "offscreen_swapchain.view")));
+
+        SwapChain {
+            device: env.device().clone(),
+            swapchain_api: ash::extensions::khr::Swapchain::new(env.instance(), env.device()),
+            swapchain: vk::SwapchainKHR::null(),
+            format,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            size,
+            images: vec![image],
+            image_views: vec![image_view],
+            framebuffers: vec![],
+            supports_readback: true,
+            image_memories: vec![image_memory],
+        }
+    }
+
+    // Linear output above 1.0 is shown on HDR display
+    pub fn is_hdr(&self) -> bool {
+        self.color_space == vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT
+    }
+
+    pub fn is_offscreen(&self) -> bool {
+        self.swapchain == vk::SwapchainKHR::null()
+    }
+
     pub fn destroy(&mut self) {
         unsafe {
             for &framebuffer in self.framebuffers.iter() {
@@ -109,7 +170,14 @@ impl SwapChain {
                 self.device.destroy_image_view(img_view, None);
             }
 
-            self.swapchain_api.destroy_swapchain(self.swapchain, None);
+            if self.is_offscreen() {
+                for (&image, &memory) in self.images.iter().zip(self.image_memories.iter()) {
+                    self.device.destroy_image(image, None);
+                    self.device.free_memory(memory, None);
+                }
+            } else {
+                self.swapchain_api.destroy_swapchain(self.swapchain, None);
+            }
         }
     }
 
@@ -3,6 +3,8 @@ use std::ptr;
 use ash::version::{DeviceV1_0, InstanceV1_0};
 use ash::vk;
 
+use crate::debug_names::DebugNames;
+
 pub(crate) fn find_memory_type(
     type_filter: u32,
     required_properties: vk::MemoryPropertyFlags,
@@ -20,12 +22,14 @@ pub(crate) fn find_memory_type(
 }
 
 
+// Buffer and memory are named "<name>" and "<name>.memory" when `debug_name` is set
 pub fn create_buffer_(
     device: &ash::Device,
     size: vk::DeviceSize,
     usage: vk::BufferUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
     device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    debug_name: Option<(&DebugNames, &str)>,
 ) -> (vk::Buffer, vk::DeviceMemory, u64) {
     let buffer_create_info = vk::BufferCreateInfo {
         s_type: vk::StructureType::BUFFER_CREATE_INFO,
@@ -70,6 +74,11 @@ pub fn create_buffer_(
             .expect("Failed to bind Buffer");
     }
 
+    if let Some((names, name)) = debug_name {
+        names.set_name(buffer, name);
+        names.set_name(buffer_memory, &format!("{}.memory", name));
+    }
+
     (buffer, buffer_memory, mem_requirements.size as u64)
 }
 
@@ -79,8 +88,9 @@ pub fn create_buffer(
     usage: vk::BufferUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
     device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    debug_name: Option<(&DebugNames, &str)>,
 ) -> (vk::Buffer, vk::DeviceMemory) {
-    let (buffer, buffer_memory, _) = create_buffer_(device, size, usage, required_memory_properties, device_memory_properties);
+    let (buffer, buffer_memory, _) = create_buffer_(device, size, usage, required_memory_properties, device_memory_properties, debug_name);
 
     (buffer, buffer_memory)
 }
@@ -181,6 +191,7 @@ fn copy_buffer(
     end_single_time_command(device, command_pool, submit_queue, command_buffer);
 }
 
+// Device local buffer filled through staging buffer, `debug_name` names the device local buffer
 pub fn create_data_buffer<T: Sized>(
     instance: &ash::Instance,
     physical_device: vk::PhysicalDevice,
@@ -188,7 +199,8 @@ pub fn create_data_buffer<T: Sized>(
     command_pool: vk::CommandPool,
     submit_queue: vk::Queue,
     usage: vk::BufferUsageFlags,
-    data: Vec<T>) -> (vk::Buffer, vk::DeviceMemory)
+    data: Vec<T>,
+    debug_name: Option<(&DebugNames, &str)>) -> (vk::Buffer, vk::DeviceMemory)
 {
     let mem_properties =
         unsafe { instance.get_physical_device_memory_properties(physical_device) };
@@ -200,6 +212,7 @@ pub fn create_data_buffer<T: Sized>(
         vk::BufferUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
         &mem_properties,
+        None,
     );
 
     unsafe {
@@ -222,7 +235,8 @@ pub fn create_data_buffer<T: Sized>(
         data_size,
         vk::BufferUsageFlags::TRANSFER_DST | usage,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        &mem_properties);
+        &mem_properties,
+        debug_name);
 
     copy_buffer(
         &device,
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Minimal OpenEXR writer: single part scanline image, uncompressed 32-bit float RGBA.
+// Enough for HDR screenshots, no reader.
+
+const MAGIC: u32 = 20000630;
+const VERSION: u32 = 2;
+const PIXEL_TYPE_FLOAT: i32 = 2;
+
+pub fn save_exr(path: &Path, image: &image::ImageBuffer<image::Rgba<f32>, Vec<f32>>) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_exr(&mut writer, image.width(), image.height(), image.as_raw())?;
+    writer.flush()
+}
+
+// `pixels` is RGBA, rows from top to bottom
+pub fn write_exr<W: Write>(writer: &mut W, width: u32, height: u32, pixels: &[f32]) -> std::io::Result<()> {
+    assert_eq!(pixels.len(), (width * height * 4) as usize, "Pixel count doesn't match image size");
+
+    let mut header = vec![];
+    header.extend_from_slice(&MAGIC.to_le_bytes());
+    header.extend_from_slice(&VERSION.to_le_bytes());
+
+    // channels are stored in alphabetical order
+    let mut channels = vec![];
+    for name in ["A", "B", "G", "R"].iter() {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&PIXEL_TYPE_FLOAT.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear + reserved
+        channels.extend_from_slice(&1i32.to_le_bytes()); // x sampling
+        channels.extend_from_slice(&1i32.to_le_bytes()); // y sampling
+    }
+    channels.push(0);
+    write_attribute(&mut header, "channels", "chlist", &channels);
+
+    write_attribute(&mut header, "compression", "compression", &[0]);
+
+    let mut window = vec![];
+    for value in [0, 0, width as i32 - 1, height as i32 - 1].iter() {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    write_attribute(&mut header, "dataWindow", "box2i", &window);
+    write_attribute(&mut header, "displayWindow", "box2i", &window);
+
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0]); // increasing y
+    write_attribute(&mut header, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0u8; 8]);
+    write_attribute(&mut header, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    header.push(0);
+
+    writer.write_all(&header)?;
+
+    // offset table: one scanline per chunk
+    let row_size = width as u64 * 4 * 4;
+    let first_chunk = header.len() as u64 + height as u64 * 8;
+    for y in 0..height as u64 {
+        writer.write_all(&(first_chunk + y * (8 + row_size)).to_le_bytes())?;
+    }
+
+    let mut row = Vec::with_capacity(row_size as usize);
+    for y in 0..height {
+        row.clear();
+        let row_pixels = &pixels[(y * width * 4) as usize..((y + 1) * width * 4) as usize];
+        // A, B, G, R
+        for channel in [3, 2, 1, 0].iter() {
+            for pixel in row_pixels.chunks_exact(4) {
+                row.extend_from_slice(&pixel[*channel].to_le_bytes());
+            }
+        }
+
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&(row_size as i32).to_le_bytes())?;
+        writer.write_all(&row)?;
+    }
+
+    Ok(())
+}
+
+fn write_attribute(header: &mut Vec<u8>, name: &str, type_name: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(type_name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    fn i32_at(bytes: &[u8], offset: usize) -> i32 {
+        i32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+    }
+
+    fn c_str(bytes: &[u8], offset: usize) -> (String, usize) {
+        let len = bytes[offset..].iter().position(|&b| b == 0).unwrap();
+        (String::from_utf8(bytes[offset..offset + len].to_vec()).unwrap(), offset + len + 1)
+    }
+
+    // (name, type, value) of every attribute and offset of the byte after the header
+    fn parse_header(bytes: &[u8]) -> (Vec<(String, String, Vec<u8>)>, usize) {
+        let mut attributes = vec![];
+        let mut offset = 8;
+        while bytes[offset] != 0 {
+            let (name, next) = c_str(bytes, offset);
+            let (type_name, next) = c_str(bytes, next);
+            let size = i32_at(bytes, next) as usize;
+            attributes.push((name, type_name, bytes[next + 4..next + 4 + size].to_vec()));
+            offset = next + 4 + size;
+        }
+
+        (attributes, offset + 1)
+    }
+
+    fn write_test_image() -> Vec<u8> {
+        // 2 x 2, pixel value encodes x, y and channel
+        let mut pixels = vec![];
+        for y in 0..2 {
+            for x in 0..2 {
+                for channel in 0..4 {
+                    pixels.push((y * 100 + x * 10 + channel) as f32);
+                }
+            }
+        }
+
+        let mut bytes = vec![];
+        write_exr(&mut bytes, 2, 2, &pixels).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn header_layout() {
+        let bytes = write_test_image();
+        assert_eq!(i32_at(&bytes, 0) as u32, MAGIC);
+        assert_eq!(i32_at(&bytes, 4) as u32, VERSION);
+
+        let (attributes, _) = parse_header(&bytes);
+        let names: Vec<&str> = attributes.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, ["channels", "compression", "dataWindow", "displayWindow", "lineOrder",
+                           "pixelAspectRatio", "screenWindowCenter", "screenWindowWidth"]);
+
+        let attribute = |name: &str| attributes.iter().find(|attribute| attribute.0 == name).unwrap();
+        assert_eq!(attribute("compression").2, vec![0]);
+        assert_eq!(attribute("lineOrder").2, vec![0]);
+
+        let (_, type_name, window) = attribute("dataWindow");
+        assert_eq!(type_name, "box2i");
+        let window: Vec<i32> = (0..4).map(|idx| i32_at(window, idx * 4)).collect();
+        assert_eq!(window, [0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn channels_are_sorted_float() {
+        let bytes = write_test_image();
+        let (attributes, _) = parse_header(&bytes);
+        let (_, type_name, channels) = attributes.iter().find(|attribute| attribute.0 == "channels").unwrap();
+        assert_eq!(type_name, "chlist");
+
+        let mut names = vec![];
+        let mut offset = 0;
+        while channels[offset] != 0 {
+            let (name, next) = c_str(channels, offset);
+            assert_eq!(i32_at(channels, next), PIXEL_TYPE_FLOAT);
+            // x and y sampling
+            assert_eq!((i32_at(channels, next + 8), i32_at(channels, next + 12)), (1, 1));
+            names.push(name);
+            offset = next + 16;
+        }
+
+        assert_eq!(names, ["A", "B", "G", "R"]);
+        assert_eq!(offset + 1, channels.len());
+    }
+
+    #[test]
+    fn scanlines_store_channels_in_header_order() {
+        let bytes = write_test_image();
+        let (_, header_size) = parse_header(&bytes);
+        let row_size = 2 * 4 * 4;
+
+        // offset table points to consecutive scanline chunks after the table
+        let chunk_offsets: Vec<usize> = (0..2)
+            .map(|y| u64::from_le_bytes(bytes[header_size + y * 8..header_size + y * 8 + 8].try_into().unwrap()) as usize)
+            .collect();
+        assert_eq!(chunk_offsets, [header_size + 16, header_size + 16 + 8 + row_size]);
+        assert_eq!(bytes.len(), chunk_offsets[1] + 8 + row_size);
+
+        for (y, &offset) in chunk_offsets.iter().enumerate() {
+            assert_eq!(i32_at(&bytes, offset), y as i32);
+            assert_eq!(i32_at(&bytes, offset + 4), row_size as i32);
+
+            let values: Vec<f32> = bytes[offset + 8..offset + 8 + row_size]
+                .chunks_exact(4)
+                .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                .collect();
+            // A, B, G, R of both pixels
+            let expected: Vec<f32> = [3, 2, 1, 0].iter()
+                .flat_map(|channel| (0..2).map(move |x| (y * 100 + x * 10 + channel) as f32))
+                .collect();
+            assert_eq!(values, expected);
+        }
+    }
+}
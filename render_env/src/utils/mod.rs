@@ -2,5 +2,6 @@ pub mod texture;
 pub mod texture_utils;
 pub mod utils;
 pub mod buffer_utils;
+pub mod exr;
 
 pub use utils::*;
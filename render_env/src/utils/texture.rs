@@ -4,6 +4,7 @@ use ash::version::DeviceV1_0;
 use ash::vk;
 use image::GenericImageView;
 
+use crate::debug_names::DebugNames;
 use crate::utils::texture_utils::{create_image_view, create_texture_image, create_texture_sampler, create_texture_sampler2};
 
 
@@ -26,6 +27,7 @@ impl Texture {
         submit_queue: vk::Queue,
         device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
         image_path: &Path,
+        debug_name: Option<(&DebugNames, &str)>,
     ) -> Texture {
         let mut image_object = image::open(image_path).unwrap();
         image_object = image_object.flipv();
@@ -40,23 +42,31 @@ impl Texture {
         let (image_width, image_height) = (image_object.width(), image_object.height());
 
         Texture::from_pixels(device, command_pool, submit_queue, device_memory_properties, vk::Format::R8G8B8A8_SRGB,
-                             &image_data, image_width, image_height, true)
+                             &image_data, image_width, image_height, true, debug_name)
     }
 
+    // Image, view and sampler are named "<name>", "<name>.view" and "<name>.sampler" when `debug_name` is set
     pub fn from_pixels(device: ash::Device,
                        command_pool: vk::CommandPool,
                        submit_queue: vk::Queue,
                        device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
                        format: vk::Format,
-                       pixel_data: &Vec<u8>, width: u32, height: u32, create_mips: bool) -> Texture
+                       pixel_data: &Vec<u8>, width: u32, height: u32, create_mips: bool,
+                       debug_name: Option<(&DebugNames, &str)>) -> Texture
     {
         let (texture_image, texture_image_memory, mip_levels) = create_texture_image(
-            &device, command_pool, submit_queue, device_memory_properties, format, pixel_data, width, height, 1, create_mips);
+            &device, command_pool, submit_queue, device_memory_properties, format, pixel_data, width, height, 1, create_mips,
+            debug_name);
 
         let texture_image_view = create_image_view(
             &device, texture_image, format,
-            vk::ImageAspectFlags::COLOR, mip_levels, 1);
-        let texture_sampler = create_texture_sampler2(&device, mip_levels);
+            vk::ImageAspectFlags::COLOR, mip_levels, 1, None);
+        let texture_sampler = create_texture_sampler2(&device, mip_levels, None);
+
+        if let Some((names, name)) = debug_name {
+            names.set_name(texture_image_view, &format!("{}.view", name));
+            names.set_name(texture_sampler, &format!("{}.sampler", name));
+        }
 
         Texture {
             device,
@@ -1,3 +1,9 @@
+use crate::compute_pipeline::ComputePipeline;
+use crate::debug_names::DebugNames;
+use crate::descriptor_set::DescriptorSetBuilder;
+use crate::env::RenderEnv;
+use crate::shader::Shader;
+use crate::utils::format_has_depth;
 use ash::vk;
 use crate::utils::buffer_utils;
 use ash::version::DeviceV1_0;
@@ -15,6 +21,7 @@ pub fn create_texture_image(
     image_height: u32,
     array_size: u32,
     create_mips: bool,
+    debug_name: Option<(&DebugNames, &str)>,
 ) -> (vk::Image, vk::DeviceMemory, u32)
 {
     let mem_size = (std::mem::size_of::<u8>() as u32 * 4 * image_width * image_height * array_size) as vk::DeviceSize;
@@ -42,6 +49,7 @@ pub fn create_texture_image(
         vk::BufferUsageFlags::TRANSFER_SRC,
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
         device_memory_properties,
+        None,
     );
 
     unsafe {
@@ -71,6 +79,7 @@ pub fn create_texture_image(
         vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
         device_memory_properties,
+        debug_name,
     );
 
     transition_image_layout(
@@ -254,6 +263,7 @@ fn generate_mipmaps(
     buffer_utils::end_single_time_command(device, command_pool, submit_queue, command_buffer);
 }
 
+// Image and memory are named "<name>" and "<name>.memory" when `debug_name` is set
 pub fn create_image(
     device: &ash::Device,
     width: u32,
@@ -266,6 +276,7 @@ pub fn create_image(
     usage: vk::ImageUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
     device_memory_properties: &vk::PhysicalDeviceMemoryProperties,
+    debug_name: Option<(&DebugNames, &str)>,
 ) -> (vk::Image, vk::DeviceMemory) {
     let flags = if array_size == 6 {
         vk::ImageCreateFlags::CUBE_COMPATIBLE
@@ -326,6 +337,11 @@ pub fn create_image(
             .expect("Failed to bind Image Memmory!");
     }
 
+    if let Some((names, name)) = debug_name {
+        names.set_name(texture_image, name);
+        names.set_name(texture_image_memory, &format!("{}.memory", name));
+    }
+
     (texture_image, texture_image_memory)
 }
 
@@ -461,6 +477,7 @@ pub fn create_image_view(
     aspect_mask: vk::ImageAspectFlags,
     mip_levels: u32,
     layer_count: u32,
+    debug_name: Option<(&DebugNames, &str)>,
 ) -> vk::ImageView {
     let view_type = if layer_count == 6 {
         vk::ImageViewType::CUBE
@@ -489,15 +506,21 @@ pub fn create_image_view(
         image,
     };
 
-    unsafe {
+    let image_view = unsafe {
         device
             .create_image_view(&imageview_create_info, None)
             .expect("Failed to create Image View!")
+    };
+
+    if let Some((names, name)) = debug_name {
+        names.set_name(image_view, name);
     }
+
+    image_view
 }
 
 
-pub fn create_texture_sampler(device: &ash::Device, mip_levels: u32) -> vk::Sampler {
+pub fn create_texture_sampler(device: &ash::Device, mip_levels: u32, debug_name: Option<(&DebugNames, &str)>) -> vk::Sampler {
     let sampler_create_info = vk::SamplerCreateInfo {
         s_type: vk::StructureType::SAMPLER_CREATE_INFO,
         p_next: ptr::null(),
@@ -521,14 +544,20 @@ pub fn create_texture_sampler(device: &ash::Device, mip_levels: u32) -> vk::Samp
         unnormalized_coordinates: vk::FALSE,
     };
 
-    unsafe {
+    let sampler = unsafe {
         device
             .create_sampler(&sampler_create_info, None)
             .expect("Failed to create Sampler!")
+    };
+
+    if let Some((names, name)) = debug_name {
+        names.set_name(sampler, name);
     }
+
+    sampler
 }
 
-pub fn create_texture_sampler2(device: &ash::Device, mip_levels: u32) -> vk::Sampler {
+pub fn create_texture_sampler2(device: &ash::Device, mip_levels: u32, debug_name: Option<(&DebugNames, &str)>) -> vk::Sampler {
     let sampler_create_info = vk::SamplerCreateInfo {
         s_type: vk::StructureType::SAMPLER_CREATE_INFO,
         p_next: ptr::null(),
@@ -552,9 +581,504 @@ pub fn create_texture_sampler2(device: &ash::Device, mip_levels: u32) -> vk::Sam
         unnormalized_coordinates: vk::FALSE,
     };
 
-    unsafe {
+    let sampler = unsafe {
         device
             .create_sampler(&sampler_create_info, None)
             .expect("Failed to create Sampler!")
+    };
+
+    if let Some((names, name)) = debug_name {
+        names.set_name(sampler, name);
+    }
+
+    sampler
+}
+
+// CPU copy of image (level 0, `layer`) in `layout`, image is left in the same layout.
+// Image must be created with TRANSFER_SRC usage. Waits for queue idle.
+//
+// * multisampled color images are resolved, multisampled depth takes sample 0 (image must have SAMPLED usage)
+// * 8-bit formats keep values, BGRA is swizzled to RGBA
+// * float formats are clamped to 0..1 and stored as 16-bit, see `read_image_hdr` for raw values
+// * depth is converted to grayscale normalized by min / max depth of the image
+pub fn read_image(env: &RenderEnv, image: vk::Image, layout: vk::ImageLayout, format: vk::Format,
+                  size: [u32; 2], samples: vk::SampleCountFlags, layer: u32) -> image::DynamicImage {
+    let (bytes, format) = read_image_bytes(env, image, layout, format, size, samples, layer);
+    let [width, height] = size;
+
+    match format {
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => {
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, bytes).unwrap())
+        }
+        vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => {
+            let mut bytes = bytes;
+            for pixel in bytes.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, bytes).unwrap())
+        }
+        vk::Format::R8_UNORM => {
+            image::DynamicImage::ImageLuma8(image::GrayImage::from_raw(width, height, bytes).unwrap())
+        }
+        _ if format_has_depth(format) => {
+            let depth: Vec<f32> = decode_texels(format, &bytes).iter().map(|texel| texel[0]).collect();
+            let min = depth.iter().cloned().fold(f32::INFINITY, f32::min);
+            let max = depth.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+            let range = if max > min { max - min } else { 1.0 };
+
+            let gray = depth.iter().map(|d| to_unorm16((d - min) / range)).collect();
+            image::DynamicImage::ImageLuma16(image::ImageBuffer::from_raw(width, height, gray).unwrap())
+        }
+        _ => {
+            let rgba = decode_texels(format, &bytes).iter().flat_map(|texel| texel.iter().map(|&v| to_unorm16(v))).collect();
+            image::DynamicImage::ImageRgba16(image::ImageBuffer::from_raw(width, height, rgba).unwrap())
+        }
+    }
+}
+
+// Same as `read_image`, but with unclamped float values (HDR buffers, positions, raw depth)
+pub fn read_image_hdr(env: &RenderEnv, image: vk::Image, layout: vk::ImageLayout, format: vk::Format,
+                      size: [u32; 2], samples: vk::SampleCountFlags, layer: u32) -> image::ImageBuffer<image::Rgba<f32>, Vec<f32>> {
+    let (bytes, format) = read_image_bytes(env, image, layout, format, size, samples, layer);
+    let rgba = decode_texels(format, &bytes).iter().flat_map(|texel| texel.iter().cloned()).collect();
+
+    image::ImageBuffer::from_raw(size[0], size[1], rgba).unwrap()
+}
+
+fn to_unorm16(value: f32) -> u16 {
+    (value.max(0.0).min(1.0) * 65535.0 + 0.5) as u16
+}
+
+// Formats `read_image` and `read_image_hdr` can decode
+pub fn is_readback_supported(format: vk::Format) -> bool {
+    texel_size(format).is_some()
+}
+
+// Bytes per texel of copied aspect (depth only for depth / stencil formats)
+fn texel_size(format: vk::Format) -> Option<u32> {
+    match format {
+        vk::Format::R8_UNORM => Some(1),
+        vk::Format::R16_SFLOAT | vk::Format::D16_UNORM | vk::Format::D16_UNORM_S8_UINT => Some(2),
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::R16G16_SFLOAT | vk::Format::R32_SFLOAT
+        | vk::Format::D32_SFLOAT | vk::Format::D32_SFLOAT_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT | vk::Format::X8_D24_UNORM_PACK32 => Some(4),
+        vk::Format::R16G16B16A16_SFLOAT => Some(8),
+        vk::Format::R32G32B32A32_SFLOAT => Some(16),
+        _ => None,
+    }
+}
+
+fn readback_texel_size(format: vk::Format) -> u32 {
+    texel_size(format).unwrap_or_else(|| panic!("Unsupported readback format {:?}!", format))
+}
+
+// RGBA values of texels, depth goes to RGB with alpha 1
+fn decode_texels(format: vk::Format, bytes: &[u8]) -> Vec<[f32; 4]> {
+    let u16_at = |chunk: &[u8], idx: usize| u16::from_le_bytes([chunk[idx * 2], chunk[idx * 2 + 1]]);
+    let f32_at = |chunk: &[u8], idx: usize| f32::from_le_bytes([chunk[idx * 4], chunk[idx * 4 + 1], chunk[idx * 4 + 2], chunk[idx * 4 + 3]]);
+    let gray = |v: f32| [v, v, v, 1.0];
+
+    bytes
+        .chunks_exact(readback_texel_size(format) as usize)
+        .map(|texel| match format {
+            vk::Format::R8_UNORM => gray(texel[0] as f32 / 255.0),
+            vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => {
+                [texel[0] as f32 / 255.0, texel[1] as f32 / 255.0, texel[2] as f32 / 255.0, texel[3] as f32 / 255.0]
+            }
+            vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => {
+                [texel[2] as f32 / 255.0, texel[1] as f32 / 255.0, texel[0] as f32 / 255.0, texel[3] as f32 / 255.0]
+            }
+            vk::Format::R16_SFLOAT => [half_to_f32(u16_at(texel, 0)), 0.0, 0.0, 1.0],
+            vk::Format::R16G16_SFLOAT => [half_to_f32(u16_at(texel, 0)), half_to_f32(u16_at(texel, 1)), 0.0, 1.0],
+            vk::Format::R16G16B16A16_SFLOAT => {
+                [half_to_f32(u16_at(texel, 0)), half_to_f32(u16_at(texel, 1)), half_to_f32(u16_at(texel, 2)), half_to_f32(u16_at(texel, 3))]
+            }
+            vk::Format::R32_SFLOAT => [f32_at(texel, 0), 0.0, 0.0, 1.0],
+            vk::Format::R32G32B32A32_SFLOAT => [f32_at(texel, 0), f32_at(texel, 1), f32_at(texel, 2), f32_at(texel, 3)],
+            vk::Format::D16_UNORM | vk::Format::D16_UNORM_S8_UINT => gray(u16_at(texel, 0) as f32 / 65535.0),
+            vk::Format::D32_SFLOAT | vk::Format::D32_SFLOAT_S8_UINT => gray(f32_at(texel, 0)),
+            // depth in low 24 bits
+            _ => gray((u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]) & 0x00ff_ffff) as f32 / 16_777_215.0),
+        })
+        .collect()
+}
+
+// IEEE 754 half precision to single precision
+pub fn half_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            // subnormal: normalize mantissa
+            let mut exponent = 127 - 15 + 1;
+            let mut mantissa = mantissa;
+            while mantissa & 0x400 == 0 {
+                mantissa <<= 1;
+                exponent -= 1;
+            }
+            sign | (exponent << 23) | ((mantissa & 0x3ff) << 13)
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+
+    f32::from_bits(bits)
+}
+
+// All aspects of the format, layout transitions of depth / stencil images need both
+fn barrier_aspect(format: vk::Format) -> vk::ImageAspectFlags {
+    if [vk::Format::D16_UNORM_S8_UINT, vk::Format::D24_UNORM_S8_UINT, vk::Format::D32_SFLOAT_S8_UINT].contains(&format) {
+        vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+    } else if format_has_depth(format) {
+        vk::ImageAspectFlags::DEPTH
+    } else {
+        vk::ImageAspectFlags::COLOR
+    }
+}
+
+// Raw texels of copied aspect, tightly packed, and their format (D32_SFLOAT for resolved multisampled depth)
+fn read_image_bytes(env: &RenderEnv, image: vk::Image, layout: vk::ImageLayout, format: vk::Format,
+                    size: [u32; 2], samples: vk::SampleCountFlags, layer: u32) -> (Vec<u8>, vk::Format) {
+    assert_ne!(layout, vk::ImageLayout::UNDEFINED, "Image content is undefined!");
+
+    let is_depth = format_has_depth(format);
+    assert_ne!(format, vk::Format::S8_UINT, "Stencil readback is not supported!");
+    // vkCmdResolveImage works with color images only
+    if is_depth && samples != vk::SampleCountFlags::TYPE_1 {
+        return (resolve_depth(env, image, layout, format, size, layer), vk::Format::D32_SFLOAT);
+    }
+
+    let barrier_aspect = barrier_aspect(format);
+    let copy_aspect = if is_depth { vk::ImageAspectFlags::DEPTH } else { vk::ImageAspectFlags::COLOR };
+
+    let device = env.device();
+    let buffer_size = (size[0] * size[1] * readback_texel_size(format)) as vk::DeviceSize;
+    let (buffer, buffer_memory) = buffer_utils::create_buffer(
+        device,
+        buffer_size,
+        vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        &env.mem_properties,
+        Some((env.debug_names(), "readback.buffer")),
+    );
+
+    // single sampled copy of multisampled image
+    let resolved = if samples != vk::SampleCountFlags::TYPE_1 {
+        Some(create_image(
+            device,
+            size[0],
+            size[1],
+            1,
+            1,
+            vk::SampleCountFlags::TYPE_1,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::TRANSFER_SRC,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            &env.mem_properties,
+            Some((env.debug_names(), "readback.resolved")),
+        ))
+    } else {
+        None
+    };
+
+    let command_buffer = buffer_utils::begin_single_time_command(device, env.command_pool());
+
+    let barrier = |image, layer, old_layout, new_layout, src_access_mask, dst_access_mask| vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: barrier_aspect,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: layer,
+            layer_count: 1,
+        },
+    };
+    let subresource = |layer| vk::ImageSubresourceLayers {
+        aspect_mask: copy_aspect,
+        mip_level: 0,
+        base_array_layer: layer,
+        layer_count: 1,
+    };
+    let extent = vk::Extent3D { width: size[0], height: size[1], depth: 1 };
+
+    unsafe {
+        // everything written before (render passes, compute) is visible to the transfer
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(image, layer, layout, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::AccessFlags::MEMORY_WRITE, vk::AccessFlags::TRANSFER_READ)],
+        );
+
+        let (copy_image, copy_layer) = match resolved {
+            Some((resolved_image, _)) => {
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(resolved_image, 0, vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE)],
+                );
+
+                let region = vk::ImageResolve {
+                    src_subresource: subresource(layer),
+                    src_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                    dst_subresource: subresource(0),
+                    dst_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+                    extent,
+                };
+                device.cmd_resolve_image(command_buffer, image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                                         resolved_image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &[region]);
+
+                device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[barrier(resolved_image, 0, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::TRANSFER_READ)],
+                );
+
+                (resolved_image, 0)
+            }
+            None => (image, layer),
+        };
+
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: subresource(copy_layer),
+            image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+            image_extent: extent,
+        };
+        device.cmd_copy_image_to_buffer(command_buffer, copy_image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, buffer, &[region]);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(image, layer, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, layout, vk::AccessFlags::TRANSFER_READ, vk::AccessFlags::MEMORY_READ)],
+        );
+    }
+
+    buffer_utils::end_single_time_command(device, env.command_pool(), env.queue(), command_buffer);
+
+    let bytes = read_host_buffer(device, buffer, buffer_memory, buffer_size);
+    if let Some((resolved_image, resolved_memory)) = resolved {
+        unsafe {
+            device.destroy_image(resolved_image, None);
+            device.free_memory(resolved_memory, None);
+        }
+    }
+
+    (bytes, format)
+}
+
+// Depth of sample 0 of multisampled depth image (`layer`) as tightly packed f32, image is left in `layout`.
+// Compute shader reads depth in DEPTH_STENCIL_READ_ONLY_OPTIMAL layout.
+fn resolve_depth(env: &RenderEnv, image: vk::Image, layout: vk::ImageLayout, format: vk::Format,
+                 size: [u32; 2], layer: u32) -> Vec<u8> {
+    let device = env.device();
+    let pipeline = ComputePipeline::new(
+        device.clone(),
+        Shader::load(device, "assets/shaders/spv/utils/depth_resolve.comp.spv"),
+    ).debug_name(env, "readback.depth_resolve");
+
+    let buffer_size = (size[0] * size[1] * 4) as vk::DeviceSize;
+    let (buffer, buffer_memory) = buffer_utils::create_buffer(
+        device,
+        buffer_size,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        &env.mem_properties,
+        Some((env.debug_names(), "readback.buffer")),
+    );
+
+    let view_create_info = vk::ImageViewCreateInfo::builder()
+        .image(image)
+        .view_type(vk::ImageViewType::TYPE_2D)
+        .format(format)
+        .subresource_range(vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::DEPTH,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: layer,
+            layer_count: 1,
+        });
+    let sampler_create_info = vk::SamplerCreateInfo::builder()
+        .min_filter(vk::Filter::NEAREST)
+        .mag_filter(vk::Filter::NEAREST)
+        .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+        .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+    let (view, sampler) = unsafe {
+        (
+            device.create_image_view(&view_create_info, None).expect("Failed to create Image View!"),
+            device.create_sampler(&sampler_create_info, None).expect("Failed to create Sampler!"),
+        )
+    };
+    env.set_name(view, "readback.depth_view");
+
+    let descriptor_set = DescriptorSetBuilder::new(device, &pipeline.descriptor_set_layouts[0])
+        .add_image_with_layout(view, sampler, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL)
+        .add_buffer(buffer)
+        .build();
+
+    let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| vk::ImageMemoryBarrier {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask,
+        dst_access_mask,
+        old_layout,
+        new_layout,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image,
+        subresource_range: vk::ImageSubresourceRange {
+            aspect_mask: barrier_aspect(format),
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: layer,
+            layer_count: 1,
+        },
+    };
+    let buffer_barrier = vk::BufferMemoryBarrier {
+        s_type: vk::StructureType::BUFFER_MEMORY_BARRIER,
+        p_next: ptr::null(),
+        src_access_mask: vk::AccessFlags::SHADER_WRITE,
+        dst_access_mask: vk::AccessFlags::HOST_READ,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        buffer,
+        offset: 0,
+        size: vk::WHOLE_SIZE,
+    };
+
+    let command_buffer = buffer_utils::begin_single_time_command(device, env.command_pool());
+    unsafe {
+        // everything written before (render passes, compute) is visible to the shader
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[barrier(layout, vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL, vk::AccessFlags::MEMORY_WRITE, vk::AccessFlags::SHADER_READ)],
+        );
+
+        device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline.pipeline);
+        device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline.pipeline_layout,
+                                        0, &[descriptor_set.set], &[]);
+        device.cmd_dispatch(command_buffer, (size[0] + 15) / 16, (size[1] + 15) / 16, 1);
+
+        device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::ALL_COMMANDS | vk::PipelineStageFlags::HOST,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[buffer_barrier],
+            &[barrier(vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL, layout, vk::AccessFlags::SHADER_READ, vk::AccessFlags::MEMORY_READ)],
+        );
+    }
+    buffer_utils::end_single_time_command(device, env.command_pool(), env.queue(), command_buffer);
+
+    unsafe {
+        device.destroy_sampler(sampler, None);
+        device.destroy_image_view(view, None);
+    }
+
+    read_host_buffer(device, buffer, buffer_memory, buffer_size)
+}
+
+// Copies and destroys host visible buffer
+fn read_host_buffer(device: &ash::Device, buffer: vk::Buffer, buffer_memory: vk::DeviceMemory, size: vk::DeviceSize) -> Vec<u8> {
+    let mut bytes = vec![0u8; size as usize];
+    unsafe {
+        let data_ptr = device
+            .map_memory(buffer_memory, 0, size, vk::MemoryMapFlags::empty())
+            .expect("Failed to Map Memory") as *const u8;
+        data_ptr.copy_to_nonoverlapping(bytes.as_mut_ptr(), bytes.len());
+        device.unmap_memory(buffer_memory);
+
+        device.destroy_buffer(buffer, None);
+        device.free_memory(buffer_memory, None);
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_to_f32_zero_and_one() {
+        assert_eq!(half_to_f32(0x0000).to_bits(), 0.0f32.to_bits());
+        assert_eq!(half_to_f32(0x8000).to_bits(), (-0.0f32).to_bits());
+        assert_eq!(half_to_f32(0x3c00), 1.0);
+        assert_eq!(half_to_f32(0xbc00), -1.0);
+        assert_eq!(half_to_f32(0x3800), 0.5);
+        assert_eq!(half_to_f32(0x3555), 0.333_251_95);
+        // largest finite half
+        assert_eq!(half_to_f32(0x7bff), 65504.0);
+    }
+
+    #[test]
+    fn half_to_f32_subnormal() {
+        let min_subnormal = 2.0f32.powi(-24);
+        assert_eq!(half_to_f32(0x0001), min_subnormal);
+        assert_eq!(half_to_f32(0x8001), -min_subnormal);
+        assert_eq!(half_to_f32(0x0200), 2.0f32.powi(-15));
+        assert_eq!(half_to_f32(0x03ff), 1023.0 * min_subnormal);
+        // smallest normal follows the largest subnormal
+        assert_eq!(half_to_f32(0x0400), 2.0f32.powi(-14));
+    }
+
+    #[test]
+    fn half_to_f32_inf_and_nan() {
+        assert_eq!(half_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(half_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(half_to_f32(0x7e00).is_nan());
+        assert!(half_to_f32(0xfc01).is_nan());
+    }
+
+    #[test]
+    fn decode_depth_texels() {
+        let d16 = decode_texels(vk::Format::D16_UNORM, &[0x00, 0x00, 0xff, 0xff]);
+        assert_eq!(d16, vec![[0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]]);
+
+        // stencil in the high byte is ignored
+        let d24 = decode_texels(vk::Format::D24_UNORM_S8_UINT, &[0xff, 0xff, 0xff, 0x12]);
+        assert_eq!(d24, vec![[1.0, 1.0, 1.0, 1.0]]);
+
+        let d32 = decode_texels(vk::Format::D32_SFLOAT, &0.25f32.to_le_bytes());
+        assert_eq!(d32, vec![[0.25, 0.25, 0.25, 1.0]]);
     }
 }
@@ -80,6 +80,16 @@ impl SwapChainSupportDetail {
         return self.formats.first().unwrap().clone();
     }
 
+    // Linear extended sRGB (scRGB) float format, values above 1.0 are brighter than SDR white.
+    // Listed only with VK_EXT_swapchain_colorspace and HDR capable display.
+    pub fn hdr_format(&self) -> Option<vk::SurfaceFormatKHR> {
+        self.formats
+            .iter()
+            .find(|format| format.format == vk::Format::R16G16B16A16_SFLOAT
+                && format.color_space == vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT)
+            .cloned()
+    }
+
     pub fn present_mode(&self) -> vk::PresentModeKHR {
         if self.present_modes.contains(&vk::PresentModeKHR::MAILBOX) {
             return vk::PresentModeKHR::MAILBOX;